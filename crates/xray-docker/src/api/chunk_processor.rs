@@ -52,6 +52,11 @@ impl ChunkProcessor {
         Ok(should_continue)
     }
 
+    /// Returns `true` if the zero-length chunk was encountered, i.e. the whole body was processed.
+    pub fn is_done(&self) -> bool {
+        self.state.is_done()
+    }
+
     /// Skips the stored in this instance number of bytes (if any).
     ///
     /// Returns the updated buffer without the skipped bytes.
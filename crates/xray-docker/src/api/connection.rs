@@ -1,13 +1,19 @@
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
 #[cfg(unix)]
 use std::os::unix::net::UnixStream;
+use std::sync::Arc;
 
 use http::StatusCode;
 use httparse::{EMPTY_HEADER, Status};
 #[cfg(windows)]
 use interprocess::os::windows::named_pipe::{DuplexPipeStream, pipe_mode};
+use rustls::pki_types::ServerName;
+use rustls::{ClientConnection, StreamOwned};
 
 use super::chunk_processor::ChunkProcessor;
+use super::docker_host::TlsOptions;
+use super::tls::client_config;
 use crate::{DockerError, Result};
 
 trait ReadWrite: Read + Write {}
@@ -19,6 +25,10 @@ pub enum DockerApiConnection {
     Unix(UnixStream),
     #[cfg(windows)]
     Windows(DuplexPipeStream<pipe_mode::Bytes>),
+    Tcp(TcpStream),
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+    #[cfg(test)]
+    Mock(tests::MockSocket),
 }
 
 enum BodyParsingMode {
@@ -28,15 +38,104 @@ enum BodyParsingMode {
 
 impl DockerApiConnection {
     const DEFAULT_BUF_SIZE: usize = 8_192;
+    const TCP_HOST_PREFIX: &str = "tcp://";
+    /// Docker uses different default ports for plain and TLS connections.
+    const DEFAULT_TCP_PORT: u16 = 2375;
+    const DEFAULT_TLS_PORT: u16 = 2376;
+
+    /// Connects to the provided Docker host.
+    ///
+    /// [TlsOptions] are only used for TCP hosts (`tcp://host[:port]`), as local sockets don't support TLS.
+    pub fn connect<R: AsRef<str>>(
+        docker_host: R,
+        tls: Option<&TlsOptions>,
+    ) -> Result<Self> {
+        let docker_host = docker_host.as_ref();
+        if let Some(addr) = docker_host.strip_prefix(Self::TCP_HOST_PREFIX) {
+            return Self::connect_tcp(addr, tls);
+        }
+        if let Some((scheme, _)) = docker_host.split_once("://")
+            && !matches!(scheme, "unix" | "npipe")
+        {
+            return Err(DockerError::Other(
+                format!("unsupported Docker host scheme: '{scheme}'").into(),
+            ));
+        }
+
+        Self::connect_local(docker_host)
+    }
+
+    /// Connects to a TCP Docker host at the provided address, using TLS if [TlsOptions] are provided.
+    fn connect_tcp(addr: &str, tls: Option<&TlsOptions>) -> Result<Self> {
+        let (addr, host, port) = Self::resolve_tcp_addr(addr, tls.is_some())?;
+        let conn = TcpStream::connect((host, port)).map_err(|e| {
+            DockerError::from_io_error_with_description(e, || {
+                format!("failed to connect to the Docker host at '{addr}'")
+                    .into()
+            })
+        })?;
+
+        let Some(tls) = tls else {
+            return Ok(DockerApiConnection::Tcp(conn));
+        };
+
+        let server_name =
+            ServerName::try_from(host.to_owned()).map_err(|_| {
+                DockerError::Other(
+                    format!("invalid Docker host name: '{host}'").into(),
+                )
+            })?;
+        let tls_conn =
+            ClientConnection::new(Arc::new(client_config(tls)?), server_name)
+                .map_err(|e| {
+                DockerError::from_tls_error_with_description(e, || {
+                    "failed to create a TLS connection".into()
+                })
+            })?;
+        let mut stream = StreamOwned::new(tls_conn, conn);
+        // Finish the handshake right away to report TLS errors before sending any requests
+        while stream.conn.is_handshaking() {
+            stream.conn.complete_io(&mut stream.sock).map_err(|e| {
+                DockerError::from_io_error_with_description(e, || {
+                    "failed to perform a TLS handshake with the Docker host"
+                        .into()
+                })
+            })?;
+        }
+
+        Ok(DockerApiConnection::Tls(Box::new(stream)))
+    }
+
+    /// Splits the address of a TCP Docker host into the address without the trailing path, the host, and the port.
+    ///
+    /// The port defaults to the one Docker uses for TLS connections if `use_tls` is `true`.
+    fn resolve_tcp_addr(
+        addr: &str,
+        use_tls: bool,
+    ) -> Result<(&str, &str, u16)> {
+        // Docker hosts can have a trailing path, which we don't need
+        let addr = addr.split_once('/').map_or(addr, |(addr, _)| addr);
+        let (host, port) = split_host_port(addr);
+        let port = match port {
+            Some(port) => port.parse::<u16>().map_err(|_| {
+                DockerError::Other(
+                    format!("invalid port in the Docker host: '{port}'").into(),
+                )
+            })?,
+            None if use_tls => Self::DEFAULT_TLS_PORT,
+            None => Self::DEFAULT_TCP_PORT,
+        };
+
+        Ok((addr, host, port))
+    }
 
     #[cfg(unix)]
-    pub fn connect<R: AsRef<str>>(docker_socket_addr: R) -> Result<Self> {
+    fn connect_local(docker_socket_addr: &str) -> Result<Self> {
         let conn = UnixStream::connect(
             // Strip the unix socket addr prefix if it's present
             docker_socket_addr
-                .as_ref()
                 .strip_prefix("unix://")
-                .unwrap_or(docker_socket_addr.as_ref()),
+                .unwrap_or(docker_socket_addr),
         )
         .map_err(|e| {
             DockerError::from_io_error_with_description(e, || {
@@ -48,13 +147,11 @@ impl DockerApiConnection {
     }
 
     #[cfg(windows)]
-    pub fn connect<R: AsRef<str>>(pipe: R) -> Result<Self> {
+    fn connect_local(pipe: &str) -> Result<Self> {
         let pipe_stream =
             DuplexPipeStream::<pipe_mode::Bytes>::connect_by_path(
                 // Strip the named pipe addr prefix if it's present
-                pipe.as_ref()
-                    .strip_prefix(r"npipe://")
-                    .unwrap_or(pipe.as_ref()),
+                pipe.strip_prefix(r"npipe://").unwrap_or(pipe),
             )
             .map_err(|e| {
                 DockerError::from_io_error_with_description(e, || {
@@ -81,6 +178,43 @@ impl DockerApiConnection {
         Ok(response_code)
     }
 
+    /// Sends an encoded request from the provided buffer and returns a [ResponseBody] that streams the response body.
+    ///
+    /// Unlike [Self::make_request], this doesn't store the whole body in memory, so it should be used for large
+    /// responses. The provided buffer is reused by the returned [ResponseBody].
+    pub fn make_streaming_request<'a>(
+        &'a mut self,
+        buf: &'a mut Vec<u8>,
+    ) -> Result<(StatusCode, ResponseBody<'a>)> {
+        // Send the request
+        self.send_request(buf)?;
+
+        // Extract the response meta like response code and headers that we need to process the body
+        let mut temp_buf = vec![0u8; Self::DEFAULT_BUF_SIZE];
+        let (response_code, parsing_mode) =
+            self.read_response_meta(buf, &mut temp_buf)?;
+
+        let parsing_mode = match parsing_mode {
+            // Track the number of body bytes that are yet to be read from the socket instead
+            BodyParsingMode::FixedLength(body_length) => {
+                BodyParsingMode::FixedLength(
+                    body_length.saturating_sub(buf.len()),
+                )
+            }
+            parsing_mode => parsing_mode,
+        };
+
+        let body = ResponseBody {
+            socket: self.get_socket(),
+            buf,
+            pos: 0,
+            parsing_mode,
+            temp_buf,
+        };
+
+        Ok((response_code, body))
+    }
+
     /// Sends the encoded HTTP request to the underlying socket.
     fn send_request(&mut self, buf: &[u8]) -> Result<()> {
         let socket = self.get_socket();
@@ -241,6 +375,333 @@ impl DockerApiConnection {
             }
             #[cfg(windows)]
             DockerApiConnection::Windows(npipe) => npipe as &mut dyn ReadWrite,
+            DockerApiConnection::Tcp(tcp) => tcp as &mut dyn ReadWrite,
+            DockerApiConnection::Tls(tls) => tls.as_mut() as &mut dyn ReadWrite,
+            #[cfg(test)]
+            DockerApiConnection::Mock(mock) => mock as &mut dyn ReadWrite,
+        }
+    }
+}
+
+/// Splits the provided address into a host and an optional port. IPv6 addresses must be enclosed in brackets.
+fn split_host_port(addr: &str) -> (&str, Option<&str>) {
+    if let Some(addr) = addr.strip_prefix('[')
+        && let Some((host, rest)) = addr.split_once(']')
+    {
+        return (host, rest.strip_prefix(':'));
+    }
+
+    match addr.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (addr, None),
+    }
+}
+
+/// A reader that streams the body of an HTTP response directly from the underlying socket.
+///
+/// Chunked bodies are de-chunked on the fly, so only a small part of the body is stored in memory at any given time.
+///
+/// # Note
+///
+/// The connection can't be used for other requests if the body wasn't read completely.
+pub struct ResponseBody<'a> {
+    socket: &'a mut dyn ReadWrite,
+    /// Body bytes that were received, but were not yet read from this reader.
+    buf: &'a mut Vec<u8>,
+    /// Position of the first unread byte in the buffer.
+    pos: usize,
+    /// Contains the number of remaining bytes instead of the full body length for fixed-length bodies.
+    parsing_mode: BodyParsingMode,
+    temp_buf: Vec<u8>,
+}
+
+impl Read for ResponseBody<'_> {
+    fn read(&mut self, dst: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.buf.len() {
+            self.receive_more_data()?;
+        }
+
+        let available = &self.buf[self.pos..];
+        let read = available.len().min(dst.len());
+        dst[..read].copy_from_slice(&available[..read]);
+        self.pos += read;
+
+        Ok(read)
+    }
+}
+
+impl ResponseBody<'_> {
+    /// Replaces the contents of the buffer with the next part of the body.
+    ///
+    /// The buffer stays empty if the whole body was already received.
+    fn receive_more_data(&mut self) -> io::Result<()> {
+        self.buf.clear();
+        self.pos = 0;
+
+        while self.buf.is_empty() {
+            let is_complete = match &self.parsing_mode {
+                BodyParsingMode::Chunks(chunk_processor) => {
+                    chunk_processor.is_done()
+                }
+                BodyParsingMode::FixedLength(remaining) => *remaining == 0,
+            };
+            if is_complete {
+                break;
+            }
+
+            let filled_bytes = self.socket.read(&mut self.temp_buf)?;
+            if filled_bytes == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the connection was closed before receiving the whole response body",
+                ));
+            }
+
+            let received = &self.temp_buf[..filled_bytes];
+            match &mut self.parsing_mode {
+                BodyParsingMode::Chunks(chunk_processor) => {
+                    chunk_processor
+                        .process_available_data(received, self.buf)
+                        .map_err(io::Error::other)?;
+                }
+                BodyParsingMode::FixedLength(remaining) => {
+                    let received = &received[..filled_bytes.min(*remaining)];
+                    self.buf.extend_from_slice(received);
+                    *remaining -= received.len();
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    const REQUEST: &[u8] =
+        b"GET /images/app/get HTTP/1.1\r\nHost: docker\r\n\r\n";
+
+    /// An in-memory socket that returns at most one of the provided parts of the response per read.
+    #[derive(Default)]
+    pub(crate) struct MockSocket {
+        reads: VecDeque<Vec<u8>>,
+        written: Vec<u8>,
+    }
+
+    impl Read for MockSocket {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some(mut part) = self.reads.pop_front() else {
+                // The connection was closed
+                return Ok(0);
+            };
+
+            let read = part.len().min(buf.len());
+            buf[..read].copy_from_slice(&part[..read]);
+            if read < part.len() {
+                self.reads.push_front(part.split_off(read));
+            }
+            Ok(read)
+        }
+    }
+
+    impl Write for MockSocket {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn mock_connection(reads: &[&[u8]]) -> DockerApiConnection {
+        DockerApiConnection::Mock(MockSocket {
+            reads: reads.iter().map(|part| part.to_vec()).collect(),
+            written: Vec::new(),
+        })
+    }
+
+    /// Makes a streaming request over the provided connection and reads the whole response body.
+    fn read_streaming_body(
+        conn: &mut DockerApiConnection,
+    ) -> (StatusCode, io::Result<Vec<u8>>) {
+        let mut buf = REQUEST.to_vec();
+        let (status, mut body) = conn.make_streaming_request(&mut buf).unwrap();
+        let mut contents = Vec::new();
+        let result = body.read_to_end(&mut contents).map(|_| contents);
+        (status, result)
+    }
+
+    fn remaining_reads(conn: &DockerApiConnection) -> &VecDeque<Vec<u8>> {
+        let DockerApiConnection::Mock(mock) = conn else {
+            unreachable!()
+        };
+        &mock.reads
+    }
+
+    #[test]
+    fn streaming_body_with_headers_in_same_read() {
+        let mut conn = mock_connection(&[
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello",
+        ]);
+        let (status, body) = read_streaming_body(&mut conn);
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.unwrap(), b"hello");
+
+        let DockerApiConnection::Mock(mock) = &conn else {
+            unreachable!()
+        };
+        assert_eq!(mock.written, REQUEST);
+    }
+
+    #[test]
+    fn streaming_fixed_length_body_stops_at_its_end() {
+        let mut conn = mock_connection(&[
+            b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhel",
+            b"lo wo",
+            b"rld",
+            b"HTTP/1.1 200 OK",
+        ]);
+        let (_, body) = read_streaming_body(&mut conn);
+        assert_eq!(body.unwrap(), b"hello world");
+        // Data after the body must not be consumed
+        assert_eq!(remaining_reads(&conn).len(), 1);
+    }
+
+    #[test]
+    fn streaming_chunked_body_split_across_reads() {
+        let mut conn = mock_connection(&[
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhel",
+            b"lo\r\n6\r",
+            b"\n world\r\n",
+            b"0\r\n\r\n",
+        ]);
+        let (status, body) = read_streaming_body(&mut conn);
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.unwrap(), b"hello world");
+    }
+
+    #[test]
+    fn streaming_chunked_body_in_same_read() {
+        let mut conn = mock_connection(&[
+            b"HTTP/1.1 404 Not Found\r\nTransfer-Encoding: chunked\r\n\r\n2\r\n{}\r\n0\r\n\r\n",
+        ]);
+        let (status, body) = read_streaming_body(&mut conn);
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body.unwrap(), b"{}");
+    }
+
+    #[test]
+    fn streaming_body_after_closed_connection() {
+        let mut conn = mock_connection(&[
+            b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n",
+            b"hello",
+        ]);
+        let (_, body) = read_streaming_body(&mut conn);
+        assert_eq!(body.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut conn = mock_connection(&[
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhel",
+        ]);
+        let (_, body) = read_streaming_body(&mut conn);
+        assert_eq!(body.unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn buffered_request() {
+        let mut conn = mock_connection(&[
+            b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\nhello",
+            b" world",
+        ]);
+        let mut buf = REQUEST.to_vec();
+        let status = conn.make_request(&mut buf).unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(buf, b"hello world");
+    }
+
+    // --- split_host_port ---
+
+    #[test]
+    fn split_host_and_port() {
+        assert_eq!(split_host_port("docker"), ("docker", None));
+        assert_eq!(split_host_port("docker:2376"), ("docker", Some("2376")));
+        assert_eq!(
+            split_host_port("10.0.0.1:2375"),
+            ("10.0.0.1", Some("2375"))
+        );
+        assert_eq!(split_host_port("[::1]"), ("::1", None));
+        assert_eq!(split_host_port("[::1]:2376"), ("::1", Some("2376")));
+        assert_eq!(
+            split_host_port("[fe80::1%eth0]:2375"),
+            ("fe80::1%eth0", Some("2375"))
+        );
+    }
+
+    // --- resolve_tcp_addr ---
+
+    #[test]
+    fn default_tcp_ports() {
+        let resolve = DockerApiConnection::resolve_tcp_addr;
+        assert_eq!(
+            resolve("docker", false).unwrap(),
+            ("docker", "docker", 2375)
+        );
+        assert_eq!(
+            resolve("docker", true).unwrap(),
+            ("docker", "docker", 2376)
+        );
+        assert_eq!(resolve("[::1]", true).unwrap(), ("[::1]", "::1", 2376));
+        // Explicit ports are used regardless of TLS
+        assert_eq!(
+            resolve("docker:2375", true).unwrap(),
+            ("docker:2375", "docker", 2375)
+        );
+    }
+
+    #[test]
+    fn tcp_addr_with_trailing_path() {
+        let resolve = DockerApiConnection::resolve_tcp_addr;
+        assert_eq!(
+            resolve("docker:2376/v1.47", true).unwrap(),
+            ("docker:2376", "docker", 2376)
+        );
+        assert_eq!(resolve("[::1]/", false).unwrap(), ("[::1]", "::1", 2375));
+    }
+
+    #[test]
+    fn tcp_addr_with_invalid_port() {
+        assert!(
+            DockerApiConnection::resolve_tcp_addr("docker:docker", false)
+                .is_err()
+        );
+        assert!(
+            DockerApiConnection::resolve_tcp_addr("docker:65536", false)
+                .is_err()
+        );
+        assert!(
+            DockerApiConnection::resolve_tcp_addr("[::1]:", false).is_err()
+        );
+    }
+
+    // --- connect ---
+
+    #[test]
+    fn unsupported_schemes() {
+        for docker_host in ["ssh://user@docker", "http://docker:2375", "fd://"]
+        {
+            let Err(error) = DockerApiConnection::connect(docker_host, None)
+            else {
+                panic!("'{docker_host}' should be rejected");
+            };
+            assert!(
+                error.to_string().contains("unsupported Docker host scheme"),
+                "unexpected error for '{docker_host}': {error}"
+            );
         }
     }
 }
@@ -6,7 +6,8 @@ use std::path::PathBuf;
 use serde::Deserialize;
 
 use super::constants::{
-    DOCKER_CONFIG_DIR_ENV_VAR, DOCKER_CONTEXTS_METADATA_DIR,
+    DOCKER_CONFIG_DIR_ENV_VAR, DOCKER_CONTEXT_TLS_ENDPOINT_DIR,
+    DOCKER_CONTEXTS_METADATA_DIR, DOCKER_CONTEXTS_TLS_DIR,
     DOCKER_DEFAULT_CONFIG_DIR,
 };
 use super::util::{encode_sha256_digest, get_home_dir, sha256_digest};
@@ -75,8 +76,23 @@ impl DockerConfig {
         Some(context_dir)
     }
 
+    /// Returns the directory with the TLS material (`ca.pem`, `cert.pem`, and `key.pem`) of the currently active Docker
+    /// context's Docker endpoint.
+    pub fn get_current_context_tls_dir(&self) -> Option<PathBuf> {
+        let current_context = self.current_context.as_ref()?;
+        let encoded_current_context_sha256_digest =
+            encode_sha256_digest(sha256_digest(current_context));
+
+        let mut tls_dir = self.config_dir.to_owned();
+        tls_dir.push(DOCKER_CONTEXTS_TLS_DIR);
+        tls_dir.push(encoded_current_context_sha256_digest);
+        tls_dir.push(DOCKER_CONTEXT_TLS_ENDPOINT_DIR);
+
+        Some(tls_dir)
+    }
+
     /// Returns the Docker config directory. Takes into account the [DOCKER_CONFIG_DIR_ENV_VAR].
-    fn get_dir() -> Result<PathBuf> {
+    pub fn get_dir() -> Result<PathBuf> {
         match env::var(DOCKER_CONFIG_DIR_ENV_VAR) {
             Ok(config_dir) => Ok(config_dir.into()),
             Err(e @ VarError::NotUnicode(_)) => {
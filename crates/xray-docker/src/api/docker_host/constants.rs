@@ -6,8 +6,18 @@ pub const DEFAULT_DOCKER_HOST: &str = "unix:///var/run/docker.sock";
 
 pub const DOCKER_HOST_ENV_VAR: &str = "DOCKER_HOST";
 
+pub const DOCKER_CERT_PATH_ENV_VAR: &str = "DOCKER_CERT_PATH";
+
+pub const DOCKER_TLS_ENV_VAR: &str = "DOCKER_TLS";
+
+pub const DOCKER_TLS_VERIFY_ENV_VAR: &str = "DOCKER_TLS_VERIFY";
+
 pub const DOCKER_CONFIG_DIR_ENV_VAR: &str = "DOCKER_CONFIG";
 
 pub const DOCKER_DEFAULT_CONFIG_DIR: &str = ".docker";
 
 pub const DOCKER_CONTEXTS_METADATA_DIR: &str = "contexts/meta";
+
+pub const DOCKER_CONTEXTS_TLS_DIR: &str = "contexts/tls";
+
+pub const DOCKER_CONTEXT_TLS_ENDPOINT_DIR: &str = "docker";
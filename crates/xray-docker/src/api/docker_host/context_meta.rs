@@ -50,6 +50,11 @@ impl ContextMetadata {
             .map(Option::Some)
     }
 
+    /// Returns `true` if the context's Docker endpoint doesn't require verifying the daemon's certificate.
+    pub fn skip_tls_verify(&self) -> bool {
+        self.endpoints.docker.skip_tls_verify
+    }
+
     pub fn into_docker_host(self) -> String {
         self.endpoints.docker.host
     }
@@ -66,4 +71,6 @@ pub struct MetadataEndpoints {
 pub struct Endpoint {
     #[serde(rename = "Host")]
     host: String,
+    #[serde(rename = "SkipTLSVerify", default)]
+    skip_tls_verify: bool,
 }
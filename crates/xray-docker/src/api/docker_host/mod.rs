@@ -3,6 +3,7 @@
 mod config;
 mod constants;
 mod context_meta;
+mod tls;
 mod util;
 
 use std::borrow::Cow;
@@ -10,5 +11,6 @@ use std::borrow::Cow;
 pub use config::DockerConfig;
 pub use constants::*;
 pub use context_meta::ContextMetadata;
+pub use tls::TlsOptions;
 
 pub type DockerHost = Cow<'static, str>;
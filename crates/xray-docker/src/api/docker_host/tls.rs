@@ -0,0 +1,246 @@
+use std::env::{self, VarError};
+use std::fs;
+use std::path::PathBuf;
+
+use super::config::DockerConfig;
+use super::constants::{
+    DOCKER_CERT_PATH_ENV_VAR, DOCKER_TLS_ENV_VAR, DOCKER_TLS_VERIFY_ENV_VAR,
+};
+use super::context_meta::ContextMetadata;
+use crate::{DockerError, Result};
+
+/// TLS settings that are used to connect to a TCP Docker host.
+pub struct TlsOptions {
+    /// Directory with the TLS material: `ca.pem`, `cert.pem`, and `key.pem`.
+    ///
+    /// `ca.pem` is required if [Self::verify] is set, while the client certificate is only sent if both `cert.pem` and
+    /// `key.pem` are present.
+    pub cert_dir: PathBuf,
+    /// Whether the daemon's certificate should be verified.
+    pub verify: bool,
+}
+
+impl TlsOptions {
+    /// Resolves the TLS settings from the [DOCKER_TLS_VERIFY_ENV_VAR], [DOCKER_TLS_ENV_VAR], and
+    /// [DOCKER_CERT_PATH_ENV_VAR] env variables.
+    ///
+    /// Just like in the Docker CLI, TLS with verification of the daemon's certificate is used if
+    /// [DOCKER_TLS_VERIFY_ENV_VAR] is set, while [DOCKER_TLS_ENV_VAR] enables TLS without verification.
+    /// [DOCKER_CERT_PATH_ENV_VAR] only points to the TLS material, which is read from the Docker config directory by
+    /// default.
+    pub fn new_from_env() -> Result<Option<Self>> {
+        Self::new_from_env_vars(get_non_empty_env_var)
+    }
+
+    /// Does the same as [Self::new_from_env], but reads the env variables using the provided function.
+    fn new_from_env_vars(
+        get_env_var: impl Fn(&'static str) -> Result<Option<String>>,
+    ) -> Result<Option<Self>> {
+        let verify = get_env_var(DOCKER_TLS_VERIFY_ENV_VAR)?.is_some();
+        if !verify && get_env_var(DOCKER_TLS_ENV_VAR)?.is_none() {
+            return Ok(None);
+        }
+
+        let cert_dir = match get_env_var(DOCKER_CERT_PATH_ENV_VAR)? {
+            Some(cert_dir) => cert_dir.into(),
+            None => DockerConfig::get_dir()?,
+        };
+
+        Ok(Some(TlsOptions { cert_dir, verify }))
+    }
+
+    /// Resolves the TLS settings of the provided [DockerConfig]'s current context.
+    ///
+    /// TLS is used if the context has TLS material for its Docker endpoint or if it skips verifying the daemon's
+    /// certificate.
+    pub fn new_from_context(
+        docker_config: &DockerConfig,
+        context_meta: &ContextMetadata,
+    ) -> Result<Option<Self>> {
+        let Some(cert_dir) = docker_config.get_current_context_tls_dir() else {
+            return Ok(None);
+        };
+
+        let has_tls_material = fs::exists(&cert_dir).map_err(|e| {
+            DockerError::from_io_error_with_description(e, || {
+                "failed to check if the context has TLS material".into()
+            })
+        })?;
+        if !has_tls_material && !context_meta.skip_tls_verify() {
+            return Ok(None);
+        }
+
+        Ok(Some(TlsOptions {
+            cert_dir,
+            verify: !context_meta.skip_tls_verify(),
+        }))
+    }
+}
+
+/// Returns the value of the provided env variable if it's set to a non-empty value.
+fn get_non_empty_env_var(var_name: &'static str) -> Result<Option<String>> {
+    match env::var(var_name) {
+        Ok(value) if !value.is_empty() => Ok(Some(value)),
+        Ok(_) | Err(VarError::NotPresent) => Ok(None),
+        Err(e @ VarError::NotUnicode(_)) => Err(
+            DockerError::from_var_error_with_var_name(e, var_name.into()),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::api::docker_host::util::{encode_sha256_digest, sha256_digest};
+
+    fn from_env_vars(vars: &[(&'static str, &str)]) -> Option<TlsOptions> {
+        let vars = vars.iter().copied().collect::<HashMap<_, _>>();
+        TlsOptions::new_from_env_vars(|var_name| {
+            Ok(vars.get(var_name).map(|value| value.to_string()))
+        })
+        .unwrap()
+    }
+
+    fn make_config(config_dir: &Path) -> DockerConfig {
+        DockerConfig {
+            current_context: Some("remote".into()),
+            config_dir: config_dir.into(),
+        }
+    }
+
+    fn make_context_meta(skip_tls_verify: bool) -> ContextMetadata {
+        serde_json::from_value(json!({
+            "Endpoints": {
+                "docker": {
+                    "Host": "tcp://remote:2376",
+                    "SkipTLSVerify": skip_tls_verify,
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    /// Creates the directory with the TLS material of the `remote` context.
+    fn make_context_tls_dir(config_dir: &Path) -> PathBuf {
+        let tls_dir = config_dir
+            .join("contexts/tls")
+            .join(encode_sha256_digest(sha256_digest("remote")))
+            .join("docker");
+        fs::create_dir_all(&tls_dir).unwrap();
+        tls_dir
+    }
+
+    // --- new_from_env ---
+
+    #[test]
+    fn env_without_tls() {
+        assert!(from_env_vars(&[]).is_none());
+        // The cert path alone only points to the TLS material
+        assert!(
+            from_env_vars(&[(DOCKER_CERT_PATH_ENV_VAR, "/certs")]).is_none()
+        );
+    }
+
+    #[test]
+    fn env_with_tls_verify() {
+        let options = from_env_vars(&[
+            (DOCKER_TLS_VERIFY_ENV_VAR, "1"),
+            (DOCKER_CERT_PATH_ENV_VAR, "/certs"),
+        ])
+        .unwrap();
+        assert!(options.verify);
+        assert_eq!(options.cert_dir, Path::new("/certs"));
+
+        // Verification takes precedence
+        let options = from_env_vars(&[
+            (DOCKER_TLS_ENV_VAR, "1"),
+            (DOCKER_TLS_VERIFY_ENV_VAR, "1"),
+        ])
+        .unwrap();
+        assert!(options.verify);
+    }
+
+    #[test]
+    fn env_with_tls_without_verify() {
+        let options = from_env_vars(&[
+            (DOCKER_TLS_ENV_VAR, "1"),
+            (DOCKER_CERT_PATH_ENV_VAR, "/certs"),
+        ])
+        .unwrap();
+        assert!(!options.verify);
+        assert_eq!(options.cert_dir, Path::new("/certs"));
+    }
+
+    #[test]
+    fn env_with_default_cert_dir() {
+        let options =
+            from_env_vars(&[(DOCKER_TLS_VERIFY_ENV_VAR, "1")]).unwrap();
+        assert_eq!(options.cert_dir, DockerConfig::get_dir().unwrap());
+    }
+
+    // --- new_from_context ---
+
+    #[test]
+    fn context_without_tls() {
+        let config_dir = TempDir::new().unwrap();
+        let options = TlsOptions::new_from_context(
+            &make_config(config_dir.path()),
+            &make_context_meta(false),
+        )
+        .unwrap();
+        assert!(options.is_none());
+
+        let config = DockerConfig {
+            current_context: None,
+            config_dir: config_dir.path().into(),
+        };
+        make_context_tls_dir(config_dir.path());
+        let options =
+            TlsOptions::new_from_context(&config, &make_context_meta(true))
+                .unwrap();
+        assert!(options.is_none());
+    }
+
+    #[test]
+    fn context_with_tls_material() {
+        let config_dir = TempDir::new().unwrap();
+        let tls_dir = make_context_tls_dir(config_dir.path());
+
+        let options = TlsOptions::new_from_context(
+            &make_config(config_dir.path()),
+            &make_context_meta(false),
+        )
+        .unwrap()
+        .unwrap();
+        assert!(options.verify);
+        assert_eq!(options.cert_dir, tls_dir);
+
+        let options = TlsOptions::new_from_context(
+            &make_config(config_dir.path()),
+            &make_context_meta(true),
+        )
+        .unwrap()
+        .unwrap();
+        assert!(!options.verify);
+        assert_eq!(options.cert_dir, tls_dir);
+    }
+
+    #[test]
+    fn context_skipping_verification_without_tls_material() {
+        let config_dir = TempDir::new().unwrap();
+
+        let options = TlsOptions::new_from_context(
+            &make_config(config_dir.path()),
+            &make_context_meta(true),
+        )
+        .unwrap()
+        .unwrap();
+        assert!(!options.verify);
+    }
+}
@@ -3,12 +3,14 @@ use std::env::{self};
 mod chunk_processor;
 mod connection;
 mod docker_host;
+mod tls;
 mod util;
 
 use connection::DockerApiConnection;
+pub use connection::ResponseBody;
 use docker_host::{
     ContextMetadata, DEFAULT_DOCKER_HOST, DOCKER_HOST_ENV_VAR, DockerConfig,
-    DockerHost,
+    DockerHost, TlsOptions,
 };
 use http::StatusCode;
 use util::encode_request;
@@ -23,8 +25,8 @@ pub struct DockerApi {
 impl DockerApi {
     /// Creates a new [DockerApi] instance using the Docker Host resolution logic from [Self::get_docker_host].
     pub fn new_with_host_resolution() -> Result<Self> {
-        let host = Self::get_docker_host()?;
-        let connection = DockerApiConnection::connect(host)?;
+        let (host, tls) = Self::resolve_docker_host()?;
+        let connection = DockerApiConnection::connect(host, tls.as_ref())?;
 
         Ok(DockerApi {
             connection,
@@ -86,8 +88,10 @@ impl DockerApi {
         }
     }
 
-    /// Downloads a tarball of the provided image.
-    pub fn export_image(&mut self, image: &str) -> Result<&[u8]> {
+    /// Starts downloading a tarball of the provided image.
+    ///
+    /// The tarball is streamed from the returned [ResponseBody] as it's being read, so it's never fully stored in memory.
+    pub fn export_image(&mut self, image: &str) -> Result<ResponseBody<'_>> {
         let request = http::Request::builder()
             .uri(format!("/images/{image}/get"))
             .header("host", "docker")
@@ -97,10 +101,11 @@ impl DockerApi {
                 DockerError::Other(format!("failed to construct the request to export the '{image}' image").into())
             })?;
 
-        // Send the  request and receive a response
+        // Send the  request and start receiving a response
         self.buffer.clear();
         encode_request(&request, &mut self.buffer)?;
-        let status_code = self.connection.make_request(&mut self.buffer)?;
+        let (status_code, body) =
+            self.connection.make_streaming_request(&mut self.buffer)?;
 
         if status_code != http::StatusCode::OK {
             match status_code {
@@ -112,7 +117,7 @@ impl DockerApi {
                 }
             }
         } else {
-            Ok(&self.buffer)
+            Ok(body)
         }
     }
 
@@ -123,24 +128,32 @@ impl DockerApi {
 
     /// Returns the resolved Docker host for the current system.
     pub fn get_docker_host() -> Result<DockerHost> {
+        Self::resolve_docker_host().map(|(host, _)| host)
+    }
+
+    /// Resolves the Docker host for the current system along with the [TlsOptions] that should be used to connect to
+    /// it.
+    fn resolve_docker_host() -> Result<(DockerHost, Option<TlsOptions>)> {
         if let Ok(host) = env::var(DOCKER_HOST_ENV_VAR) {
             // No need to check anything else if we have an explicit env var
-            return Ok(host.into());
+            return Ok((host.into(), TlsOptions::new_from_env()?));
         }
 
         // If we don't have an env, we need to check the Docker Context
         let Some(docker_config) = DockerConfig::new()? else {
             // We can't do anything else at this point besides returning the default Docker host
-            return Ok(DEFAULT_DOCKER_HOST.into());
+            return Ok((DEFAULT_DOCKER_HOST.into(), None));
         };
 
         let context_meta =
             ContextMetadata::new_from_docker_config(&docker_config)?;
         if let Some(context_meta) = context_meta {
-            Ok(context_meta.into_docker_host().into())
+            let tls =
+                TlsOptions::new_from_context(&docker_config, &context_meta)?;
+            Ok((context_meta.into_docker_host().into(), tls))
         } else {
             // Return default Docker host for the current OS
-            Ok(DEFAULT_DOCKER_HOST.into())
+            Ok((DEFAULT_DOCKER_HOST.into(), None))
         }
     }
 }
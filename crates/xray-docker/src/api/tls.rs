@@ -0,0 +1,190 @@
+//! Contains the logic required to set up TLS connections to TCP Docker hosts.
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use rustls::crypto::{
+    CryptoProvider, verify_tls12_signature, verify_tls13_signature,
+};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{
+    ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+
+use super::docker_host::TlsOptions;
+use crate::{DockerError, Result};
+
+const CA_CERT_FILENAME: &str = "ca.pem";
+const CLIENT_CERT_FILENAME: &str = "cert.pem";
+const CLIENT_KEY_FILENAME: &str = "key.pem";
+
+/// Creates a TLS [ClientConfig] that uses the TLS material from the provided [TlsOptions].
+pub fn client_config(options: &TlsOptions) -> Result<ClientConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| {
+            DockerError::from_tls_error_with_description(e, || {
+                "failed to select TLS protocol versions".into()
+            })
+        })?;
+
+    let builder = if options.verify {
+        let mut root_certs = RootCertStore::empty();
+        let ca_cert_path = options.cert_dir.join(CA_CERT_FILENAME);
+        if !file_exists(&ca_cert_path)? {
+            return Err(DockerError::Other(
+                format!(
+                    "'{}' is required to verify the daemon's certificate",
+                    ca_cert_path.display()
+                )
+                .into(),
+            ));
+        }
+        for cert in read_certs(&ca_cert_path)? {
+            root_certs.add(cert).map_err(|e| {
+                DockerError::from_tls_error_with_description(e, || {
+                    "failed to add the CA certificate".into()
+                })
+            })?;
+        }
+        builder.with_root_certificates(root_certs)
+    } else {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoVerification(
+                provider,
+            )))
+    };
+
+    let client_cert_path = options.cert_dir.join(CLIENT_CERT_FILENAME);
+    let client_key_path = options.cert_dir.join(CLIENT_KEY_FILENAME);
+    if !file_exists(&client_cert_path)? || !file_exists(&client_key_path)? {
+        return Ok(builder.with_no_client_auth());
+    }
+
+    let client_key =
+        PrivateKeyDer::from_pem_file(&client_key_path).map_err(|e| {
+            DockerError::Other(
+                format!(
+                    "failed to read the client key from '{}': {e}",
+                    client_key_path.display()
+                )
+                .into(),
+            )
+        })?;
+    builder
+        .with_client_auth_cert(read_certs(&client_cert_path)?, client_key)
+        .map_err(|e| {
+            DockerError::from_tls_error_with_description(e, || {
+                "invalid client certificate or key".into()
+            })
+        })
+}
+
+/// Reads all certificates from the provided PEM file.
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<std::result::Result<Vec<_>, _>>())
+        .map_err(|e| {
+            DockerError::Other(
+                format!(
+                    "failed to read certificates from '{}': {e}",
+                    path.display()
+                )
+                .into(),
+            )
+        })
+}
+
+fn file_exists(path: &Path) -> Result<bool> {
+    fs::exists(path).map_err(|e| {
+        DockerError::from_io_error_with_description(e, || {
+            format!("failed to check if '{}' exists", path.display()).into()
+        })
+    })
+}
+
+/// Accepts any certificate of the Docker daemon, which is what `SkipTLSVerify` in Docker contexts does.
+///
+/// Signatures are still checked to make sure that the daemon owns the certificate it presents.
+#[derive(Debug)]
+struct NoVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn verification_requires_ca_cert() {
+        let cert_dir = TempDir::new().unwrap();
+
+        let Err(error) = client_config(&TlsOptions {
+            cert_dir: cert_dir.path().into(),
+            verify: true,
+        }) else {
+            panic!("the CA certificate is missing");
+        };
+        assert!(error.to_string().contains(CA_CERT_FILENAME));
+
+        assert!(
+            client_config(&TlsOptions {
+                cert_dir: cert_dir.path().into(),
+                verify: false,
+            })
+            .is_ok()
+        );
+    }
+}
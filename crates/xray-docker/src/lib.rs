@@ -4,7 +4,7 @@ use thiserror::Error;
 
 mod api;
 
-pub use api::DockerApi;
+pub use api::{DockerApi, ResponseBody};
 
 pub type Result<T> = std::result::Result<T, DockerError>;
 
@@ -30,6 +30,12 @@ pub enum DockerError {
         #[source]
         source: serde_json::Error,
     },
+    #[error("failed to set up a TLS connection: {description}")]
+    TlsError {
+        description: Cow<'static, str>,
+        #[source]
+        source: rustls::Error,
+    },
     #[error("{0}")]
     Other(Cow<'static, str>),
 }
@@ -52,6 +58,16 @@ impl DockerError {
         }
     }
 
+    fn from_tls_error_with_description(
+        source: rustls::Error,
+        description: impl Fn() -> Cow<'static, str>,
+    ) -> DockerError {
+        DockerError::TlsError {
+            description: description(),
+            source,
+        }
+    }
+
     fn from_serde_error_with_description(
         source: serde_json::Error,
         description: impl Fn() -> Cow<'static, str>,
@@ -1,4 +1,6 @@
-use std::process::{Child, Command, Output, Stdio};
+use std::io::Read;
+use std::process::{Child, ChildStdout, Command, Output, Stdio};
+use std::thread::JoinHandle;
 
 use anyhow::Context;
 
@@ -34,3 +36,130 @@ impl CommandRunner {
             .map_err(Into::into)
     }
 }
+
+impl CommandRunner {
+    /// Turns this runner into a [StreamingCommandRunner] that allows reading the command's stdout while it's running.
+    pub(crate) fn into_streaming(mut self) -> Result<StreamingCommandRunner> {
+        let stdout = self
+            .child
+            .stdout
+            .take()
+            .context("missing stdout of a child process")?;
+        let mut stderr = self
+            .child
+            .stderr
+            .take()
+            .context("missing stderr of a child process")?;
+
+        // Stderr must be read concurrently, as the command would block once the pipe is full otherwise
+        let stderr = std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            stderr.read_to_end(&mut buf).map(|_| buf)
+        });
+
+        Ok(StreamingCommandRunner {
+            child: self.child,
+            stdout,
+            stderr,
+        })
+    }
+}
+
+/// A [CommandRunner] that streams the command's stdout instead of collecting it.
+///
+/// Stderr is still collected in the background and is returned by [Self::wait_until_completion].
+pub(crate) struct StreamingCommandRunner {
+    child: Child,
+    stdout: ChildStdout,
+    stderr: JoinHandle<std::io::Result<Vec<u8>>>,
+}
+
+impl Read for StreamingCommandRunner {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.stdout.read(buf)
+    }
+}
+
+impl StreamingCommandRunner {
+    /// Waits until the wrapped shell command exits and returns its [Output].
+    ///
+    /// The returned [Output] doesn't contain stdout, as it was already streamed. Any stdout that wasn't read is discarded.
+    pub(crate) fn wait_until_completion(mut self) -> Result<Output> {
+        // Close our end of the pipe, so that the command doesn't block on writing to it
+        drop(self.stdout);
+
+        let status = self.child.wait().context("child process failed")?;
+        let stderr = self
+            .stderr
+            .join()
+            .map_err(|_| anyhow::anyhow!("stderr reader panicked"))?
+            .context("failed to read stderr of a child process")?;
+
+        Ok(Output {
+            status,
+            stdout: Vec::new(),
+            stderr,
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn spawn_streaming(script: &str) -> StreamingCommandRunner {
+        CommandRunner::spawn("sh", &["-c", script])
+            .unwrap()
+            .into_streaming()
+            .unwrap()
+    }
+
+    #[test]
+    fn streams_stdout_and_collects_stderr() {
+        let mut runner =
+            spawn_streaming("printf 'a tarball'; printf 'a warning' >&2");
+        let mut stdout = Vec::new();
+        runner.read_to_end(&mut stdout).unwrap();
+        assert_eq!(stdout, b"a tarball");
+
+        let output = runner.wait_until_completion().unwrap();
+        assert!(output.status.success());
+        assert!(output.stdout.is_empty());
+        assert_eq!(output.stderr, b"a warning");
+    }
+
+    #[test]
+    fn reports_exit_code_after_stdout_is_drained() {
+        let mut runner =
+            spawn_streaming("printf 'partial'; printf 'no space' >&2; exit 3");
+        let mut stdout = Vec::new();
+        runner.read_to_end(&mut stdout).unwrap();
+        assert_eq!(stdout, b"partial");
+
+        let output = runner.wait_until_completion().unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stderr, b"no space");
+    }
+
+    #[test]
+    fn large_stderr_does_not_block_stdout() {
+        // More than a pipe can hold, the command would block forever if stderr wasn't read concurrently
+        let mut runner =
+            spawn_streaming("head -c 1000000 /dev/zero >&2; printf 'done'");
+        let mut stdout = Vec::new();
+        runner.read_to_end(&mut stdout).unwrap();
+        assert_eq!(stdout, b"done");
+
+        let output = runner.wait_until_completion().unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stderr.len(), 1_000_000);
+    }
+
+    #[test]
+    fn unread_stdout_is_discarded() {
+        let runner = spawn_streaming("head -c 1000000 /dev/zero");
+        let output = runner.wait_until_completion().unwrap();
+        // The command is killed by SIGPIPE or exits normally, but it must not hang
+        assert!(output.stdout.is_empty());
+    }
+}
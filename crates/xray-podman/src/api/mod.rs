@@ -1,9 +1,11 @@
 mod command_runner;
 
+use std::io::Read;
+
 use anyhow::Context;
 
 use super::{PodmanError, Result};
-use crate::api::command_runner::CommandRunner;
+use crate::api::command_runner::{CommandRunner, StreamingCommandRunner};
 
 const EXIT_CODE_MISSING_IMAGE: i32 = 1;
 
@@ -67,14 +69,40 @@ impl PodmanApi {
         ))
     }
 
-    /// Downloads a tarball of the provided image.
-    pub fn save_image(&self, image: &str) -> Result<Vec<u8>> {
+    /// Starts saving a tarball of the provided image.
+    ///
+    /// The tarball is streamed from the returned [SavedImage] while Podman is writing it, so it's never fully stored
+    /// in memory.
+    pub fn save_image(&self, image: &str) -> Result<SavedImage> {
         let runner = CommandRunner::spawn(
             "podman",
             &["save", "-q", "--format", "oci-archive", image],
         )?;
 
-        let output = runner
+        Ok(SavedImage {
+            runner: runner.into_streaming()?,
+        })
+    }
+}
+
+/// A tarball of an image that is being saved by Podman.
+///
+/// [SavedImage::finish] must be called after reading the whole tarball to check whether Podman actually succeeded.
+pub struct SavedImage {
+    runner: StreamingCommandRunner,
+}
+
+impl Read for SavedImage {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.runner.read(buf)
+    }
+}
+
+impl SavedImage {
+    /// Waits until Podman exits and checks that the image was saved successfully.
+    pub fn finish(self) -> Result<()> {
+        let output = self
+            .runner
             .wait_until_completion()
             .context("failed to run a podman CLI command to completion")?;
 
@@ -86,6 +114,33 @@ impl PodmanApi {
             ));
         }
 
-        Ok(output.stdout)
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_image_reports_failure_after_reading() {
+        let runner = CommandRunner::spawn(
+            "sh",
+            &["-c", "printf 'partial'; printf 'no\nspace' >&2; exit 125"],
+        )
+        .unwrap();
+        let mut saved_image = SavedImage {
+            runner: runner.into_streaming().unwrap(),
+        };
+        let mut tarball = Vec::new();
+        saved_image.read_to_end(&mut tarball).unwrap();
+        assert_eq!(tarball, b"partial");
+
+        let error = saved_image.finish().unwrap_err();
+        assert!(matches!(
+            error,
+            PodmanError::PodmanCli { exit_code: 125, .. }
+        ));
+        assert!(error.to_string().contains("'no. space'"), "{error}");
     }
 }
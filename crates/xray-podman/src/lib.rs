@@ -1,5 +1,5 @@
 mod api;
-pub use api::PodmanApi;
+pub use api::{PodmanApi, SavedImage};
 mod util;
 
 use thiserror::Error;
@@ -0,0 +1,432 @@
+use std::io::Read;
+use std::sync::{Mutex, PoisonError};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use ureq::config::RedirectAuthHeaders;
+use ureq::http::{Response, StatusCode};
+use ureq::{Agent, Body, BodyReader};
+
+use crate::auth::Challenge;
+use crate::credentials::Credentials;
+use crate::reference::Reference;
+use crate::{RegistryError, Result};
+
+/// Media types of manifests that we can parse, in the order of preference.
+const MANIFEST_MEDIA_TYPES: &str = "application/vnd.oci.image.index.v1+json, \
+    application/vnd.oci.image.manifest.v1+json, \
+    application/vnd.docker.distribution.manifest.list.v2+json, \
+    application/vnd.docker.distribution.manifest.v2+json";
+/// Manifests are kept in memory, so anything larger than this is most likely not a manifest.
+const MAX_MANIFEST_SIZE: u64 = 4 * 1024 * 1024;
+const SHA256_DIGEST_PREFIX: &str = "sha256:";
+
+/// A client for the [OCI Distribution API](https://github.com/opencontainers/distribution-spec/blob/main/spec.md) of
+/// a single repository.
+///
+/// Handles the Basic and Bearer token authentication schemes transparently: requests are retried once after getting an
+/// authentication challenge, and the resulting `Authorization` header is reused for all later requests.
+pub struct RegistryApi {
+    agent: Agent,
+    /// Base URL of the repository's API, e.g. `https://ghcr.io/v2/owner/image`.
+    base_url: String,
+    /// Used to request a token if the registry doesn't provide a scope in its challenge.
+    repository: String,
+    credentials: Option<Credentials>,
+    /// The value of the `Authorization` header that was obtained after the last authentication challenge.
+    authorization: Mutex<Option<String>>,
+}
+
+impl RegistryApi {
+    /// Creates a new [RegistryApi] for the repository of the provided [Reference] using the credentials from the Docker
+    /// config (check [Credentials::from_docker_config]).
+    pub fn new(reference: &Reference) -> Result<Self> {
+        let credentials =
+            Credentials::from_docker_config(reference.registry())?;
+        Ok(Self::new_with_credentials(reference, credentials))
+    }
+
+    /// Creates a new [RegistryApi] for the repository of the provided [Reference] using the provided [Credentials].
+    ///
+    /// Plain HTTP is only used for registries on the loopback interface (e.g. `localhost:5000`), just like Docker does.
+    pub fn new_with_credentials(
+        reference: &Reference,
+        credentials: Option<Credentials>,
+    ) -> Self {
+        let host = reference.api_host();
+        let scheme = if is_loopback(host) { "http" } else { "https" };
+        let agent = Agent::config_builder()
+            // Error statuses are handled manually, as some of them are expected (e.g. authentication challenges)
+            .http_status_as_error(false)
+            // Blobs are usually served from a CDN, which would reject the registry's credentials
+            .redirect_auth_headers(RedirectAuthHeaders::SameHost)
+            .build()
+            .new_agent();
+
+        RegistryApi {
+            agent,
+            base_url: format!(
+                "{scheme}://{host}/v2/{}",
+                reference.repository()
+            ),
+            repository: reference.repository().to_owned(),
+            credentials,
+            authorization: Mutex::new(None),
+        }
+    }
+
+    /// Fetches the manifest (an Image Manifest or an Image Index) with the provided tag or digest.
+    ///
+    /// Manifests that are fetched by their digests are verified against them.
+    pub fn get_manifest(&self, reference: &str) -> Result<Vec<u8>> {
+        let url = format!("{}/manifests/{reference}", self.base_url);
+        let manifest = self
+            .get(&url, MANIFEST_MEDIA_TYPES)?
+            .into_body()
+            .with_config()
+            .limit(MAX_MANIFEST_SIZE)
+            .read_to_vec()
+            .map_err(|e| RegistryError::from_http_error(e, &url))?;
+
+        if let Some(expected_digest) =
+            reference.strip_prefix(SHA256_DIGEST_PREFIX)
+        {
+            let digest = Sha256::digest(&manifest)
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect::<String>();
+            if digest != expected_digest {
+                return Err(RegistryError::Other(
+                    format!(
+                        "the manifest doesn't match its digest '{reference}'"
+                    )
+                    .into(),
+                ));
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    /// Starts downloading the blob (e.g. an Image Config or a layer) with the provided digest.
+    ///
+    /// The blob is streamed from the returned [BlobReader] as it's being read, so it's never fully stored in memory.
+    pub fn get_blob(&self, digest: &str) -> Result<BlobReader> {
+        let url = format!("{}/blobs/{digest}", self.base_url);
+        let response = self.get(&url, "*/*")?;
+
+        Ok(BlobReader(response.into_body().into_reader()))
+    }
+
+    /// Sends a GET request to the provided URL, authenticating with the registry if it responds with a challenge.
+    fn get(&self, url: &str, accept: &str) -> Result<Response<Body>> {
+        let mut authenticated = false;
+        loop {
+            let authorization = self
+                .authorization
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone();
+            let mut request = self.agent.get(url).header("accept", accept);
+            if let Some(authorization) = authorization {
+                request = request.header("authorization", authorization);
+            }
+            let response = request
+                .call()
+                .map_err(|e| RegistryError::from_http_error(e, url))?;
+
+            // Tokens can expire, so we try to get a new one even if we already have one
+            if response.status() == StatusCode::UNAUTHORIZED && !authenticated {
+                let challenge = response
+                    .headers()
+                    .get("www-authenticate")
+                    .and_then(|header| header.to_str().ok())
+                    .and_then(Challenge::parse)
+                    .ok_or(RegistryError::AuthenticationError(
+                        "the registry responded with an unsupported challenge"
+                            .into(),
+                    ))?;
+                let authorization = self.authenticate(challenge)?;
+                *self
+                    .authorization
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner) =
+                    Some(authorization);
+                authenticated = true;
+                continue;
+            }
+
+            if !response.status().is_success() {
+                return Err(error_from_response(url, response));
+            }
+
+            return Ok(response);
+        }
+    }
+
+    /// Responds to the provided [Challenge], returning the value of the `Authorization` header that should be used.
+    fn authenticate(&self, challenge: Challenge) -> Result<String> {
+        let (realm, service, scope) = match challenge {
+            Challenge::Basic => {
+                return self
+                    .credentials
+                    .as_ref()
+                    .map(Credentials::basic_authorization)
+                    .ok_or(RegistryError::AuthenticationError(
+                        "the registry requires credentials".into(),
+                    ));
+            }
+            Challenge::Bearer {
+                realm,
+                service,
+                scope,
+            } => (realm, service, scope),
+        };
+
+        let scope = scope
+            .unwrap_or_else(|| format!("repository:{}:pull", self.repository));
+        let mut request = self.agent.get(&realm).query("scope", scope);
+        if let Some(service) = service {
+            request = request.query("service", service);
+        }
+        // Anonymous tokens are enough for public images
+        if let Some(credentials) = self.credentials.as_ref() {
+            request = request
+                .header("authorization", credentials.basic_authorization());
+        }
+        let response = request
+            .call()
+            .map_err(|e| RegistryError::from_http_error(e, &realm))?;
+        if !response.status().is_success() {
+            return Err(error_from_response(&realm, response));
+        }
+
+        let token = response
+            .into_body()
+            .with_config()
+            .limit(MAX_MANIFEST_SIZE)
+            .read_to_vec()
+            .map_err(|e| RegistryError::from_http_error(e, &realm))?;
+        let token =
+            serde_json::from_slice::<TokenResponse>(&token).map_err(|e| {
+                RegistryError::from_serde_error_with_description(e, || {
+                    "token response".into()
+                })
+            })?;
+
+        token
+            .token
+            .or(token.access_token)
+            .map(|token| format!("Bearer {token}"))
+            .ok_or(RegistryError::AuthenticationError(
+                "the authorization service didn't return a token".into(),
+            ))
+    }
+}
+
+/// Streams a blob from the registry.
+pub struct BlobReader(BodyReader<'static>);
+
+impl Read for BlobReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+/// A response from the authorization service.
+///
+/// Docker uses `token`, while OAuth2-compatible services use `access_token`.
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+/// An error response from the registry.
+#[derive(Deserialize)]
+struct ErrorResponse {
+    errors: Vec<ErrorEntry>,
+}
+
+#[derive(Deserialize)]
+struct ErrorEntry {
+    code: String,
+    message: Option<String>,
+}
+
+/// Creates a [RegistryError::UnexpectedStatus] using the error details from the response if they are present.
+fn error_from_response(url: &str, response: Response<Body>) -> RegistryError {
+    let status = response.status();
+    let message = response
+        .into_body()
+        .with_config()
+        .limit(MAX_MANIFEST_SIZE)
+        .read_to_vec()
+        .ok()
+        .and_then(|body| serde_json::from_slice::<ErrorResponse>(&body).ok())
+        .map(|response| {
+            response
+                .errors
+                .into_iter()
+                .map(|error| match error.message {
+                    Some(message) => format!("{} ({message})", error.code),
+                    None => error.code,
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .filter(|message| !message.is_empty())
+        .unwrap_or_else(|| {
+            status
+                .canonical_reason()
+                .unwrap_or("unknown error")
+                .to_owned()
+        });
+
+    RegistryError::UnexpectedStatus {
+        url: url.to_owned(),
+        status: status.as_u16(),
+        message,
+    }
+}
+
+/// Checks if the provided host (with an optional port) points to the loopback interface.
+fn is_loopback(host: &str) -> bool {
+    let host = if let Some(host) = host.strip_prefix('[') {
+        // IPv6 addresses are enclosed in brackets
+        host.split_once(']').map_or(host, |(host, _)| host)
+    } else {
+        host.split_once(':').map_or(host, |(host, _)| host)
+    };
+
+    host == "localhost"
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    use super::*;
+
+    const TOKEN: &str = "secret-token";
+
+    /// Starts a minimal registry that requires a Bearer token and serves a single manifest and blob.
+    ///
+    /// Returns the registry's address.
+    fn start_registry(manifest: &'static [u8], blob: &'static [u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let realm = format!("http://{address}/token");
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let path = request_line.split(' ').nth(1).unwrap().to_owned();
+                let mut authorized = false;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    authorized |= header.to_ascii_lowercase().trim()
+                        == format!("authorization: bearer {TOKEN}");
+                }
+
+                let (status, body) = if path.starts_with("/token?") {
+                    assert!(
+                        path.contains("scope=repository%3Ateam%2Fapp%3Apull")
+                    );
+                    ("200 OK", format!(r#"{{"token":"{TOKEN}"}}"#).into_bytes())
+                } else if !authorized {
+                    (
+                        "401 Unauthorized",
+                        br#"{"errors":[{"code":"UNAUTHORIZED"}]}"#.to_vec(),
+                    )
+                } else if path.starts_with("/v2/team/app/manifests/")
+                    && !path.ends_with("/missing")
+                {
+                    ("200 OK", manifest.to_vec())
+                } else if path.starts_with("/v2/team/app/blobs/sha256:") {
+                    ("200 OK", blob.to_vec())
+                } else {
+                    (
+                        "404 Not Found",
+                        br#"{"errors":[{"code":"MANIFEST_UNKNOWN","message":"manifest unknown"}]}"#.to_vec(),
+                    )
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\
+                    WWW-Authenticate: Bearer realm=\"{realm}\",service=\"registry\"\r\n\r\n",
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+
+        address
+    }
+
+    #[test]
+    fn authenticate_and_fetch_from_registry() {
+        let address = start_registry(b"{\"manifest\":true}", b"layer contents");
+        let reference =
+            Reference::parse(&format!("{address}/team/app:v1")).unwrap();
+        let api = RegistryApi::new_with_credentials(&reference, None);
+
+        let manifest =
+            api.get_manifest(reference.manifest_reference()).unwrap();
+        assert_eq!(manifest, b"{\"manifest\":true}");
+
+        let mut blob = String::new();
+        api.get_blob(&format!("sha256:{}", "a".repeat(64)))
+            .unwrap()
+            .read_to_string(&mut blob)
+            .unwrap();
+        assert_eq!(blob, "layer contents");
+
+        let error = api.get_manifest("missing").unwrap_err();
+        assert!(
+            matches!(error, RegistryError::UnexpectedStatus { status: 404, ref message, .. } if message == "MANIFEST_UNKNOWN (manifest unknown)"),
+            "{error:?}"
+        );
+    }
+
+    #[test]
+    fn verify_manifest_digest() {
+        let manifest = b"{\"manifest\":true}";
+        let address = start_registry(manifest, b"");
+        let reference =
+            Reference::parse(&format!("{address}/team/app:v1")).unwrap();
+        let api = RegistryApi::new_with_credentials(&reference, None);
+
+        let digest = Sha256::digest(manifest)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>();
+        assert!(api.get_manifest(&format!("sha256:{digest}")).is_ok());
+
+        let error = api
+            .get_manifest(&format!("sha256:{}", "0".repeat(64)))
+            .unwrap_err();
+        assert!(matches!(error, RegistryError::Other(_)), "{error:?}");
+    }
+
+    #[test]
+    fn detect_loopback_hosts() {
+        assert!(is_loopback("localhost:5000"));
+        assert!(is_loopback("127.0.0.1:5000"));
+        assert!(is_loopback("[::1]:5000"));
+        assert!(!is_loopback("ghcr.io"));
+        assert!(!is_loopback("registry.local:5000"));
+    }
+}
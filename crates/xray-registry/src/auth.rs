@@ -0,0 +1,125 @@
+/// An authentication challenge from the `WWW-Authenticate` header of a registry's response.
+///
+/// Source: [Token Authentication Specification](https://distribution.github.io/distribution/spec/auth/token/)
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Challenge {
+    /// The registry accepts the credentials directly.
+    Basic,
+    /// The registry accepts tokens that are issued by the authorization service at `realm`.
+    Bearer {
+        realm: String,
+        service: Option<String>,
+        scope: Option<String>,
+    },
+}
+
+impl Challenge {
+    /// Parses the value of the `WWW-Authenticate` header.
+    ///
+    /// Returns `None` if the scheme is not supported or the challenge is malformed.
+    pub fn parse(header: &str) -> Option<Self> {
+        let header = header.trim();
+        let (scheme, params) = header
+            .split_once(' ')
+            .unwrap_or((header, Default::default()));
+
+        if scheme.eq_ignore_ascii_case("basic") {
+            return Some(Challenge::Basic);
+        }
+        if !scheme.eq_ignore_ascii_case("bearer") {
+            return None;
+        }
+
+        let (mut realm, mut service, mut scope) = (None, None, None);
+        for (key, value) in parse_params(params)? {
+            match key.to_ascii_lowercase().as_str() {
+                "realm" => realm = Some(value),
+                "service" => service = Some(value),
+                "scope" => scope = Some(value),
+                _ => {}
+            }
+        }
+
+        Some(Challenge::Bearer {
+            realm: realm?,
+            service,
+            scope,
+        })
+    }
+}
+
+/// Parses comma-separated `key=value` parameters of a challenge, where values can be quoted.
+fn parse_params(mut params: &str) -> Option<Vec<(String, String)>> {
+    let mut parsed = Vec::new();
+    loop {
+        params = params.trim_start_matches([' ', ',']);
+        if params.is_empty() {
+            return Some(parsed);
+        }
+
+        let (key, rest) = params.split_once('=')?;
+        let (value, rest) = if let Some(rest) = rest.strip_prefix('"') {
+            // Quoted values can contain commas and escaped quotes
+            let mut value = String::new();
+            let mut chars = rest.char_indices();
+            let end = loop {
+                match chars.next()? {
+                    (_, '\\') => value.push(chars.next()?.1),
+                    (idx, '"') => break idx,
+                    (_, c) => value.push(c),
+                }
+            };
+            (value, &rest[end + 1..])
+        } else {
+            let (value, rest) = rest.split_once(',').unwrap_or((rest, ""));
+            (value.trim().to_owned(), rest)
+        };
+
+        parsed.push((key.trim().to_owned(), value));
+        params = rest;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bearer_challenge() {
+        let challenge = Challenge::parse(
+            r#"Bearer realm="https://auth.docker.io/token",service="registry.docker.io",scope="repository:library/nginx:pull,push""#,
+        );
+        assert_eq!(
+            challenge,
+            Some(Challenge::Bearer {
+                realm: "https://auth.docker.io/token".into(),
+                service: Some("registry.docker.io".into()),
+                // Commas within quoted values must be preserved
+                scope: Some("repository:library/nginx:pull,push".into()),
+            })
+        );
+
+        let challenge =
+            Challenge::parse(r#"bearer realm="http://localhost/t\"oken""#);
+        assert_eq!(
+            challenge,
+            Some(Challenge::Bearer {
+                realm: r#"http://localhost/t"oken"#.into(),
+                service: None,
+                scope: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_other_challenges() {
+        assert_eq!(
+            Challenge::parse(r#"Basic realm="Registry""#),
+            Some(Challenge::Basic)
+        );
+        assert_eq!(Challenge::parse("Negotiate"), None);
+        // The realm is required
+        assert_eq!(Challenge::parse(r#"Bearer service="registry""#), None);
+        assert_eq!(Challenge::parse(r#"Bearer realm="unterminated"#), None);
+    }
+}
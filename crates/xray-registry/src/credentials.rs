@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::env::{self, VarError};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::Deserialize;
+
+use crate::reference::DOCKER_HUB_REGISTRY;
+use crate::{RegistryError, Result};
+
+const DOCKER_CONFIG_DIR_ENV_VAR: &str = "DOCKER_CONFIG";
+const DOCKER_DEFAULT_CONFIG_DIR: &str = ".docker";
+const DOCKER_CONFIG_FILENAME: &str = "config.json";
+/// Docker stores the credentials for Docker Hub under this legacy URL.
+const DOCKER_HUB_SERVER_URL: &str = "https://index.docker.io/v1/";
+const CREDENTIAL_HELPER_PREFIX: &str = "docker-credential-";
+/// Credential helpers return this username if the secret is an identity token instead of a password.
+const IDENTITY_TOKEN_USERNAME: &str = "<token>";
+
+/// Credentials that are used to authenticate with a registry.
+#[derive(Clone)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl Credentials {
+    pub fn new(
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        Credentials {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    /// Looks up the credentials for the provided registry in the Docker config (`$DOCKER_CONFIG/config.json` or
+    /// `~/.docker/config.json`).
+    ///
+    /// Credential helpers (`credHelpers` and `credsStore`) take precedence over the credentials stored in `auths`, just
+    /// like in Docker. Helpers that are not installed are ignored.
+    pub fn from_docker_config(registry: &str) -> Result<Option<Self>> {
+        let Some(config) = DockerConfig::new()? else {
+            return Ok(None);
+        };
+
+        let server_url = if registry == DOCKER_HUB_REGISTRY {
+            DOCKER_HUB_SERVER_URL
+        } else {
+            registry
+        };
+        let helper = config
+            .cred_helpers
+            .get(registry)
+            .or(config.creds_store.as_ref());
+        if let Some(helper) = helper
+            && let Some(credentials) =
+                run_credential_helper(helper, server_url)?
+        {
+            return Ok(Some(credentials));
+        }
+
+        config
+            .auths
+            .iter()
+            .find(|(server, _)| normalize_server(server) == registry)
+            .map(|(_, entry)| entry.credentials())
+            .transpose()
+            .map(Option::flatten)
+    }
+
+    /// Returns the value of the `Authorization` header for the Basic authentication scheme.
+    pub(crate) fn basic_authorization(&self) -> String {
+        format!(
+            "Basic {}",
+            BASE64.encode(format!("{}:{}", self.username, self.password))
+        )
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the password
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A subset of fields from the Docker Config that are needed to authenticate with registries.
+#[derive(Deserialize)]
+struct DockerConfig {
+    #[serde(default)]
+    auths: HashMap<String, AuthEntry>,
+    #[serde(rename = "credsStore")]
+    creds_store: Option<String>,
+    #[serde(rename = "credHelpers", default)]
+    cred_helpers: HashMap<String, String>,
+}
+
+impl DockerConfig {
+    /// Tries to parse the Docker config from the resolved config directory.
+    fn new() -> Result<Option<Self>> {
+        let mut path = match env::var(DOCKER_CONFIG_DIR_ENV_VAR) {
+            Ok(config_dir) => PathBuf::from(config_dir),
+            Err(VarError::NotPresent) => {
+                let Some(mut home_dir) = homedir::my_home()? else {
+                    return Ok(None);
+                };
+                home_dir.push(DOCKER_DEFAULT_CONFIG_DIR);
+                home_dir
+            }
+            Err(VarError::NotUnicode(_)) => {
+                return Err(RegistryError::Other(
+                    format!(
+                        "'{DOCKER_CONFIG_DIR_ENV_VAR}' is not valid unicode"
+                    )
+                    .into(),
+                ));
+            }
+        };
+        path.push(DOCKER_CONFIG_FILENAME);
+
+        let config = match File::open(&path) {
+            Ok(config) => config,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(None);
+            }
+            Err(e) => {
+                return Err(RegistryError::from_io_error_with_description(
+                    e,
+                    || "failed to read the Docker config file".into(),
+                ));
+            }
+        };
+
+        serde_json::from_reader(BufReader::new(config))
+            .map(Some)
+            .map_err(|e| {
+                RegistryError::from_serde_error_with_description(e, || {
+                    "docker config".into()
+                })
+            })
+    }
+}
+
+/// A single entry from the `auths` object of the Docker config.
+#[derive(Deserialize)]
+struct AuthEntry {
+    /// Base64-encoded `username:password`.
+    auth: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl AuthEntry {
+    fn credentials(&self) -> Result<Option<Credentials>> {
+        if let (Some(username), Some(password)) =
+            (&self.username, &self.password)
+        {
+            return Ok(Some(Credentials::new(username, password)));
+        }
+
+        let Some(auth) = self.auth.as_deref().filter(|auth| !auth.is_empty())
+        else {
+            return Ok(None);
+        };
+        let invalid_auth = || {
+            RegistryError::Other(
+                "invalid credentials in the Docker config".into(),
+            )
+        };
+        let decoded = BASE64.decode(auth).map_err(|_| invalid_auth())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| invalid_auth())?;
+        let (username, password) =
+            decoded.split_once(':').ok_or_else(invalid_auth)?;
+
+        Ok(Some(Credentials::new(username, password)))
+    }
+}
+
+/// The output of the `get` command of a credential helper.
+#[derive(Deserialize)]
+struct HelperCredentials {
+    #[serde(rename = "Username")]
+    username: String,
+    #[serde(rename = "Secret")]
+    secret: String,
+}
+
+/// Runs the `docker-credential-<helper>` binary to get the credentials for the provided server.
+///
+/// Returns `None` if the helper is not installed or doesn't have any credentials for the server.
+fn run_credential_helper(
+    helper: &str,
+    server_url: &str,
+) -> Result<Option<Credentials>> {
+    let child = Command::new(format!("{CREDENTIAL_HELPER_PREFIX}{helper}"))
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(RegistryError::from_io_error_with_description(
+                e,
+                || "failed to run the credential helper".into(),
+            ));
+        }
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(server_url.as_bytes()).map_err(|e| {
+            RegistryError::from_io_error_with_description(e, || {
+                "failed to send the server URL to the credential helper".into()
+            })
+        })?;
+    }
+    let output = child.wait_with_output().map_err(|e| {
+        RegistryError::from_io_error_with_description(e, || {
+            "failed to run the credential helper".into()
+        })
+    })?;
+    if !output.status.success() {
+        // Helpers fail if they don't have credentials for the server
+        return Ok(None);
+    }
+
+    let credentials = serde_json::from_slice::<HelperCredentials>(
+        &output.stdout,
+    )
+    .map_err(|e| {
+        RegistryError::from_serde_error_with_description(e, || {
+            "credential helper response".into()
+        })
+    })?;
+    if credentials.username == IDENTITY_TOKEN_USERNAME {
+        // Identity tokens require an OAuth2 flow that registries don't implement consistently
+        return Ok(None);
+    }
+
+    Ok(Some(Credentials::new(
+        credentials.username,
+        credentials.secret,
+    )))
+}
+
+/// Converts a server from the `auths` object of the Docker config to a registry, e.g.
+/// `https://index.docker.io/v1/` to `docker.io`.
+fn normalize_server(server: &str) -> &str {
+    let server = server
+        .strip_prefix("https://")
+        .or_else(|| server.strip_prefix("http://"))
+        .unwrap_or(server);
+    let host = server.split_once('/').map_or(server, |(host, _)| host);
+
+    match host {
+        "index.docker.io" | "registry-1.docker.io" => DOCKER_HUB_REGISTRY,
+        host => host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_docker_config_servers() {
+        assert_eq!(normalize_server(DOCKER_HUB_SERVER_URL), "docker.io");
+        assert_eq!(normalize_server("docker.io"), "docker.io");
+        assert_eq!(normalize_server("https://ghcr.io"), "ghcr.io");
+        assert_eq!(
+            normalize_server("http://localhost:5000/v2/"),
+            "localhost:5000"
+        );
+    }
+
+    #[test]
+    fn credentials_from_auth_entries() {
+        let entry: AuthEntry =
+            serde_json::from_str(r#"{"auth": "dXNlcjpwYTpzcw=="}"#).unwrap();
+        let credentials = entry.credentials().unwrap().unwrap();
+        assert_eq!(credentials.username, "user");
+        // Passwords can contain colons
+        assert_eq!(credentials.password, "pa:ss");
+
+        let entry: AuthEntry =
+            serde_json::from_str(r#"{"username": "user", "password": "pass"}"#)
+                .unwrap();
+        let credentials = entry.credentials().unwrap().unwrap();
+        assert_eq!(credentials.basic_authorization(), "Basic dXNlcjpwYXNz");
+
+        let entry: AuthEntry = serde_json::from_str(r#"{"auth": ""}"#).unwrap();
+        assert!(entry.credentials().unwrap().is_none());
+
+        let entry: AuthEntry =
+            serde_json::from_str(r#"{"auth": "not base64"}"#).unwrap();
+        assert!(entry.credentials().is_err());
+    }
+}
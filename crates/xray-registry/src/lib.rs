@@ -0,0 +1,82 @@
+use std::borrow::Cow;
+
+use thiserror::Error;
+
+mod api;
+mod auth;
+mod credentials;
+mod reference;
+
+pub use api::{BlobReader, RegistryApi};
+pub use credentials::Credentials;
+pub use reference::Reference;
+
+pub type Result<T> = std::result::Result<T, RegistryError>;
+
+#[derive(Error, Debug)]
+pub enum RegistryError {
+    #[error("invalid image reference '{reference}': {reason}")]
+    InvalidReference {
+        reference: String,
+        reason: Cow<'static, str>,
+    },
+    #[error("failed to send a request to '{url}'")]
+    HttpError {
+        url: String,
+        #[source]
+        source: Box<ureq::Error>,
+    },
+    #[error("the registry responded with {status} to '{url}': {message}")]
+    UnexpectedStatus {
+        url: String,
+        status: u16,
+        message: String,
+    },
+    #[error("failed to authenticate with the registry: {0}")]
+    AuthenticationError(Cow<'static, str>),
+    #[error("failed to get the home directory of the current user")]
+    GetHomeError(#[from] homedir::GetHomeError),
+    #[error("failed to perform an I/O operation: {description}")]
+    IoError {
+        description: Cow<'static, str>,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to deserialize a JSON {description}")]
+    JsonDeserializationError {
+        description: Cow<'static, str>,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("{0}")]
+    Other(Cow<'static, str>),
+}
+
+impl RegistryError {
+    fn from_http_error(source: ureq::Error, url: impl Into<String>) -> Self {
+        RegistryError::HttpError {
+            url: url.into(),
+            source: Box::new(source),
+        }
+    }
+
+    fn from_io_error_with_description(
+        source: std::io::Error,
+        description: impl Fn() -> Cow<'static, str>,
+    ) -> RegistryError {
+        RegistryError::IoError {
+            description: description(),
+            source,
+        }
+    }
+
+    fn from_serde_error_with_description(
+        source: serde_json::Error,
+        description: impl Fn() -> Cow<'static, str>,
+    ) -> RegistryError {
+        RegistryError::JsonDeserializationError {
+            description: description(),
+            source,
+        }
+    }
+}
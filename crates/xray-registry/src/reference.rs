@@ -0,0 +1,254 @@
+use std::fmt;
+
+use crate::{RegistryError, Result};
+
+/// The registry that is used for images without an explicit registry.
+pub(crate) const DOCKER_HUB_REGISTRY: &str = "docker.io";
+/// The host that serves the Docker Hub's registry API.
+const DOCKER_HUB_API_HOST: &str = "registry-1.docker.io";
+/// Other names that are used to refer to Docker Hub.
+const DOCKER_HUB_ALIASES: [&str; 2] = ["index.docker.io", DOCKER_HUB_API_HOST];
+/// Repositories of official images on Docker Hub are stored under this namespace.
+const DOCKER_HUB_OFFICIAL_NAMESPACE: &str = "library/";
+const DEFAULT_TAG: &str = "latest";
+const SHA256_DIGEST_PREFIX: &str = "sha256:";
+const SHA256_DIGEST_HEX_LENGTH: usize = 64;
+const MAX_TAG_LENGTH: usize = 128;
+
+/// A reference to an image stored in a registry, e.g. `ghcr.io/owner/image:tag` or `nginx@sha256:...`.
+///
+/// Follows the same rules as Docker: references without a registry point to Docker Hub, while single-component
+/// repositories on Docker Hub are official images from the `library` namespace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    registry: String,
+    repository: String,
+    tag: Option<String>,
+    digest: Option<String>,
+}
+
+impl Reference {
+    /// Parses the provided image reference.
+    pub fn parse(image: &str) -> Result<Self> {
+        let invalid = |reason: &'static str| RegistryError::InvalidReference {
+            reference: image.to_owned(),
+            reason: reason.into(),
+        };
+
+        let (name, digest) = match image.split_once('@') {
+            Some((name, digest)) => (name, Some(digest)),
+            None => (image, None),
+        };
+        // Only the last component can have a tag, as the registry can contain a port
+        let (name, tag) = match name.rsplit_once(':') {
+            Some((name, tag)) if !tag.contains('/') => (name, Some(tag)),
+            _ => (name, None),
+        };
+        let (registry, repository) = match name.split_once('/') {
+            Some((registry, repository))
+                if registry.contains(['.', ':']) || registry == "localhost" =>
+            {
+                (registry, repository)
+            }
+            _ => (DOCKER_HUB_REGISTRY, name),
+        };
+        let registry = if DOCKER_HUB_ALIASES.contains(&registry) {
+            DOCKER_HUB_REGISTRY
+        } else {
+            registry
+        };
+
+        if !repository.split('/').all(is_valid_path_component) {
+            return Err(invalid(
+                "the repository must consist of lowercase alphanumeric components separated by '/'",
+            ));
+        }
+        if let Some(tag) = tag
+            && (tag.is_empty()
+                || tag.len() > MAX_TAG_LENGTH
+                || tag.starts_with(['.', '-'])
+                || !tag.chars().all(|c| {
+                    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')
+                }))
+        {
+            return Err(invalid("the tag contains invalid characters"));
+        }
+        if let Some(digest) = digest
+            && !digest
+                .strip_prefix(SHA256_DIGEST_PREFIX)
+                .is_some_and(|hex| {
+                    hex.len() == SHA256_DIGEST_HEX_LENGTH
+                        && hex
+                            .chars()
+                            .all(|c| matches!(c, '0'..='9' | 'a'..='f'))
+                })
+        {
+            return Err(invalid("only SHA256 digests are supported"));
+        }
+
+        let repository =
+            if registry == DOCKER_HUB_REGISTRY && !repository.contains('/') {
+                format!("{DOCKER_HUB_OFFICIAL_NAMESPACE}{repository}")
+            } else {
+                repository.to_owned()
+            };
+
+        Ok(Reference {
+            registry: registry.to_owned(),
+            repository,
+            tag: tag.map(ToOwned::to_owned),
+            digest: digest.map(ToOwned::to_owned),
+        })
+    }
+
+    /// Returns the registry of the image (e.g. `docker.io` or `localhost:5000`).
+    pub fn registry(&self) -> &str {
+        &self.registry
+    }
+
+    /// Returns the repository of the image within the registry (e.g. `library/nginx`).
+    pub fn repository(&self) -> &str {
+        &self.repository
+    }
+
+    /// Returns a short name of the image without the registry and the `library` namespace of Docker Hub.
+    pub fn name(&self) -> &str {
+        if self.registry == DOCKER_HUB_REGISTRY {
+            self.repository
+                .strip_prefix(DOCKER_HUB_OFFICIAL_NAMESPACE)
+                .unwrap_or(&self.repository)
+        } else {
+            &self.repository
+        }
+    }
+
+    /// Returns the tag of the image, which is `latest` if neither a tag nor a digest were provided.
+    pub fn tag(&self) -> Option<&str> {
+        match (&self.tag, &self.digest) {
+            (Some(tag), _) => Some(tag),
+            (None, Some(_)) => None,
+            (None, None) => Some(DEFAULT_TAG),
+        }
+    }
+
+    /// Returns the digest of the image if it was provided.
+    pub fn digest(&self) -> Option<&str> {
+        self.digest.as_deref()
+    }
+
+    /// Returns the tag or digest that should be used to fetch the image's manifest.
+    ///
+    /// The digest takes precedence over the tag, as it's immutable.
+    pub fn manifest_reference(&self) -> &str {
+        self.digest
+            .as_deref()
+            .or(self.tag.as_deref())
+            .unwrap_or(DEFAULT_TAG)
+    }
+
+    /// Returns the host that serves the registry's API.
+    pub(crate) fn api_host(&self) -> &str {
+        if self.registry == DOCKER_HUB_REGISTRY {
+            DOCKER_HUB_API_HOST
+        } else {
+            &self.registry
+        }
+    }
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.registry, self.repository)?;
+        if let Some(tag) = self.tag() {
+            write!(f, ":{tag}")?;
+        }
+        if let Some(digest) = self.digest() {
+            write!(f, "@{digest}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks if the provided string is a valid path component of a repository.
+fn is_valid_path_component(component: &str) -> bool {
+    !component.is_empty()
+        && component.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && component.ends_with(|c: char| c.is_ascii_alphanumeric())
+        && component.chars().all(|c| {
+            c.is_ascii_lowercase()
+                || c.is_ascii_digit()
+                || matches!(c, '.' | '_' | '-')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn docker_hub_references() {
+        let reference = Reference::parse("nginx").unwrap();
+        assert_eq!(reference.registry(), "docker.io");
+        assert_eq!(reference.repository(), "library/nginx");
+        assert_eq!(reference.name(), "nginx");
+        assert_eq!(reference.manifest_reference(), "latest");
+        assert_eq!(reference.api_host(), "registry-1.docker.io");
+
+        let reference = Reference::parse("grafana/grafana:11.0.0").unwrap();
+        assert_eq!(reference.repository(), "grafana/grafana");
+        assert_eq!(reference.manifest_reference(), "11.0.0");
+
+        let reference = Reference::parse("index.docker.io/nginx:1").unwrap();
+        assert_eq!(reference.to_string(), "docker.io/library/nginx:1");
+    }
+
+    #[test]
+    fn references_with_registry() {
+        let reference =
+            Reference::parse("localhost:5000/team/app:v1.2").unwrap();
+        assert_eq!(reference.registry(), "localhost:5000");
+        assert_eq!(reference.repository(), "team/app");
+        assert_eq!(reference.name(), "team/app");
+        assert_eq!(reference.tag(), Some("v1.2"));
+
+        // The port must not be mistaken for a tag
+        let reference = Reference::parse("localhost:5000/app").unwrap();
+        assert_eq!(reference.registry(), "localhost:5000");
+        assert_eq!(reference.tag(), Some("latest"));
+
+        let reference = Reference::parse("ghcr.io/owner/image").unwrap();
+        assert_eq!(reference.registry(), "ghcr.io");
+        assert_eq!(reference.repository(), "owner/image");
+    }
+
+    #[test]
+    fn references_with_digest() {
+        let digest = format!("sha256:{}", "a".repeat(64));
+        let reference =
+            Reference::parse(&format!("ghcr.io/owner/image@{digest}")).unwrap();
+        assert_eq!(reference.tag(), None);
+        assert_eq!(reference.digest(), Some(digest.as_str()));
+        assert_eq!(reference.manifest_reference(), digest);
+
+        let reference =
+            Reference::parse(&format!("image:tag@{digest}")).unwrap();
+        assert_eq!(reference.tag(), Some("tag"));
+        assert_eq!(reference.manifest_reference(), digest);
+    }
+
+    #[test]
+    fn invalid_references() {
+        for image in [
+            "",
+            "/tmp/image.tar",
+            "Uppercase",
+            "image:",
+            "image:-tag",
+            "image@sha256:abc",
+            "image@md5:0123",
+            "ghcr.io/",
+        ] {
+            assert!(Reference::parse(image).is_err(), "{image}");
+        }
+    }
+}
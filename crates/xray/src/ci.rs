@@ -0,0 +1,496 @@
+//! Contains the non-interactive mode that prints a summary of the image and checks it against the configured rules.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::{Deserialize, Deserializer};
+
+use crate::config::parse_size;
+use crate::parser::{Image, ImageEfficiency, LayerChangeSet, Node};
+use crate::tui::util::Unit;
+
+/// The maximum number of the largest files that are printed in the summary.
+const LARGEST_FILES_LIMIT: usize = 10;
+/// The maximum number of paths that waste space that are printed in the summary.
+const WASTED_PATHS_LIMIT: usize = 10;
+/// Commands that created the layers are truncated to this number of characters.
+const MAX_COMMAND_LENGTH: usize = 80;
+
+/// Thresholds that an image must not exceed when running in the CI mode.
+///
+/// Rules can be set both using the CLI flags and a TOML file, in which case the flags take precedence.
+#[derive(clap::Args, Deserialize, Debug, Default, Clone)]
+// Prevents clap from using this doc comment as the app's description
+#[command(about = None, long_about = None)]
+#[serde(deny_unknown_fields)]
+pub struct CiRules {
+    /// Fail if the total size of the image exceeds this value.
+    ///
+    /// Format: a number of bytes with an optional unit (B, kB, MB, GB), e.g. 500MB
+    #[arg(long, value_parser = parse_size, requires = "ci")]
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub max_image_size: Option<u64>,
+    /// Fail if the size of any layer exceeds this value.
+    ///
+    /// Format: a number of bytes with an optional unit (B, kB, MB, GB), e.g. 100MB
+    #[arg(long, value_parser = parse_size, requires = "ci")]
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub max_layer_size: Option<u64>,
+    /// Fail if the image has more layers than this.
+    #[arg(long, requires = "ci")]
+    #[serde(default)]
+    pub max_layers: Option<usize>,
+    /// Fail if the efficiency score of the image is lower than this percentage.
+    ///
+    /// Format: a number from 0 to 100
+    #[arg(long, value_parser = parse_percentage, requires = "ci")]
+    #[serde(default, deserialize_with = "deserialize_percentage")]
+    pub min_efficiency: Option<f64>,
+    /// Fail if the image wastes more space than this value.
+    ///
+    /// Format: a number of bytes with an optional unit (B, kB, MB, GB), e.g. 10MB
+    #[arg(long, value_parser = parse_size, requires = "ci")]
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub max_wasted_size: Option<u64>,
+}
+
+impl CiRules {
+    /// Reads the rules from a TOML file.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let rules = std::fs::read_to_string(path)
+            .context("failed to read the rules file")?;
+        toml::from_str(&rules).context("failed to parse the rules file")
+    }
+
+    /// Returns the rules that are set in `self` and falls back to the ones from `fallback` for the missing ones.
+    pub fn or(self, fallback: CiRules) -> CiRules {
+        CiRules {
+            max_image_size: self.max_image_size.or(fallback.max_image_size),
+            max_layer_size: self.max_layer_size.or(fallback.max_layer_size),
+            max_layers: self.max_layers.or(fallback.max_layers),
+            min_efficiency: self.min_efficiency.or(fallback.min_efficiency),
+            max_wasted_size: self.max_wasted_size.or(fallback.max_wasted_size),
+        }
+    }
+}
+
+/// The result of checking a single rule.
+struct Check {
+    description: String,
+    passed: bool,
+}
+
+impl Check {
+    fn new(passed: bool, description: String) -> Self {
+        Check {
+            description,
+            passed,
+        }
+    }
+}
+
+/// Prints a summary of the image and checks it against the provided [CiRules].
+///
+/// Returns an error if any of the rules is broken.
+pub fn run_ci(image: &Image, rules: &CiRules) -> anyhow::Result<()> {
+    println!("Image: {}:{}", image.image_name, image.tag);
+    println!("Total size: {}", format_size(image.size));
+
+    println!();
+    println!("Layers ({}):", image.layers.len());
+    for (idx, (_, layer)) in image.layers.iter().enumerate() {
+        println!(
+            "  {:>3}  {:>9}  {}",
+            idx + 1,
+            format_size(layer.size),
+            truncate_command(&layer.created_by)
+        );
+    }
+
+    let aggregated_changeset = image
+        .layers
+        .values()
+        .filter_map(|layer| layer.changeset.clone())
+        .reduce(LayerChangeSet::merge);
+    if let Some(aggregated_changeset) = aggregated_changeset.as_ref() {
+        let mut files = Vec::new();
+        collect_files(
+            aggregated_changeset,
+            &mut PathBuf::from("/"),
+            &mut files,
+        );
+        files.sort_by(|(a_size, a_path), (b_size, b_path)| {
+            b_size.cmp(a_size).then_with(|| a_path.cmp(b_path))
+        });
+
+        println!();
+        println!("Largest files:");
+        for (size, path) in files.iter().take(LARGEST_FILES_LIMIT) {
+            println!("  {:>9}  {}", format_size(*size), path.display());
+        }
+    }
+
+    let efficiency = image.efficiency();
+    println!();
+    println!(
+        "Wasted space: {} in {} paths (efficiency score: {:.1} %)",
+        format_size(efficiency.wasted_bytes),
+        efficiency.inefficiencies.len(),
+        efficiency.score * 100.0
+    );
+    for inefficiency in
+        efficiency.inefficiencies.iter().take(WASTED_PATHS_LIMIT)
+    {
+        let layers = inefficiency
+            .layers
+            .iter()
+            .map(|layer_idx| (layer_idx + 1).to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        println!(
+            "  {:>9}  {:<11}  {:<8}  {}",
+            format_size(inefficiency.wasted_bytes),
+            inefficiency.kind.name(),
+            layers,
+            inefficiency.path.display()
+        );
+    }
+
+    let checks = check_rules(image, &efficiency, rules);
+    if checks.is_empty() {
+        return Ok(());
+    }
+
+    println!();
+    println!("Rules:");
+    for check in checks.iter() {
+        let status = if check.passed { "PASS" } else { "FAIL" };
+        println!("  {}  {}", status, check.description);
+    }
+
+    let failed_checks = checks.iter().filter(|check| !check.passed).count();
+    if failed_checks != 0 {
+        anyhow::bail!("{} out of {} checks failed", failed_checks, checks.len())
+    }
+
+    Ok(())
+}
+
+/// Checks the image and its [ImageEfficiency] against the provided [CiRules].
+///
+/// Returns a [Check] for each rule that is set, or for each layer in case of per-layer rules.
+fn check_rules(
+    image: &Image,
+    efficiency: &ImageEfficiency,
+    rules: &CiRules,
+) -> Vec<Check> {
+    let mut checks = Vec::new();
+    if let Some(max_image_size) = rules.max_image_size {
+        checks.push(Check::new(
+            image.size <= max_image_size,
+            format!(
+                "image size is {} (max: {})",
+                format_size(image.size),
+                format_size(max_image_size)
+            ),
+        ));
+    }
+    if let Some(max_layer_size) = rules.max_layer_size {
+        for (idx, (_, layer)) in image.layers.iter().enumerate() {
+            checks.push(Check::new(
+                layer.size <= max_layer_size,
+                format!(
+                    "layer {} size is {} (max: {})",
+                    idx + 1,
+                    format_size(layer.size),
+                    format_size(max_layer_size)
+                ),
+            ));
+        }
+    }
+    if let Some(max_layers) = rules.max_layers {
+        checks.push(Check::new(
+            image.layers.len() <= max_layers,
+            format!(
+                "image has {} layers (max: {})",
+                image.layers.len(),
+                max_layers
+            ),
+        ));
+    }
+    if let Some(min_efficiency) = rules.min_efficiency {
+        let efficiency = efficiency.score * 100.0;
+        checks.push(Check::new(
+            efficiency >= min_efficiency,
+            format!(
+                "efficiency score is {:.1} % (min: {:.1} %)",
+                efficiency, min_efficiency
+            ),
+        ));
+    }
+    if let Some(max_wasted_size) = rules.max_wasted_size {
+        checks.push(Check::new(
+            efficiency.wasted_bytes <= max_wasted_size,
+            format!(
+                "wasted space is {} (max: {})",
+                format_size(efficiency.wasted_bytes),
+                format_size(max_wasted_size)
+            ),
+        ));
+    }
+
+    checks
+}
+
+/// Collects the sizes and full paths of all files that are present in the provided tree.
+fn collect_files(
+    node: &Node,
+    path: &mut PathBuf,
+    files: &mut Vec<(u64, PathBuf)>,
+) {
+    let Some(children) = node.inner.children() else {
+        return;
+    };
+
+    for (name, child) in children.iter() {
+        path.push(name);
+        if child.inner.is_dir() {
+            collect_files(child, path, files);
+        } else if !child.inner.is_deleted() {
+            files.push((child.inner.size(), path.clone()));
+        }
+        path.pop();
+    }
+}
+
+/// Formats the provided number of bytes using the most suitable [Unit].
+fn format_size(bytes: u64) -> String {
+    let (size, unit) = Unit::bytes_to_human_readable_units(bytes);
+    format!("{:.1} {}", size, unit.human_readable())
+}
+
+/// Returns the first line of the command, truncated to [MAX_COMMAND_LENGTH] characters.
+fn truncate_command(command: &str) -> String {
+    let command = command.lines().next().unwrap_or_default().trim();
+    if command.chars().count() <= MAX_COMMAND_LENGTH {
+        return command.to_string();
+    }
+
+    let mut truncated = command
+        .chars()
+        .take(MAX_COMMAND_LENGTH - 3)
+        .collect::<String>();
+    truncated.push_str("...");
+    truncated
+}
+
+/// Parses a percentage from `0` to `100`.
+fn parse_percentage(percentage: &str) -> anyhow::Result<f64> {
+    let percentage = percentage
+        .trim()
+        .trim_end_matches('%')
+        .parse::<f64>()
+        .with_context(|| format!("invalid percentage: '{}'", percentage))?;
+    if !(0.0..=100.0).contains(&percentage) {
+        anyhow::bail!("percentage must be between 0 and 100")
+    }
+
+    Ok(percentage)
+}
+
+/// Deserializes a percentage from `0` to `100`.
+fn deserialize_percentage<'de, D>(
+    deserializer: D,
+) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let percentage = f64::deserialize(deserializer)?;
+    if !(0.0..=100.0).contains(&percentage) {
+        return Err(serde::de::Error::custom(
+            "percentage must be between 0 and 100",
+        ));
+    }
+
+    Ok(Some(percentage))
+}
+
+/// Deserializes a size that is either a number of bytes or a string that is accepted by [parse_size].
+fn deserialize_size<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(u64),
+        WithUnit(String),
+    }
+
+    match Size::deserialize(deserializer)? {
+        Size::Bytes(bytes) => Ok(Some(bytes)),
+        Size::WithUnit(size) => parse_size(&size)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::parser::test_util::{Entry, make_image};
+
+    /// Creates an image of 350 bytes with 3 layers, which wastes 150 bytes (an efficiency score of ~57.1 %).
+    fn make_test_image() -> Image {
+        make_image(
+            "app",
+            &[
+                (1, &[Entry::File("a", 100), Entry::File("b", 50)]),
+                (2, &[Entry::File("a", 200)]),
+                (3, &[Entry::Whiteout("b")]),
+            ],
+        )
+    }
+
+    fn check_results(image: &Image, rules: &CiRules) -> Vec<(bool, String)> {
+        check_rules(image, &image.efficiency(), rules)
+            .into_iter()
+            .map(|check| (check.passed, check.description))
+            .collect()
+    }
+
+    fn rules_from_toml(rules: &str) -> anyhow::Result<CiRules> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(rules.as_bytes()).unwrap();
+        CiRules::from_file(file.path())
+    }
+
+    // --- CiRules ---
+
+    #[test]
+    fn flags_take_precedence_over_file() {
+        let flags = CiRules {
+            max_image_size: Some(1),
+            min_efficiency: Some(90.0),
+            ..Default::default()
+        };
+        let file = CiRules {
+            max_image_size: Some(2),
+            max_layer_size: Some(3),
+            min_efficiency: Some(50.0),
+            ..Default::default()
+        };
+
+        let rules = flags.or(file);
+        assert_eq!(rules.max_image_size, Some(1));
+        assert_eq!(rules.max_layer_size, Some(3));
+        assert_eq!(rules.max_layers, None);
+        assert_eq!(rules.min_efficiency, Some(90.0));
+        assert_eq!(rules.max_wasted_size, None);
+    }
+
+    #[test]
+    fn rules_from_file() {
+        let rules = rules_from_toml(
+            r#"
+            max_image_size = "1.5 MB"
+            max_layer_size = 1024
+            max_layers = 10
+            min_efficiency = 95
+            max_wasted_size = "10kB"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(rules.max_image_size, Some(1_500_000));
+        assert_eq!(rules.max_layer_size, Some(1024));
+        assert_eq!(rules.max_layers, Some(10));
+        assert_eq!(rules.min_efficiency, Some(95.0));
+        assert_eq!(rules.max_wasted_size, Some(10_000));
+
+        let rules = rules_from_toml("").unwrap();
+        assert!(rules.max_image_size.is_none());
+        assert!(rules.min_efficiency.is_none());
+    }
+
+    #[test]
+    fn invalid_rules_from_file() {
+        for rules in [
+            "max_layer = 10",
+            "max_image_size = \"10 TB\"",
+            "max_image_size = -1",
+            "max_layers = \"10\"",
+            "min_efficiency = 100.5",
+            "min_efficiency = -1",
+        ] {
+            assert!(rules_from_toml(rules).is_err(), "{rules}");
+        }
+    }
+
+    #[test]
+    fn percentage_bounds() {
+        assert_eq!(parse_percentage("0").unwrap(), 0.0);
+        assert_eq!(parse_percentage("100").unwrap(), 100.0);
+        assert_eq!(parse_percentage(" 95.5% ").unwrap(), 95.5);
+        for invalid in ["-0.1", "100.1", "", "high"] {
+            assert!(parse_percentage(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    // --- check_rules ---
+
+    #[test]
+    fn no_rules_no_checks() {
+        let image = make_test_image();
+        assert!(check_results(&image, &CiRules::default()).is_empty());
+    }
+
+    #[test]
+    fn passing_rules() {
+        let image = make_test_image();
+        let rules = CiRules {
+            max_image_size: Some(350),
+            max_layer_size: Some(200),
+            max_layers: Some(3),
+            min_efficiency: Some(57.0),
+            max_wasted_size: Some(150),
+        };
+
+        let results = check_results(&image, &rules);
+        // One check per layer for the layer size
+        assert_eq!(results.len(), 7);
+        assert!(results.iter().all(|(passed, _)| *passed), "{results:?}");
+    }
+
+    #[test]
+    fn failing_rules() {
+        let image = make_test_image();
+        let rules = CiRules {
+            max_image_size: Some(349),
+            max_layer_size: Some(150),
+            max_layers: Some(2),
+            min_efficiency: Some(58.0),
+            max_wasted_size: Some(149),
+        };
+
+        let results = check_results(&image, &rules);
+        let failed = results
+            .iter()
+            .filter(|(passed, _)| !passed)
+            .map(|(_, description)| description.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            failed,
+            vec![
+                "image size is 350.0 B (max: 349.0 B)",
+                "layer 2 size is 200.0 B (max: 150.0 B)",
+                "image has 3 layers (max: 2)",
+                "efficiency score is 57.1 % (min: 58.0 %)",
+                "wasted space is 150.0 B (max: 149.0 B)",
+            ]
+        );
+        // The other layers are within the limit
+        assert_eq!(results.len(), 7);
+    }
+}
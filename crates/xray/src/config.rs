@@ -1,9 +1,16 @@
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 
 use anyhow::Context;
 use clap::Parser;
 use dirs::{config_dir, state_dir};
 
+use crate::ci::CiRules;
+use crate::export::ExportOptions;
+use crate::extract::ExtractOptions;
+use crate::parser::Platform;
+use crate::tui::util::Unit;
+
 #[derive(clap::Args)]
 #[group(required = false, multiple = false)]
 struct ClapImageSource {
@@ -16,6 +23,9 @@ struct ClapImageSource {
     /// Force image resolution using Podman.
     #[arg(long = "podman")]
     force_podman: bool,
+    /// Force image resolution by pulling the image directly from its registry.
+    #[arg(long = "registry")]
+    force_registry: bool,
 }
 
 impl ClapImageSource {
@@ -26,33 +36,114 @@ impl ClapImageSource {
             ImageSource::ForceFS
         } else if self.force_podman {
             ImageSource::ForcePodman
+        } else if self.force_registry {
+            ImageSource::ForceRegistry
         } else {
             ImageSource::Default
         }
     }
 }
 
-#[derive(Parser)]
-#[command(version, about)]
-struct Arg {
+#[derive(clap::Args)]
+struct ClapImageOptions {
     /// Override the config directory location.
     ///
     /// Default: $XDG_CONFIG_HOME/xray or $HOME/.config/xray
     #[arg(short = 'p', long)]
     config_path: Option<PathBuf>,
-    // TODO: implement layer caching
-    // #[arg(short = 'c', long, default_value_t = true)]
-    // cache_layers: bool,
     #[clap(flatten)]
     image_source: ClapImageSource,
-    #[arg()]
-    image: String,
+    /// Select the image for this platform from multi-platform images.
+    ///
+    /// Format: os/arch[/variant]. Default: the host platform
+    #[arg(long)]
+    platform: Option<Platform>,
+    /// Verify the digests of the image's layers while parsing them.
+    ///
+    /// Layers that fail verification are marked in the "Layers" pane. If stdout is not a terminal, the results are
+    /// printed instead of starting the UI, and the run fails if any layer didn't pass verification
+    #[arg(long)]
+    verify: bool,
+    /// The number of threads used to parse the image's layers.
+    ///
+    /// Default: the number of available CPU cores
+    #[arg(short = 't', long)]
+    threads: Option<NonZeroUsize>,
+    /// Don't read or write the layer cache.
+    ///
+    /// Parsed compressed layers are cached in $XDG_STATE_HOME/xray/cache or $HOME/.local/state/xray/cache, so that
+    /// they don't need to be parsed again when another image uses them
+    #[arg(long)]
+    no_cache: bool,
+    /// Remove all cached layers before parsing the image
+    #[arg(long)]
+    clear_cache: bool,
+}
+
+#[derive(clap::Subcommand)]
+enum ClapCommand {
+    /// Export the parsed image (its layers and their file trees) in a machine-readable format.
+    Export {
+        #[clap(flatten)]
+        options: ClapImageOptions,
+        #[clap(flatten)]
+        export_options: ExportOptions,
+        #[arg()]
+        image: String,
+    },
+    /// Extract a file or a directory from the image to the local disk.
+    ///
+    /// Modes and symlinks are preserved, while files that were deleted in the image are skipped.
+    Extract {
+        #[clap(flatten)]
+        options: ClapImageOptions,
+        #[arg()]
+        image: String,
+        #[clap(flatten)]
+        extract_options: ExtractOptions,
+    },
+}
+
+#[derive(Parser)]
+#[command(
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Arg {
+    #[command(subcommand)]
+    command: Option<ClapCommand>,
+    #[clap(flatten)]
+    options: ClapImageOptions,
+    /// Print a summary of the image instead of starting the UI.
+    ///
+    /// The summary contains the size of the image and its layers, the largest files, and the wasted space. The run
+    /// fails if the image breaks any of the provided rules
+    #[arg(long)]
+    ci: bool,
+    /// Read the rules for the CI mode from this TOML file.
+    ///
+    /// The file uses the same keys as the flags, e.g. `max_image_size = "500MB"` or `min_efficiency = 95`. Flags
+    /// take precedence over the values from the file
+    #[arg(long, requires = "ci")]
+    ci_rules: Option<PathBuf>,
+    #[clap(flatten)]
+    rules: CiRules,
+    #[arg(required = true)]
+    image: Option<String>,
+    /// Compare the image with this one instead of inspecting it.
+    ///
+    /// The "Layer Changes" pane shows the files that were added, removed, or modified in this image compared to the
+    /// first one, while the "Configuration" pane shows which layers are shared between them
+    #[arg(conflicts_with = "ci")]
+    compare_to: Option<String>,
 }
 
 /// Used to configure the provided image's source.
 #[derive(Debug, Clone, Copy)]
 pub enum ImageSource {
-    /// Try to read to read the image from FS, then Docker, then Podman.
+    /// Try to read to read the image from FS, then Docker, then Podman, then pull it from the registry.
     Default,
     /// Try Docker, don't try reading the image from anywhere else.
     ForceDocker,
@@ -60,23 +151,76 @@ pub enum ImageSource {
     ForceFS,
     /// Try Podman, don't try reading the image from anywhere else.
     ForcePodman,
+    /// Pull the image from the registry, don't try reading the image from anywhere else.
+    ForceRegistry,
+}
+
+/// What should be done with the image.
+#[derive(Debug)]
+pub enum Command {
+    /// Inspect the image using the UI.
+    Inspect,
+    /// Export the image instead of starting the UI.
+    Export(ExportOptions),
+    /// Extract a node from the image instead of starting the UI.
+    Extract(ExtractOptions),
 }
 
 #[derive(Debug)]
 pub struct Config {
+    command: Command,
     config_path: PathBuf,
     state_path: PathBuf,
     image: String,
+    compare_to: Option<String>,
     image_source: ImageSource,
+    platform: Option<Platform>,
+    verify: bool,
+    threads: NonZeroUsize,
+    cache_path: PathBuf,
+    use_cache: bool,
+    clear_cache: bool,
+    ci: bool,
+    ci_rules: CiRules,
 }
 
 impl Config {
     pub fn new() -> anyhow::Result<Self> {
         let Arg {
-            config_path,
+            command,
+            options,
+            ci,
+            ci_rules,
+            rules,
             image,
-            image_source,
+            compare_to,
         } = Arg::parse();
+        let (command, options, image) = match command {
+            Some(ClapCommand::Export {
+                options,
+                export_options,
+                image,
+            }) => (Command::Export(export_options), options, image),
+            Some(ClapCommand::Extract {
+                options,
+                image,
+                extract_options,
+            }) => (Command::Extract(extract_options), options, image),
+            None => (
+                Command::Inspect,
+                options,
+                image.context("bug: clap should've required the image")?,
+            ),
+        };
+        let ClapImageOptions {
+            config_path,
+            image_source,
+            platform,
+            verify,
+            threads,
+            no_cache,
+            clear_cache,
+        } = options;
         let image_source = image_source.into_enum();
 
         let config_path = config_path
@@ -91,11 +235,32 @@ impl Config {
         std::fs::create_dir_all(&state_path)
             .context("failed to create the state directory")?;
 
+        let cache_path = state_path.join("cache");
+
+        let ci_rules = match ci_rules {
+            Some(path) => {
+                rules.or(CiRules::from_file(&path).with_context(|| {
+                    format!("failed to load the CI rules from {path:?}")
+                })?)
+            }
+            None => rules,
+        };
+
         Ok(Config {
+            command,
             config_path,
             state_path,
             image,
+            compare_to,
             image_source,
+            platform,
+            verify,
+            threads: threads.unwrap_or_else(default_threads),
+            cache_path,
+            use_cache: !no_cache,
+            clear_cache,
+            ci,
+            ci_rules,
         })
     }
 
@@ -105,6 +270,10 @@ impl Config {
         config_path
     }
 
+    pub fn command(&self) -> &Command {
+        &self.command
+    }
+
     pub fn config_path(&self) -> &Path {
         &self.config_path
     }
@@ -117,9 +286,45 @@ impl Config {
         &self.image
     }
 
+    pub fn compare_to(&self) -> Option<&str> {
+        self.compare_to.as_deref()
+    }
+
     pub fn image_source(&self) -> ImageSource {
         self.image_source
     }
+
+    pub fn platform(&self) -> Option<&Platform> {
+        self.platform.as_ref()
+    }
+
+    pub fn verify(&self) -> bool {
+        self.verify
+    }
+
+    pub fn threads(&self) -> NonZeroUsize {
+        self.threads
+    }
+
+    pub fn cache_path(&self) -> &Path {
+        &self.cache_path
+    }
+
+    pub fn use_cache(&self) -> bool {
+        self.use_cache
+    }
+
+    pub fn clear_cache(&self) -> bool {
+        self.clear_cache
+    }
+
+    pub fn ci(&self) -> bool {
+        self.ci
+    }
+
+    pub fn ci_rules(&self) -> &CiRules {
+        &self.ci_rules
+    }
 }
 
 fn default_config_path() -> Option<PathBuf> {
@@ -129,9 +334,43 @@ fn default_config_path() -> Option<PathBuf> {
     })
 }
 
+fn default_threads() -> NonZeroUsize {
+    std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
+
 fn default_state_path() -> Option<PathBuf> {
     state_dir().map(|mut path| {
         path.push("xray");
         path
     })
 }
+
+/// Parses a size with an optional unit (e.g. `500MB` or `1.5 GB`) into a number of bytes.
+pub(crate) fn parse_size(size: &str) -> anyhow::Result<u64> {
+    let size = size.trim();
+    let unit_start = size
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(size.len());
+    let (value, unit) = size.split_at(unit_start);
+
+    let value = value
+        .trim()
+        .parse::<f64>()
+        .with_context(|| format!("invalid size: '{}'", size))?;
+    if !value.is_finite() || value < 0.0 {
+        anyhow::bail!("invalid size: '{}'", size)
+    }
+
+    let unit = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => Unit::Bytes,
+        "k" | "kb" => Unit::Kilobytes,
+        "m" | "mb" => Unit::Megabytes,
+        "g" | "gb" => Unit::Gigabytes,
+        _ => anyhow::bail!(
+            "unknown size unit: '{}', expected one of: B, kB, MB, GB",
+            unit
+        ),
+    };
+
+    Ok((value * unit.scale_to_units(1) as f64) as u64)
+}
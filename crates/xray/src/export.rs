@@ -0,0 +1,702 @@
+//! Contains the logic for exporting a parsed [Image] in a machine-readable format.
+//!
+//! The schema of the exported data is documented in the README and is versioned using [SCHEMA_VERSION].
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use anyhow::Context;
+use regex::Regex;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::config::parse_size;
+use crate::parser::{Image, LayerChangeSet, Node, NodeFilters, NodeKind};
+use crate::tui::util::encode_hex;
+
+/// Version of the exported schema.
+///
+/// It's bumped whenever a field is removed or its meaning is changed, but not when new fields are added.
+const SCHEMA_VERSION: u32 = 1;
+
+/// Supported export formats.
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum ExportFormat {
+    Json,
+}
+
+/// File trees that can be included into the export.
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum ExportedTree {
+    /// Changes made by each layer.
+    Layers,
+    /// The final file tree of the image.
+    Aggregated,
+    /// Don't include any file trees.
+    None,
+}
+
+/// Options of the `export` command.
+#[derive(clap::Args, Debug, Clone)]
+// Prevents clap from using this doc comment as the command's description
+#[command(about = None, long_about = None)]
+pub struct ExportOptions {
+    /// The format of the exported image.
+    #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+    format: ExportFormat,
+    /// The file trees to include.
+    #[arg(long, value_enum, default_value_t = ExportedTree::Layers)]
+    tree: ExportedTree,
+    /// Only include the nodes that match this path.
+    ///
+    /// Works the same way as the path filter in the "Layer Changes" pane
+    #[arg(long, conflicts_with = "regex")]
+    path: Option<PathBuf>,
+    /// Only include the nodes whose paths match this regular expression.
+    #[arg(long, value_parser = Regex::new)]
+    regex: Option<Regex>,
+    /// Only include the nodes that are at least this large.
+    ///
+    /// Format: a number of bytes with an optional unit (B, kB, MB, GB), e.g. 10MB
+    #[arg(long, value_parser = parse_size)]
+    min_size: Option<u64>,
+    /// Write the exported image to this file instead of stdout.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl ExportOptions {
+    /// Returns the [NodeFilters] built from these options.
+    fn filters(&self) -> NodeFilters<'_, '_> {
+        let mut filters = NodeFilters::default();
+
+        if let Some(min_size) = self.min_size {
+            filters = filters.with_size_filter(min_size);
+        }
+
+        if let Some(path) = self.path.as_deref() {
+            filters = filters.with_path_filter(path);
+        } else if let Some(regex) = self.regex.as_ref() {
+            filters = filters.with_regex(Cow::Borrowed(regex));
+        }
+
+        filters
+    }
+}
+
+/// Exports the provided [Image] using the provided [ExportOptions].
+pub fn export_image(
+    image: &Image,
+    options: &ExportOptions,
+) -> anyhow::Result<()> {
+    let mut writer: BufWriter<Box<dyn Write>> = match options.output.as_ref() {
+        Some(path) => BufWriter::new(Box::new(
+            File::create(path).context("failed to create the output file")?,
+        )),
+        None => BufWriter::new(Box::new(std::io::stdout().lock())),
+    };
+
+    write_image(image, options, &mut writer)?;
+
+    writer.flush().context("failed to write the exported image")
+}
+
+/// Writes the provided [Image] to the provided writer in the format from [ExportOptions].
+fn write_image(
+    image: &Image,
+    options: &ExportOptions,
+    mut writer: impl Write,
+) -> anyhow::Result<()> {
+    let mut layer_changes = Vec::new();
+
+    let mut aggregated_changeset: Option<LayerChangeSet> = None;
+    for (layer_idx, layer) in image.layers.values().enumerate() {
+        if let Some(changeset) = layer.changeset.as_ref() {
+            aggregated_changeset = Some(match aggregated_changeset {
+                Some(lower) => lower.merge(changeset.clone()),
+                None => changeset.clone(),
+            });
+        }
+
+        if matches!(options.tree, ExportedTree::Layers) {
+            // Statuses of the changed nodes are only known after merging the layer with the lower ones, so the
+            // changes are taken from the aggregated tree
+            let changes = aggregated_changeset
+                .as_ref()
+                .filter(|_| layer.changeset.is_some())
+                .map(|changeset| {
+                    filter_tree(
+                        changeset,
+                        options
+                            .filters()
+                            .with_show_files_changed_in_layer(layer_idx as u8),
+                    )
+                });
+            layer_changes.push(changes);
+        }
+    }
+
+    let trees = match options.tree {
+        ExportedTree::Layers => FilteredTrees::Layers(layer_changes),
+        ExportedTree::Aggregated => FilteredTrees::Aggregated(
+            aggregated_changeset
+                .as_ref()
+                .map(|changeset| filter_tree(changeset, options.filters())),
+        ),
+        ExportedTree::None => FilteredTrees::None,
+    };
+    let exported_image = ExportedImage { image, trees };
+
+    match options.format {
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, &exported_image)
+                .context("failed to serialize the image")?;
+            writeln!(writer)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns a copy of the provided tree with the filters applied.
+fn filter_tree(tree: &Node, filters: NodeFilters) -> Node {
+    let mut tree = tree.clone();
+    if !tree.filter(filters) {
+        // Nothing matched the filters, so export an empty tree
+        tree = Node::new(tree.updated_in);
+    }
+    tree
+}
+
+/// File trees that should be exported after applying the filters to them.
+///
+/// Trees are `None` for empty layers.
+enum FilteredTrees {
+    Layers(Vec<Option<Node>>),
+    Aggregated(Option<Node>),
+    None,
+}
+
+/// The root object of the exported data.
+struct ExportedImage<'a> {
+    image: &'a Image,
+    trees: FilteredTrees,
+}
+
+impl Serialize for ExportedImage<'_> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let image = self.image;
+
+        let mut state = serializer.serialize_struct("Image", 13)?;
+        state.serialize_field("schema_version", &SCHEMA_VERSION)?;
+        state.serialize_field("name", &image.image_name)?;
+        state.serialize_field("tag", &image.tag)?;
+        state.serialize_field("size", &image.size)?;
+        state.serialize_field("architecture", &image.architecture)?;
+        state.serialize_field("os", &image.os)?;
+        state.serialize_field("variant", &image.variant)?;
+        state.serialize_field("os_version", &image.os_version)?;
+        state.serialize_field("created", &image.created)?;
+        state.serialize_field("author", &image.author)?;
+        state.serialize_field(
+            "layers",
+            &SerializeIter(image.layers.iter().enumerate().map(
+                |(idx, (digest, layer))| {
+                    ExportedLayer {
+                        index: idx,
+                        digest: format!("sha256:{}", encode_hex(digest)),
+                        diff_id: layer.diff_id.map(|diff_id| {
+                            format!("sha256:{}", encode_hex(diff_id))
+                        }),
+                        size: layer.size,
+                        created_by: &layer.created_by,
+                        comment: layer.comment.as_deref(),
+                        history_mismatch: layer.history_mismatch,
+                        changes: match &self.trees {
+                            FilteredTrees::Layers(changes) => Some(
+                                changes
+                                    .get(idx)
+                                    .and_then(Option::as_ref)
+                                    .map(ExportedChildren::of_root)
+                                    .unwrap_or_default(),
+                            ),
+                            _ => None,
+                        },
+                    }
+                },
+            )),
+        )?;
+        match &self.trees {
+            FilteredTrees::Aggregated(tree) => state.serialize_field(
+                "aggregated_tree",
+                &tree
+                    .as_ref()
+                    .map(ExportedChildren::of_root)
+                    .unwrap_or_default(),
+            )?,
+            _ => state.skip_field("aggregated_tree")?,
+        }
+        state.end()
+    }
+}
+
+/// A single layer of the [Image].
+#[derive(Serialize)]
+struct ExportedLayer<'a> {
+    index: usize,
+    digest: String,
+    diff_id: Option<String>,
+    size: u64,
+    created_by: &'a str,
+    comment: Option<&'a str>,
+    history_mismatch: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    changes: Option<ExportedChildren<'a>>,
+}
+
+/// Children of a directory, serialized as an array of [ExportedNodes](ExportedNode).
+#[derive(Default)]
+struct ExportedChildren<'a> {
+    /// Path of the parent directory.
+    path: PathBuf,
+    parent: Option<&'a Node>,
+}
+
+impl<'a> ExportedChildren<'a> {
+    fn of_root(root: &'a Node) -> Self {
+        ExportedChildren {
+            path: PathBuf::from("/"),
+            parent: Some(root),
+        }
+    }
+}
+
+impl Serialize for ExportedChildren<'_> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let children = self.parent.and_then(|parent| parent.inner.children());
+        serializer.collect_seq(children.into_iter().flatten().map(
+            |(name, node)| ExportedNode {
+                path: self.path.join(name),
+                node,
+            },
+        ))
+    }
+}
+
+/// A single file tree node.
+///
+/// Children of directories are serialized lazily, so that the whole tree isn't copied when exporting it.
+struct ExportedNode<'a> {
+    path: PathBuf,
+    node: &'a Node,
+}
+
+impl Serialize for ExportedNode<'_> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let inner = &self.node.inner;
+
+        let status = if inner.is_deleted() {
+            "deleted"
+        } else if inner.is_modified() {
+            "modified"
+        } else {
+            "added"
+        };
+
+        let mut state = serializer.serialize_struct("Node", 13)?;
+        state.serialize_field("path", &self.path.to_string_lossy())?;
+        state.serialize_field("status", status)?;
+        state.serialize_field("kind", kind_name(inner.kind()))?;
+        state.serialize_field("size", &inner.size())?;
+        match inner.deleted_size() {
+            Some(deleted_size) => {
+                state.serialize_field("deleted_size", &deleted_size)?
+            }
+            None => state.skip_field("deleted_size")?,
+        }
+        match inner.get_link() {
+            Some(link) => {
+                state.serialize_field("link", &link.to_string_lossy())?
+            }
+            None => state.skip_field("link")?,
+        }
+        if let Some(metadata) = inner.metadata() {
+            state.serialize_field("mode", &metadata.mode)?;
+            state.serialize_field("uid", &metadata.uid)?;
+            state.serialize_field("gid", &metadata.gid)?;
+            state.serialize_field("user", &metadata.uname.as_deref())?;
+            state.serialize_field("group", &metadata.gname.as_deref())?;
+            state.serialize_field("mtime", &metadata.mtime)?;
+        } else {
+            for field in ["mode", "uid", "gid", "user", "group", "mtime"] {
+                state.skip_field(field)?;
+            }
+        }
+        if inner.is_dir() {
+            state.serialize_field(
+                "children",
+                &ExportedChildren {
+                    path: self.path.clone(),
+                    parent: Some(self.node),
+                },
+            )?;
+        } else {
+            state.skip_field("children")?;
+        }
+        state.end()
+    }
+}
+
+/// Serializes all items of the wrapped iterator as a sequence.
+struct SerializeIter<I>(I);
+
+impl<I> Serialize for SerializeIter<I>
+where
+    I: Iterator + Clone,
+    I::Item: Serialize,
+{
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.clone())
+    }
+}
+
+/// Returns the name of the provided [NodeKind] that is used in the exported schema.
+fn kind_name(kind: NodeKind) -> &'static str {
+    match kind {
+        NodeKind::File => "file",
+        NodeKind::Directory => "directory",
+        NodeKind::Symlink => "symlink",
+        NodeKind::Hardlink => "hardlink",
+        NodeKind::CharDevice => "char_device",
+        NodeKind::BlockDevice => "block_device",
+        NodeKind::Fifo => "fifo",
+        NodeKind::Other => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::parser::NodeMetadata;
+    use crate::parser::test_util::{
+        Entry, FileState, InnerNode, NodeStatus, make_image,
+    };
+
+    fn make_test_image() -> Image {
+        let metadata = NodeMetadata {
+            mode: 0o644,
+            uid: 0,
+            gid: 101,
+            uname: Some("root".into()),
+            gname: None,
+            mtime: 1735689600,
+        };
+        let config = InnerNode::File(
+            FileState::new(NodeStatus::Added(10), None).with_metadata(metadata),
+        );
+        let symlink = InnerNode::File(
+            FileState::new(NodeStatus::Added(0), Some("busybox".into()))
+                .with_kind(NodeKind::Symlink),
+        );
+
+        let mut image = make_image(
+            "app",
+            &[
+                (
+                    1,
+                    &[
+                        Entry::Node("etc/app.conf", config),
+                        Entry::Node("bin/sh", symlink),
+                        Entry::File("bin/busybox", 1000),
+                        Entry::File("tmp/cache", 100),
+                    ],
+                ),
+                (
+                    2,
+                    &[Entry::File("etc/app.conf", 20), Entry::Whiteout("tmp")],
+                ),
+            ],
+        );
+        image.architecture = "amd64".into();
+        image.os = "linux".into();
+        image.created = Some("2025-01-01T00:00:00Z".into());
+        let layer = image.layers.get_index_mut(1).unwrap().1;
+        layer.diff_id = Some([3; 32]);
+        layer.comment = Some("buildkit.dockerfile.v0".into());
+        image
+    }
+
+    fn options(tree: ExportedTree) -> ExportOptions {
+        ExportOptions {
+            format: ExportFormat::Json,
+            tree,
+            path: None,
+            regex: None,
+            min_size: None,
+            output: None,
+        }
+    }
+
+    fn export(image: &Image, options: &ExportOptions) -> Value {
+        let mut exported = Vec::new();
+        write_image(image, options, &mut exported).unwrap();
+        serde_json::from_slice(&exported).unwrap()
+    }
+
+    /// Returns the exported metadata of the test image without its file trees.
+    fn exported_metadata() -> Value {
+        json!({
+            "schema_version": 1,
+            "name": "app",
+            "tag": "latest",
+            "size": 1130,
+            "architecture": "amd64",
+            "os": "linux",
+            "variant": null,
+            "os_version": null,
+            "created": "2025-01-01T00:00:00Z",
+            "author": null,
+            "layers": [
+                {
+                    "index": 0,
+                    "digest": format!("sha256:{}", "01".repeat(32)),
+                    "diff_id": null,
+                    "size": 1110,
+                    "created_by": "layer 1",
+                    "comment": null,
+                    "history_mismatch": false,
+                },
+                {
+                    "index": 1,
+                    "digest": format!("sha256:{}", "02".repeat(32)),
+                    "diff_id": format!("sha256:{}", "03".repeat(32)),
+                    "size": 20,
+                    "created_by": "layer 2",
+                    "comment": "buildkit.dockerfile.v0",
+                    "history_mismatch": false,
+                },
+            ],
+        })
+    }
+
+    fn exported_config(size: u64, status: &str) -> Value {
+        json!({
+            "path": "/etc",
+            "status": status,
+            "kind": "directory",
+            "size": size,
+            "children": [{
+                "path": "/etc/app.conf",
+                "status": status,
+                "kind": "file",
+                "size": size,
+                "mode": 0o644,
+                "uid": 0,
+                "gid": 101,
+                "user": "root",
+                "group": null,
+                "mtime": 1735689600,
+            }],
+        })
+    }
+
+    fn exported_bin(include_busybox: bool, include_sh: bool) -> Value {
+        let mut children = Vec::new();
+        if include_busybox {
+            children.push(json!({
+                "path": "/bin/busybox",
+                "status": "added",
+                "kind": "file",
+                "size": 1000,
+            }));
+        }
+        if include_sh {
+            children.push(json!({
+                "path": "/bin/sh",
+                "status": "added",
+                "kind": "symlink",
+                "size": 0,
+                "link": "busybox",
+            }));
+        }
+        json!({
+            "path": "/bin",
+            "status": "added",
+            "kind": "directory",
+            "size": 1000,
+            "children": children,
+        })
+    }
+
+    fn exported_tmp(deleted: bool) -> Value {
+        if deleted {
+            json!({
+                "path": "/tmp",
+                "status": "deleted",
+                "kind": "directory",
+                "size": 0,
+                "deleted_size": 100,
+                "children": [{
+                    "path": "/tmp/cache",
+                    "status": "deleted",
+                    "kind": "file",
+                    "size": 0,
+                    "deleted_size": 100,
+                }],
+            })
+        } else {
+            json!({
+                "path": "/tmp",
+                "status": "added",
+                "kind": "directory",
+                "size": 100,
+                "children": [{
+                    "path": "/tmp/cache",
+                    "status": "added",
+                    "kind": "file",
+                    "size": 100,
+                }],
+            })
+        }
+    }
+
+    /// Adds the provided changes to the layers of the exported metadata.
+    fn with_changes(mut exported: Value, changes: [Value; 2]) -> Value {
+        for (layer, changes) in exported["layers"]
+            .as_array_mut()
+            .unwrap()
+            .iter_mut()
+            .zip(changes)
+        {
+            layer["changes"] = changes;
+        }
+        exported
+    }
+
+    fn with_aggregated_tree(mut exported: Value, tree: Value) -> Value {
+        exported["aggregated_tree"] = tree;
+        exported
+    }
+
+    #[test]
+    fn export_layers_tree() {
+        let exported =
+            export(&make_test_image(), &options(ExportedTree::Layers));
+        let expected = with_changes(
+            exported_metadata(),
+            [
+                json!([
+                    exported_bin(true, true),
+                    exported_config(10, "added"),
+                    exported_tmp(false),
+                ]),
+                json!([exported_config(20, "modified"), exported_tmp(true)]),
+            ],
+        );
+        assert_eq!(exported, expected);
+    }
+
+    #[test]
+    fn export_aggregated_tree() {
+        let exported =
+            export(&make_test_image(), &options(ExportedTree::Aggregated));
+        let expected = with_aggregated_tree(
+            exported_metadata(),
+            json!([
+                exported_bin(true, true),
+                exported_config(20, "modified"),
+                exported_tmp(true),
+            ]),
+        );
+        assert_eq!(exported, expected);
+    }
+
+    #[test]
+    fn export_without_trees() {
+        let exported = export(&make_test_image(), &options(ExportedTree::None));
+        assert_eq!(exported, exported_metadata());
+    }
+
+    #[test]
+    fn export_filtered_trees() {
+        let image = make_test_image();
+
+        let mut path_options = options(ExportedTree::Aggregated);
+        path_options.path = Some("/etc".into());
+        assert_eq!(
+            export(&image, &path_options)["aggregated_tree"],
+            json!([exported_config(20, "modified")])
+        );
+
+        let mut size_options = options(ExportedTree::Aggregated);
+        size_options.min_size = Some(500);
+        assert_eq!(
+            export(&image, &size_options)["aggregated_tree"],
+            json!([exported_bin(true, false)])
+        );
+
+        // Layers without matching changes have empty trees
+        let mut regex_options = options(ExportedTree::Layers);
+        regex_options.regex = Some(Regex::new("sh$").unwrap());
+        assert_eq!(
+            export(&image, &regex_options),
+            with_changes(
+                exported_metadata(),
+                [json!([exported_bin(false, true)]), json!([])]
+            )
+        );
+    }
+
+    /// Fails if a field is added to the exported schema without documenting it in the README.
+    #[test]
+    fn exported_fields_are_documented() {
+        fn collect_fields(value: &Value, fields: &mut BTreeSet<String>) {
+            match value {
+                Value::Object(object) => {
+                    for (field, value) in object {
+                        fields.insert(field.clone());
+                        collect_fields(value, fields);
+                    }
+                }
+                Value::Array(array) => {
+                    array.iter().for_each(|value| collect_fields(value, fields))
+                }
+                _ => {}
+            }
+        }
+
+        let readme = include_str!("../../../README.md");
+        let image = make_test_image();
+        let mut fields = BTreeSet::new();
+        for tree in [ExportedTree::Layers, ExportedTree::Aggregated] {
+            collect_fields(&export(&image, &options(tree)), &mut fields);
+        }
+        for field in fields {
+            assert!(
+                readme.contains(&format!("\"{field}\":")),
+                "'{field}' is not documented in the README"
+            );
+        }
+        assert!(
+            readme.contains(&format!("\"schema_version\": {SCHEMA_VERSION},"))
+        );
+    }
+}
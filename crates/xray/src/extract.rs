@@ -0,0 +1,576 @@
+//! Contains the logic for extracting files and directories from a parsed [Image] to the local disk.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::Context;
+use indexmap::IndexMap;
+
+use crate::parser::{
+    Image, Layer, LayerChangeSet, Node, NodeKind, Sha256Digest,
+};
+
+/// The permission bits of a mode, i.e. everything except the setuid, setgid, and sticky bits.
+const PERMISSION_BITS: u32 = 0o777;
+
+/// Options of the `extract` command.
+#[derive(clap::Args, Debug, Clone)]
+// Prevents clap from using this doc comment as the command's description
+#[command(about = None, long_about = None)]
+pub struct ExtractOptions {
+    /// Path to the file or directory within the image.
+    #[arg()]
+    path: PathBuf,
+    /// The directory to extract the file or directory into.
+    ///
+    /// It's created if it doesn't exist
+    #[arg()]
+    dest: PathBuf,
+    /// Extract the file or directory as it is after this layer (a 0-based index) instead of the last one.
+    #[arg(long)]
+    layer: Option<usize>,
+    /// Keep the setuid, setgid, and sticky bits of the extracted files and directories.
+    ///
+    /// Only the permission bits are kept by default
+    #[arg(long)]
+    preserve_special_bits: bool,
+}
+
+/// The number of nodes that were extracted from the image.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ExtractionSummary {
+    pub files: usize,
+    pub directories: usize,
+    pub symlinks: usize,
+    /// Nodes that can't be extracted (e.g. devices and fifos).
+    pub skipped: usize,
+}
+
+impl std::fmt::Display for ExtractionSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} files, {} directories, {} symlinks",
+            self.files, self.directories, self.symlinks
+        )?;
+        if self.skipped != 0 {
+            write!(f, ", {} skipped", self.skipped)?;
+        }
+        Ok(())
+    }
+}
+
+/// Extracts a node from the provided [Image] using the provided [ExtractOptions].
+pub fn extract_image(
+    image: &Image,
+    options: &ExtractOptions,
+) -> anyhow::Result<()> {
+    let last_layer_idx = image.layers.len() - 1;
+    let layer_idx = options.layer.unwrap_or(last_layer_idx);
+    if layer_idx > last_layer_idx {
+        anyhow::bail!(
+            "the image has only {} layers, but layer {} was requested",
+            image.layers.len(),
+            layer_idx
+        )
+    }
+
+    let tree = image
+        .layers
+        .values()
+        .take(layer_idx + 1)
+        .filter_map(|layer| layer.changeset.clone())
+        .reduce(LayerChangeSet::merge)
+        .context("the image doesn't have any files")?;
+
+    let summary = extract_node(
+        &tree,
+        &image.layers,
+        &options.path,
+        &options.dest,
+        options.preserve_special_bits,
+    )?;
+    println!(
+        "Extracted {} into {}: {}",
+        options.path.display(),
+        options.dest.display(),
+        summary
+    );
+
+    Ok(())
+}
+
+/// Extracts the node at the provided path within the aggregated `tree` into the `dest` directory.
+///
+/// The node keeps its name, so `/etc/nginx` is extracted into `dest/nginx`. The contents of files are read from the
+/// blobs of the layers that last updated them, while deleted nodes are skipped. Hard links are extracted as copies of
+/// the files they point to.
+///
+/// The setuid, setgid, and sticky bits are dropped from the extracted nodes unless `preserve_special_bits` is set.
+pub fn extract_node(
+    tree: &LayerChangeSet,
+    layers: &IndexMap<Sha256Digest, Layer>,
+    path: &Path,
+    dest: &Path,
+    preserve_special_bits: bool,
+) -> anyhow::Result<ExtractionSummary> {
+    let node = tree
+        .get(path)
+        .filter(|node| !node.inner.is_deleted())
+        .with_context(|| {
+            format!("'{}' doesn't exist in the image", path.display())
+        })?;
+    let name = path
+        .file_name()
+        .context("the root directory can't be extracted")?;
+
+    std::fs::create_dir_all(dest)
+        .context("failed to create the destination directory")?;
+    let dest = dest.join(name);
+    if dest.symlink_metadata().is_ok() {
+        anyhow::bail!("'{}' already exists", dest.display())
+    }
+
+    let mut extraction = Extraction {
+        layers,
+        preserve_special_bits,
+        summary: ExtractionSummary::default(),
+        files: BTreeMap::new(),
+        directories: Vec::new(),
+    };
+    extraction.create_node(node, dest)?;
+    extraction.write_files()?;
+
+    // Directories are made read-only only after everything was written into them, starting from the deepest ones
+    for (path, mode) in extraction.directories.iter().rev() {
+        set_mode(path, *mode).with_context(|| {
+            format!("failed to set the mode of '{}'", path.display())
+        })?;
+    }
+
+    Ok(extraction.summary)
+}
+
+/// A regular file that should be written once the directory tree is created.
+struct PendingFile {
+    dest: PathBuf,
+    content_offset: u64,
+    size: u64,
+    mode: Option<u32>,
+}
+
+/// Keeps track of the extraction of a single node.
+struct Extraction<'a> {
+    /// Layers of the image, which are used to resolve hard links and read the contents of files.
+    layers: &'a IndexMap<Sha256Digest, Layer>,
+    /// Whether the setuid, setgid, and sticky bits should be kept.
+    preserve_special_bits: bool,
+    summary: ExtractionSummary,
+    /// Files that should be written, grouped by the layers that they should be read from.
+    files: BTreeMap<u8, Vec<PendingFile>>,
+    /// Created directories and the modes that should be set for them.
+    directories: Vec<(PathBuf, u32)>,
+}
+
+impl Extraction<'_> {
+    /// Creates the provided node and its children at the provided path.
+    ///
+    /// Contents of files are not written here, but are written by [Self::write_files] instead.
+    fn create_node(
+        &mut self,
+        node: &Node,
+        dest: PathBuf,
+    ) -> anyhow::Result<()> {
+        if node.inner.is_deleted() {
+            return Ok(());
+        }
+
+        let mode = self.mode_of(node);
+        match node.inner.kind() {
+            NodeKind::Directory => {
+                std::fs::create_dir(&dest).with_context(|| {
+                    format!("failed to create '{}'", dest.display())
+                })?;
+                self.summary.directories += 1;
+                if let Some(mode) = mode {
+                    self.directories.push((dest.clone(), mode));
+                }
+
+                for (name, child) in node.inner.children().into_iter().flatten()
+                {
+                    // Names come from the image, so make sure that they can't escape the destination directory
+                    let name: &Path = name.as_ref();
+                    if !matches!(
+                        name.components().collect::<Vec<_>>()[..],
+                        [Component::Normal(_)]
+                    ) {
+                        tracing::warn!(
+                            "Skipping a node with an invalid name: {}",
+                            name.display()
+                        );
+                        self.summary.skipped += 1;
+                        continue;
+                    }
+                    self.create_node(child, dest.join(name))?;
+                }
+            }
+            NodeKind::Symlink => {
+                let target = node
+                    .inner
+                    .get_link()
+                    .context("bug: symlinks should have a target")?;
+                create_symlink(target, &dest).with_context(|| {
+                    format!("failed to create '{}'", dest.display())
+                })?;
+                self.summary.symlinks += 1;
+            }
+            kind @ (NodeKind::File | NodeKind::Hardlink) => {
+                let file = if kind == NodeKind::Hardlink {
+                    let target = node
+                        .inner
+                        .get_link()
+                        .context("bug: hard links should have a target")?;
+                    // Hard links point to the file from the same layer even if it was replaced later
+                    self.layers
+                        .get_index(node.updated_in.into())
+                        .and_then(|(_, layer)| layer.changeset.as_ref())
+                        .and_then(|changeset| changeset.get(target))
+                        .filter(|node| node.inner.content_offset().is_some())
+                        .with_context(|| {
+                            format!(
+                                "the target of '{}' is missing",
+                                dest.display()
+                            )
+                        })?
+                } else {
+                    node
+                };
+                let content_offset = file
+                    .inner
+                    .content_offset()
+                    .context("bug: files should have contents")?;
+
+                let mode = self.mode_of(file);
+                self.files.entry(file.updated_in).or_default().push(
+                    PendingFile {
+                        dest,
+                        content_offset,
+                        size: file.inner.size(),
+                        mode,
+                    },
+                );
+                self.summary.files += 1;
+            }
+            NodeKind::CharDevice
+            | NodeKind::BlockDevice
+            | NodeKind::Fifo
+            | NodeKind::Other => {
+                tracing::warn!("Skipping a special file: {}", dest.display());
+                self.summary.skipped += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the mode that should be set for the provided node once it's extracted.
+    fn mode_of(&self, node: &Node) -> Option<u32> {
+        let mode = node.inner.metadata()?.mode;
+        Some(if self.preserve_special_bits {
+            mode
+        } else {
+            mode & PERMISSION_BITS
+        })
+    }
+
+    /// Writes the contents of all pending files, reading each layer only once.
+    fn write_files(&mut self) -> anyhow::Result<()> {
+        for (layer_idx, files) in self.files.iter_mut() {
+            let blob = self
+                .layers
+                .get_index((*layer_idx).into())
+                .and_then(|(_, layer)| layer.blob.as_ref())
+                .with_context(|| {
+                    format!(
+                        "the contents of layer {} are not available",
+                        layer_idx
+                    )
+                })?;
+            let mut reader = blob.reader().with_context(|| {
+                format!("failed to open layer {}", layer_idx)
+            })?;
+
+            // Compressed layers can only be read sequentially
+            files.sort_unstable_by_key(|file| file.content_offset);
+            // Hard links share the contents with their targets, so they are copied instead of being read again
+            for same_files in
+                files.chunk_by(|a, b| a.content_offset == b.content_offset)
+            {
+                let file = &same_files[0];
+                let mut src = reader.file(file.content_offset, file.size)?;
+                let mut dst = BufWriter::new(
+                    File::create_new(&file.dest).with_context(|| {
+                        format!("failed to create '{}'", file.dest.display())
+                    })?,
+                );
+                let written = std::io::copy(&mut src, &mut dst)
+                    .and_then(|written| dst.flush().map(|_| written))
+                    .with_context(|| {
+                        format!("failed to write '{}'", file.dest.display())
+                    })?;
+                if written != file.size {
+                    anyhow::bail!(
+                        "layer {} ended before the end of '{}'",
+                        layer_idx,
+                        file.dest.display()
+                    )
+                }
+
+                for copy in &same_files[1..] {
+                    std::fs::copy(&file.dest, &copy.dest).with_context(
+                        || format!("failed to write '{}'", copy.dest.display()),
+                    )?;
+                }
+
+                for file in same_files {
+                    if let Some(mode) = file.mode {
+                        set_mode(&file.dest, mode).with_context(|| {
+                            format!(
+                                "failed to set the mode of '{}'",
+                                file.dest.display()
+                            )
+                        })?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+#[cfg(unix)]
+fn create_symlink(target: &Path, path: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(not(unix))]
+fn create_symlink(_target: &Path, _path: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "symlinks can only be extracted on Unix",
+    ))
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> std::io::Result<()> {
+    // Unix modes can't be preserved on other platforms
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    use tempfile::{NamedTempFile, TempDir};
+
+    use super::*;
+    use crate::parser::test_util::{
+        Entry, TarEntry, make_layer, make_layer_from_tar, make_tar,
+    };
+
+    /// Builds the layers from their tar blobs and aggregates them.
+    ///
+    /// The returned files store the blobs and must be kept around during the extraction.
+    fn make_layers(
+        blobs: &[Vec<u8>],
+    ) -> (
+        LayerChangeSet,
+        IndexMap<Sha256Digest, Layer>,
+        Vec<NamedTempFile>,
+    ) {
+        let mut layers = IndexMap::new();
+        let mut files = Vec::new();
+        for (idx, blob) in blobs.iter().enumerate() {
+            let (layer, file) = make_layer_from_tar(idx as u8, blob);
+            layers.insert([idx as u8; 32], layer);
+            files.push(file);
+        }
+        let tree = layers
+            .values()
+            .filter_map(|layer| layer.changeset.clone())
+            .reduce(LayerChangeSet::merge)
+            .unwrap();
+        (tree, layers, files)
+    }
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    fn mode(path: &Path) -> u32 {
+        path.symlink_metadata().unwrap().permissions().mode() & 0o7777
+    }
+
+    fn app_layer() -> Vec<u8> {
+        make_tar(&[
+            TarEntry::Dir("app", 0o755),
+            TarEntry::Dir("app/bin", 0o555),
+            TarEntry::File("app/bin/tool", 0o4755, b"tool\n"),
+            TarEntry::File("app/data", 0o600, b"data\n"),
+            TarEntry::Symlink("app/link", "data"),
+            TarEntry::Hardlink("app/hard", "app/data"),
+        ])
+    }
+
+    // --- extract_node ---
+
+    #[test]
+    fn extract_directory_tree() {
+        let (tree, layers, _files) = make_layers(&[app_layer()]);
+        let dest = TempDir::new().unwrap();
+
+        let summary =
+            extract_node(&tree, &layers, Path::new("/app"), dest.path(), false)
+                .unwrap();
+
+        let app = dest.path().join("app");
+        assert_eq!(
+            (
+                summary.files,
+                summary.directories,
+                summary.symlinks,
+                summary.skipped
+            ),
+            (3, 2, 1, 0)
+        );
+        assert_eq!(read(&app.join("bin/tool")), "tool\n");
+        assert_eq!(read(&app.join("data")), "data\n");
+        assert_eq!(read(&app.join("hard")), "data\n");
+        assert_eq!(
+            std::fs::read_link(app.join("link")).unwrap(),
+            Path::new("data")
+        );
+
+        // The read-only directory gets its mode only after its children were written
+        assert_eq!(mode(&app.join("bin")), 0o555);
+        assert_eq!(mode(&app.join("data")), 0o600);
+        // The setuid bit is dropped by default
+        assert_eq!(mode(&app.join("bin/tool")), 0o755);
+
+        // Allow the temporary directory to be removed
+        set_mode(&app.join("bin"), 0o755).unwrap();
+    }
+
+    #[test]
+    fn extract_with_special_bits() {
+        let (tree, layers, _files) = make_layers(&[app_layer()]);
+        let dest = TempDir::new().unwrap();
+
+        extract_node(&tree, &layers, Path::new("app/bin"), dest.path(), true)
+            .unwrap();
+
+        assert_eq!(mode(&dest.path().join("bin/tool")), 0o4755);
+        set_mode(&dest.path().join("bin"), 0o755).unwrap();
+    }
+
+    #[test]
+    fn extract_skips_deleted_nodes() {
+        let (tree, layers, _files) = make_layers(&[
+            make_tar(&[
+                TarEntry::File("app/data", 0o644, b"data\n"),
+                TarEntry::File("app/removed", 0o644, b"removed\n"),
+            ]),
+            make_tar(&[TarEntry::File("app/.wh.removed", 0o644, b"")]),
+        ]);
+        let dest = TempDir::new().unwrap();
+
+        let summary =
+            extract_node(&tree, &layers, Path::new("app"), dest.path(), false)
+                .unwrap();
+
+        assert_eq!(summary.files, 1);
+        assert!(dest.path().join("app/data").exists());
+        assert!(!dest.path().join("app/removed").exists());
+
+        let error = extract_node(
+            &tree,
+            &layers,
+            Path::new("app/removed"),
+            dest.path(),
+            false,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("doesn't exist"));
+    }
+
+    #[test]
+    fn extract_hardlink_from_its_own_layer() {
+        // The hard link keeps pointing to the original file after it's replaced by the next layer
+        let (tree, layers, _files) = make_layers(&[
+            make_tar(&[
+                TarEntry::File("app/data", 0o644, b"old\n"),
+                TarEntry::Hardlink("app/hard", "app/data"),
+            ]),
+            make_tar(&[TarEntry::File("app/data", 0o644, b"new\n")]),
+        ]);
+        let dest = TempDir::new().unwrap();
+
+        extract_node(&tree, &layers, Path::new("app"), dest.path(), false)
+            .unwrap();
+
+        assert_eq!(read(&dest.path().join("app/data")), "new\n");
+        assert_eq!(read(&dest.path().join("app/hard")), "old\n");
+    }
+
+    #[test]
+    fn extract_skips_escaping_names() {
+        let tree = make_layer(0, &[Entry::File("app/../escaped", 5)]);
+        let dest = TempDir::new().unwrap();
+        let out = dest.path().join("out");
+
+        let summary = extract_node(
+            &tree,
+            &IndexMap::new(),
+            Path::new("app"),
+            &out,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!((summary.directories, summary.skipped), (1, 1));
+        assert!(!dest.path().join("escaped").exists());
+        assert!(!out.join("escaped").exists());
+        assert_eq!(std::fs::read_dir(out.join("app")).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn extract_into_existing_path() {
+        let (tree, layers, _files) =
+            make_layers(&[make_tar(&[TarEntry::File(
+                "app/data", 0o644, b"data\n",
+            )])]);
+        let dest = TempDir::new().unwrap();
+        std::fs::write(dest.path().join("data"), "local\n").unwrap();
+
+        let error = extract_node(
+            &tree,
+            &layers,
+            Path::new("app/data"),
+            dest.path(),
+            false,
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().contains("already exists"));
+        assert_eq!(read(&dest.path().join("data")), "local\n");
+    }
+}
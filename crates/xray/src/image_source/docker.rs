@@ -1,15 +1,19 @@
-use std::io::Cursor;
+use std::io::BufReader;
 
 use anyhow::Context as _;
 use xray_docker::DockerApi;
 
-use super::ImageSourcer;
+use super::{ImageSourcer, spool_to_temp_file};
 use crate::Parser;
 
 pub struct DockerSource;
 
 impl ImageSourcer for DockerSource {
-    fn get_image(&self, image: &str) -> anyhow::Result<crate::parser::Image> {
+    fn get_image(
+        &self,
+        image: &str,
+        parser: Parser,
+    ) -> anyhow::Result<crate::parser::Image> {
         let mut docker_api = DockerApi::new_with_host_resolution()?;
 
         if !docker_api
@@ -28,16 +32,18 @@ impl ImageSourcer for DockerSource {
             image
         );
 
-        let raw_image = docker_api.export_image(image)?;
+        let mut raw_image = docker_api.export_image(image)?;
+        let exported_image = spool_to_temp_file(&mut raw_image)
+            .context("failed to export the image from Docker")?;
 
         tracing::info!(
             "Successfully exported the image from Docker, parsing it..."
         );
 
-        let reader = Cursor::new(raw_image);
-        let parser = Parser::default();
         parser
-            .parse_image(reader)
+            .parse_image_concurrently(move || {
+                exported_image.reopen().map(BufReader::new)
+            })
             .context("failed to parse the image")
     }
 
@@ -1,5 +1,6 @@
 use std::fs::File;
 use std::io::BufReader;
+use std::path::Path;
 
 use anyhow::Context as _;
 
@@ -9,22 +10,33 @@ use crate::Parser;
 pub struct FilesystemSource;
 
 impl ImageSourcer for FilesystemSource {
-    fn get_image(&self, image: &str) -> anyhow::Result<crate::parser::Image> {
+    fn get_image(
+        &self,
+        image: &str,
+        parser: Parser,
+    ) -> anyhow::Result<crate::parser::Image> {
+        if let Some((layout, tag)) = split_image_layout(image) {
+            tracing::info!("Found the OCI image layout locally, parsing...");
+
+            return parser
+                .parse_image_layout(layout, tag)
+                .context("failed to parse the OCI image layout");
+        }
+
         if !std::fs::exists(image).with_context(|| {
             format!("failed to check if the '{image}' path exists")
         })? {
             tracing::info!("Failed to find the tarred image locally");
             anyhow::bail!("the specified path doesn't exist")
         }
-        let raw_image =
-            File::open(image).context("failed to open the tarred image")?;
 
         tracing::info!("Found the tarred image locally, parsing...");
 
-        let reader = BufReader::new(raw_image);
-        let parser = Parser::default();
+        let image = image.to_owned();
         parser
-            .parse_image(reader)
+            .parse_image_concurrently(move || {
+                File::open(&image).map(BufReader::new)
+            })
             .context("failed to parse the tarred image")
     }
 
@@ -32,3 +44,17 @@ impl ImageSourcer for FilesystemSource {
         "Filesystem"
     }
 }
+
+/// Checks if the provided image points to an OCI image layout directory, optionally followed by a tag (`path[:tag]`).
+///
+/// Returns the path to the layout and the tag if it's a layout.
+fn split_image_layout(image: &str) -> Option<(&Path, Option<&str>)> {
+    if Path::new(image).is_dir() {
+        return Some((Path::new(image), None));
+    }
+
+    let (layout, tag) = image.rsplit_once(':')?;
+    Path::new(layout)
+        .is_dir()
+        .then_some((Path::new(layout), Some(tag)))
+}
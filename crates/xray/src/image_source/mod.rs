@@ -1,34 +1,51 @@
 mod docker;
 mod filesystem;
 mod podman;
+mod registry;
 
+use std::io::{BufWriter, Read, Write};
+
+use anyhow::Context as _;
 use docker::DockerSource;
 use filesystem::FilesystemSource;
+use registry::RegistrySource;
+use tempfile::NamedTempFile;
 
-use crate::Config;
 use crate::config::ImageSource;
 use crate::image_source::podman::PodmanSource;
-use crate::parser::Image;
+use crate::parser::{Image, LayerCache};
+use crate::{Config, Parser};
 
 /// A trait that represents entities that act as an OCI [Image] source.
 trait ImageSourcer {
-    /// Get the image using the provided image name or path.
+    /// Get the image using the provided image name or path and parse it using the provided [Parser].
     ///
     /// Returns an [Image] upon success.
-    fn get_image(&self, image: &str) -> anyhow::Result<Image>;
+    fn get_image(&self, image: &str, parser: Parser) -> anyhow::Result<Image>;
 
     /// Returns a human-readable name representing this source.
     fn name(&self) -> &'static str;
 }
 
 pub fn resolve_image_from_config(config: &Config) -> anyhow::Result<Image> {
+    resolve_image(config, config.image())
+}
+
+/// Resolves the provided image using the image sources and parsing options from the provided [Config].
+pub fn resolve_image(config: &Config, image: &str) -> anyhow::Result<Image> {
     let image_sources: Vec<&dyn ImageSourcer> = match config.image_source() {
         ImageSource::Default => {
-            vec![&FilesystemSource, &DockerSource, &PodmanSource]
+            vec![
+                &FilesystemSource,
+                &DockerSource,
+                &PodmanSource,
+                &RegistrySource,
+            ]
         }
         ImageSource::ForceDocker => vec![&DockerSource],
         ImageSource::ForceFS => vec![&FilesystemSource],
         ImageSource::ForcePodman => vec![&PodmanSource],
+        ImageSource::ForceRegistry => vec![&RegistrySource],
     };
 
     if image_sources.is_empty() {
@@ -38,7 +55,7 @@ pub fn resolve_image_from_config(config: &Config) -> anyhow::Result<Image> {
 
     let mut errors = Vec::new();
     for source in &image_sources {
-        match source.get_image(config.image()) {
+        match source.get_image(image, parser_from_config(config)) {
             Ok(image) => return Ok(image),
             Err(e) => {
                 tracing::debug!(
@@ -67,3 +84,31 @@ pub fn resolve_image_from_config(config: &Config) -> anyhow::Result<Image> {
         joined_errors
     )
 }
+
+/// Creates a [Parser] with all the parsing options from the provided [Config].
+fn parser_from_config(config: &Config) -> Parser {
+    Parser::new()
+        .with_platform(config.platform().cloned())
+        .with_verification(config.verify())
+        .with_threads(config.threads())
+        .with_cache(
+            config
+                .use_cache()
+                .then(|| LayerCache::new(config.cache_path())),
+        )
+}
+
+/// Copies the provided image into a temporary file.
+///
+/// Used to avoid keeping the image in memory, as images can be huge.
+fn spool_to_temp_file(src: &mut impl Read) -> anyhow::Result<NamedTempFile> {
+    let mut spooled = NamedTempFile::new()
+        .context("failed to create a temporary file for the image")?;
+    let mut dst = BufWriter::new(spooled.as_file_mut());
+    std::io::copy(src, &mut dst)
+        .and_then(|_| dst.flush())
+        .context("failed to copy the image to a temporary file")?;
+    drop(dst);
+
+    Ok(spooled)
+}
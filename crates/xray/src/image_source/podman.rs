@@ -1,15 +1,19 @@
-use std::io::Cursor;
+use std::io::BufReader;
 
 use anyhow::Context as _;
 use xray_podman::PodmanApi;
 
 use crate::Parser;
-use crate::image_source::ImageSourcer;
+use crate::image_source::{ImageSourcer, spool_to_temp_file};
 
 pub struct PodmanSource;
 
 impl ImageSourcer for PodmanSource {
-    fn get_image(&self, image: &str) -> anyhow::Result<crate::parser::Image> {
+    fn get_image(
+        &self,
+        image: &str,
+        parser: Parser,
+    ) -> anyhow::Result<crate::parser::Image> {
         let podman_api = PodmanApi::new();
 
         if !podman_api
@@ -28,18 +32,25 @@ impl ImageSourcer for PodmanSource {
             image
         );
 
-        let raw_image = podman_api.save_image(image)?;
+        let mut raw_image = podman_api.save_image(image)?;
+        let spool_result = spool_to_temp_file(&mut raw_image);
+        // Podman must be waited for even if copying failed
+        let podman_result = raw_image.finish();
+        let exported_image =
+            spool_result.context("failed to export the image from Podman")?;
+        podman_result?;
 
         tracing::info!(
             "Successfully exported the image from Podman, parsing it..."
         );
 
-        let reader = Cursor::new(raw_image);
         // Podman images don't usually contain Docker-like manifests, so we simply deduce the image name from
         // the input arg.
-        let parser = Parser::new_with_image(image);
         parser
-            .parse_image(reader)
+            .with_image(image)
+            .parse_image_concurrently(move || {
+                exported_image.reopen().map(BufReader::new)
+            })
             .context("failed to parse the image")
     }
 
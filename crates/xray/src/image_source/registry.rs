@@ -0,0 +1,67 @@
+use std::io::Read;
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use xray_registry::{Reference, RegistryApi};
+
+use super::ImageSourcer;
+use crate::Parser;
+use crate::parser::{RemoteImage, Sha256Digest};
+
+pub struct RegistrySource;
+
+impl ImageSourcer for RegistrySource {
+    fn get_image(
+        &self,
+        image: &str,
+        parser: Parser,
+    ) -> anyhow::Result<crate::parser::Image> {
+        let reference = Reference::parse(image)?;
+        let registry_api = RegistryApi::new(&reference)?;
+
+        tracing::info!("Pulling the '{}' image from the registry", reference);
+
+        let manifest = registry_api
+            .get_manifest(reference.manifest_reference())
+            .context("failed to fetch the image manifest")?;
+
+        // Registries don't store image names in manifests, so we deduce the image name from the reference
+        let tag = reference.tag().unwrap_or(reference.manifest_reference());
+        parser
+            .with_image(format!("{}:{}", reference.name(), tag))
+            .parse_remote_image(
+                &manifest,
+                Arc::new(RegistryImage(registry_api)),
+            )
+            .context("failed to parse the image")
+    }
+
+    fn name(&self) -> &'static str {
+        "Registry"
+    }
+}
+
+/// Provides the manifests and blobs of an image from its registry.
+struct RegistryImage(RegistryApi);
+
+impl RemoteImage for RegistryImage {
+    fn fetch_manifest(&self, digest: &Sha256Digest) -> anyhow::Result<Vec<u8>> {
+        Ok(self.0.get_manifest(&digest_reference(digest))?)
+    }
+
+    fn fetch_blob(
+        &self,
+        digest: &Sha256Digest,
+    ) -> anyhow::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(self.0.get_blob(&digest_reference(digest))?))
+    }
+}
+
+/// Formats the provided digest as it's used in the registry API (`sha256:<hex>`).
+fn digest_reference(digest: &Sha256Digest) -> String {
+    let hex = digest
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    format!("sha256:{hex}")
+}
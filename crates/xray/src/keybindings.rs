@@ -16,7 +16,7 @@ pub enum KeyAction {
     // NOTE: crossterm reports Shift+q as "Shift+Q" on MacOS.
     #[keybindings["Control+c", "Q", "Shift+Q"]]
     Exit,
-    /// Close active window (i.e. the help popup).
+    /// Close active window (i.e. the help popup, the node details popup or the file contents/diff popup).
     ///
     /// Can also **close the application** if there are no active windows.
     #[keybindings["q"]]
@@ -33,6 +33,21 @@ pub enum KeyAction {
     /// 2. Changing size units.
     #[keybindings["Control+l"]]
     FilterSubaction,
+    /// Toggle the mode, owner and modification time columns in the Layer Inspector pane.
+    #[keybindings["m"]]
+    ToggleMetadataColumns,
+    /// Toggle the popup with details (mode, ownership, modification time, etc.) of the selected node in the Layer Inspector pane.
+    #[keybindings["i"]]
+    ToggleNodeDetails,
+    /// Toggle the popup with the contents of the selected file (as of the selected layer) in the Layer Inspector pane.
+    #[keybindings["v"]]
+    ToggleFileContents,
+    /// Toggle the popup with a diff between the selected modified file and its version from the previous layer that updated it in the Layer Inspector pane.
+    #[keybindings["d"]]
+    ToggleFileDiff,
+    /// Extract the selected file or directory (as of the selected layer) from the Layer Inspector pane into a directory that is asked for in a popup.
+    #[keybindings["x"]]
+    ExtractNode,
 
     /// <Context-dependent movements>
 
@@ -3,12 +3,18 @@
 mod logging;
 pub use logging::init_logging;
 mod config;
-pub use config::Config;
+pub use config::{Command, Config};
 mod parser;
-pub use parser::Parser;
+pub use parser::{Image, LayerCache, Parser};
 mod tui;
 pub use tui::AppDispatcher;
 mod image_source;
-pub use image_source::resolve_image_from_config;
+pub use image_source::{resolve_image, resolve_image_from_config};
 mod keybindings;
 pub use keybindings::init_keybindings;
+mod ci;
+pub use ci::{CiRules, run_ci};
+mod export;
+pub use export::{ExportOptions, export_image};
+mod extract;
+pub use extract::{ExtractOptions, extract_image};
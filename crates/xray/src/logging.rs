@@ -29,15 +29,19 @@ pub fn init_logging(config_folder: &Path) -> anyhow::Result<()> {
 
     tracing_subscriber::registry()
         .with(
+            // Keep stdout clean for the non-interactive modes (e.g. when exporting the image)
             layer()
                 .with_target(false)
+                .with_writer(std::io::stderr)
                 .with_filter(LevelFilter::INFO)
                 .and_then(
-                    layer().with_file(true).with_line_number(true).with_filter(
-                        filter_fn(|metadata| {
+                    layer()
+                        .with_writer(std::io::stderr)
+                        .with_file(true)
+                        .with_line_number(true)
+                        .with_filter(filter_fn(|metadata| {
                             *metadata.level() > LevelFilter::INFO
-                        }),
-                    ),
+                        })),
                 )
                 .with_filter(env_filter),
         )
@@ -1,9 +1,11 @@
+use std::io::IsTerminal as _;
 use std::path::Path;
 
 use anyhow::Context;
 use xray_tui::{
-    AppDispatcher, Config, init_keybindings, init_logging,
-    resolve_image_from_config,
+    AppDispatcher, Command, Config, Image, LayerCache, export_image,
+    extract_image, init_keybindings, init_logging, resolve_image,
+    resolve_image_from_config, run_ci,
 };
 
 fn main() -> anyhow::Result<()> {
@@ -12,14 +14,80 @@ fn main() -> anyhow::Result<()> {
     init_logging(Path::new(config.state_path()))?;
     init_keybindings(Path::new(config.config_path()))?;
 
-    let image = resolve_image_from_config(&config)
+    if config.clear_cache() {
+        LayerCache::new(config.cache_path())
+            .clear()
+            .context("failed to clear the layer cache")?;
+    }
+
+    let mut image = resolve_image_from_config(&config)
         .context("failed to resolve the image")?;
     if image.layers.is_empty() {
         anyhow::bail!("Got an image with zero layers, nothing to inspect here")
     }
 
+    if let Command::Export(options) = config.command() {
+        return export_image(&image, options)
+            .context("failed to export the image");
+    }
+
+    if let Command::Extract(options) = config.command() {
+        return extract_image(&image, options)
+            .context("failed to extract the files");
+    }
+
+    if config.ci() {
+        if config.verify() {
+            report_verification_results(&image)?;
+            println!();
+        }
+        return run_ci(&image, config.ci_rules());
+    }
+
+    if config.verify() && !std::io::stdout().is_terminal() {
+        // There is no one to look at the UI, so just report the results
+        return report_verification_results(&image);
+    }
+
+    if let Some(compare_to) = config.compare_to() {
+        let other_image = resolve_image(&config, compare_to)
+            .context("failed to resolve the image to compare with")?;
+        image = image
+            .compare(other_image)
+            .context("failed to compare the images")?;
+    }
+
     AppDispatcher::init(image)
         .context("failed to initialize the app")?
         .run_until_stopped()
         .context("error during execution")
 }
+
+/// Prints the results of verifying the image's layers.
+///
+/// Returns an error if any of the layers didn't pass verification.
+fn report_verification_results(image: &Image) -> anyhow::Result<()> {
+    let mut failed_layers = 0;
+    for (idx, (_, layer)) in image.layers.iter().enumerate() {
+        let errors = layer.verification_errors.as_deref().unwrap_or_default();
+        let status = if errors.is_empty() { "OK" } else { "FAILED" };
+        println!("Layer {} ({}): {}", idx, layer.created_by, status);
+
+        for error in errors {
+            println!("  - {}", error);
+        }
+        if !errors.is_empty() {
+            failed_layers += 1;
+        }
+    }
+
+    if failed_layers != 0 {
+        anyhow::bail!(
+            "{} out of {} layers failed verification",
+            failed_layers,
+            image.layers.len()
+        )
+    }
+
+    Ok(())
+}
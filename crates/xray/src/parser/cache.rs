@@ -0,0 +1,572 @@
+//! Contains the on-disk cache of parsed layers.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::Context;
+
+use super::node::{
+    InnerNode, Node, NodeKind, NodeMetadata, NodeName, intern_owner_name,
+};
+use super::util::sha256_digest_to_hex;
+use super::{
+    DirMap, DirectoryState, FileState, LayerChangeSet, LayerSize, NodeStatus,
+    Sha256Digest,
+};
+
+/// Identifies files that were created by the [LayerCache].
+const CACHE_FILE_MAGIC: &[u8; 8] = b"XRAYLYR\0";
+/// Version of the cache file format.
+///
+/// NOTE: must be bumped whenever the format or the way the layers are parsed changes, as cached layers would silently
+/// become stale otherwise.
+const CACHE_FORMAT_VERSION: u32 = 3;
+const CACHE_FILE_EXTENSION: &str = "layer";
+
+/// The maximum total size of all cached layers, after which the least recently used ones are evicted.
+pub const DEFAULT_MAX_CACHE_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// An on-disk cache of parsed layers, keyed by the digests the layers are referenced by.
+///
+/// Errors that happen while using the cache are never fatal, as the layers can always be parsed again.
+#[derive(Debug, Clone)]
+pub struct LayerCache {
+    dir: PathBuf,
+    max_size: u64,
+}
+
+impl LayerCache {
+    /// Creates a new [LayerCache] that stores the layers in the provided directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        LayerCache {
+            dir: dir.into(),
+            max_size: DEFAULT_MAX_CACHE_SIZE,
+        }
+    }
+
+    /// Sets the maximum total size of all cached layers in bytes.
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Removes all cached layers.
+    pub fn clear(&self) -> anyhow::Result<()> {
+        match std::fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).context("failed to remove the cache directory")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the cached layer with the provided digest if it's present in the cache.
+    pub(super) fn get(
+        &self,
+        digest: &Sha256Digest,
+    ) -> Option<(LayerChangeSet, LayerSize)> {
+        let path = self.layer_path(digest);
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                tracing::debug!(
+                    "Failed to open the cached layer {}: {}",
+                    path.display(),
+                    e
+                );
+                return None;
+            }
+        };
+
+        match read_cache_file(&mut file) {
+            Ok(layer) => {
+                // Eviction is based on the modification time, so this marks the layer as recently used
+                let _ = file.set_modified(SystemTime::now());
+                Some(layer)
+            }
+            Err(e) => {
+                tracing::debug!(
+                    "Removing the invalid cached layer {}: {:#}",
+                    path.display(),
+                    e
+                );
+                let _ = std::fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    /// Stores the provided layer in the cache, replacing the existing one if it's present.
+    pub(super) fn insert(
+        &self,
+        digest: &Sha256Digest,
+        changeset: &LayerChangeSet,
+        size: LayerSize,
+    ) {
+        if let Err(e) = self.try_insert(digest, changeset, size) {
+            tracing::debug!(
+                "Failed to cache layer {}: {:#}",
+                sha256_digest_to_hex(digest),
+                e
+            );
+        }
+    }
+
+    fn try_insert(
+        &self,
+        digest: &Sha256Digest,
+        changeset: &LayerChangeSet,
+        size: LayerSize,
+    ) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .context("failed to create the cache directory")?;
+
+        // Write to a temporary file first, so that other instances never see partially written layers
+        let mut file = tempfile::NamedTempFile::new_in(&self.dir)
+            .context("failed to create a temporary file")?;
+        let mut dst = BufWriter::new(file.as_file_mut());
+        dst.write_all(CACHE_FILE_MAGIC)?;
+        dst.write_all(&CACHE_FORMAT_VERSION.to_le_bytes())?;
+        dst.write_all(&size.to_le_bytes())?;
+        encode_node(changeset, &mut dst)?;
+        dst.flush()?;
+        drop(dst);
+
+        file.persist(self.layer_path(digest))
+            .context("failed to move the cached layer into place")?;
+
+        Ok(())
+    }
+
+    /// Removes the least recently used layers until the total size of the cache fits into its maximum size.
+    pub(super) fn evict(&self) {
+        if let Err(e) = self.try_evict() {
+            tracing::debug!("Failed to evict layers from the cache: {:#}", e);
+        }
+    }
+
+    fn try_evict(&self) -> anyhow::Result<()> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(());
+            }
+            Err(e) => {
+                return Err(e).context("failed to read the cache directory");
+            }
+        };
+
+        let mut cached_layers = Vec::new();
+        let mut total_size = 0;
+        for entry in entries {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_none_or(|ext| ext != CACHE_FILE_EXTENSION)
+            {
+                continue;
+            }
+            let metadata = std::fs::metadata(&path)?;
+            total_size += metadata.len();
+            cached_layers.push((metadata.modified()?, metadata.len(), path));
+        }
+
+        if total_size <= self.max_size {
+            return Ok(());
+        }
+
+        cached_layers.sort_unstable();
+        for (_, size, path) in cached_layers {
+            if total_size <= self.max_size {
+                break;
+            }
+            tracing::debug!("Evicting {} from the cache", path.display());
+            std::fs::remove_file(&path)?;
+            total_size -= size;
+        }
+
+        Ok(())
+    }
+
+    fn layer_path(&self, digest: &Sha256Digest) -> PathBuf {
+        self.dir.join(format!(
+            "{}.{}",
+            sha256_digest_to_hex(digest),
+            CACHE_FILE_EXTENSION
+        ))
+    }
+}
+
+/// Reads a cached layer, making sure that it was written using the current format version.
+fn read_cache_file(
+    file: &mut File,
+) -> anyhow::Result<(LayerChangeSet, LayerSize)> {
+    let mut data = Vec::new();
+    std::io::Read::read_to_end(file, &mut data)
+        .context("failed to read the file")?;
+    let mut src = Decoder { data: &data };
+
+    if src.take(CACHE_FILE_MAGIC.len())? != CACHE_FILE_MAGIC {
+        anyhow::bail!("not a cached layer");
+    }
+    let version = src.u32()?;
+    if version != CACHE_FORMAT_VERSION {
+        anyhow::bail!("unsupported format version {}", version);
+    }
+    let size = src.u64()?;
+    let changeset = decode_node(&mut src)?;
+    if !src.data.is_empty() {
+        anyhow::bail!("unexpected data after the layer");
+    }
+
+    Ok((changeset, size))
+}
+
+fn encode_node(node: &Node, dst: &mut impl Write) -> anyhow::Result<()> {
+    dst.write_all(&[node.updated_in])?;
+    match &node.inner {
+        InnerNode::File(state) => {
+            dst.write_all(&[0])?;
+            encode_status(state.status, dst)?;
+            dst.write_all(&[encode_kind(state.kind)])?;
+            encode_optional(state.actual_file.as_deref(), dst, encode_path)?;
+            encode_optional(state.metadata.as_ref(), dst, encode_metadata)?;
+            dst.write_all(&state.content_offset.to_le_bytes())?;
+        }
+        InnerNode::Directory(state) => {
+            dst.write_all(&[1])?;
+            encode_status(state.status, dst)?;
+            dst.write_all(&[state.opaque as u8])?;
+            encode_optional(state.metadata.as_ref(), dst, encode_metadata)?;
+            dst.write_all(&(state.children.len() as u64).to_le_bytes())?;
+            for (path, child) in state.children.iter() {
+                encode_path(path, dst)?;
+                encode_node(child, dst)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn decode_node(src: &mut Decoder<'_>) -> anyhow::Result<Node> {
+    let updated_in = src.u8()?;
+    let inner = match src.u8()? {
+        0 => InnerNode::File(FileState {
+            status: decode_status(src)?,
+            kind: decode_kind(src.u8()?)?,
+            actual_file: decode_optional(src, decode_path)?,
+            metadata: decode_optional(src, decode_metadata)?,
+            content_offset: src.u64()?,
+        }),
+        1 => {
+            let status = decode_status(src)?;
+            let opaque = src.u8()? != 0;
+            let metadata = decode_optional(src, decode_metadata)?;
+            let mut children = DirMap::new();
+            for _ in 0..src.u64()? {
+                let name = NodeName::new(&decode_path(src)?);
+                children.insert(name, decode_node(src)?);
+            }
+            InnerNode::Directory(DirectoryState {
+                status,
+                children: Arc::new(children),
+                opaque,
+                metadata,
+            })
+        }
+        tag => anyhow::bail!("unknown node tag {}", tag),
+    };
+
+    Ok(Node::new_with_inner(updated_in, inner))
+}
+
+fn encode_status(
+    status: NodeStatus,
+    dst: &mut impl Write,
+) -> anyhow::Result<()> {
+    match status {
+        NodeStatus::Added(size) => {
+            dst.write_all(&[0])?;
+            dst.write_all(&size.to_le_bytes())?;
+        }
+        NodeStatus::Modified(size) => {
+            dst.write_all(&[1])?;
+            dst.write_all(&size.to_le_bytes())?;
+        }
+        NodeStatus::Deleted(size) => {
+            dst.write_all(&[2])?;
+            dst.write_all(&size.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+fn decode_status(src: &mut Decoder<'_>) -> anyhow::Result<NodeStatus> {
+    Ok(match src.u8()? {
+        0 => NodeStatus::Added(src.u64()?),
+        1 => NodeStatus::Modified(src.u64()?),
+        2 => NodeStatus::Deleted(src.u64()?),
+        tag => anyhow::bail!("unknown node status {}", tag),
+    })
+}
+
+fn encode_kind(kind: NodeKind) -> u8 {
+    match kind {
+        NodeKind::File => 0,
+        NodeKind::Directory => 1,
+        NodeKind::Symlink => 2,
+        NodeKind::Hardlink => 3,
+        NodeKind::CharDevice => 4,
+        NodeKind::BlockDevice => 5,
+        NodeKind::Fifo => 6,
+        NodeKind::Other => 7,
+    }
+}
+
+fn decode_kind(tag: u8) -> anyhow::Result<NodeKind> {
+    Ok(match tag {
+        0 => NodeKind::File,
+        1 => NodeKind::Directory,
+        2 => NodeKind::Symlink,
+        3 => NodeKind::Hardlink,
+        4 => NodeKind::CharDevice,
+        5 => NodeKind::BlockDevice,
+        6 => NodeKind::Fifo,
+        7 => NodeKind::Other,
+        tag => anyhow::bail!("unknown node kind {}", tag),
+    })
+}
+
+fn encode_metadata(
+    metadata: &NodeMetadata,
+    dst: &mut impl Write,
+) -> anyhow::Result<()> {
+    dst.write_all(&metadata.mode.to_le_bytes())?;
+    dst.write_all(&metadata.uid.to_le_bytes())?;
+    dst.write_all(&metadata.gid.to_le_bytes())?;
+    encode_optional(metadata.uname.as_deref(), dst, encode_str)?;
+    encode_optional(metadata.gname.as_deref(), dst, encode_str)?;
+    dst.write_all(&metadata.mtime.to_le_bytes())?;
+    Ok(())
+}
+
+fn decode_metadata(src: &mut Decoder<'_>) -> anyhow::Result<NodeMetadata> {
+    Ok(NodeMetadata {
+        mode: src.u32()?,
+        uid: src.u64()?,
+        gid: src.u64()?,
+        uname: decode_optional(src, decode_str)?
+            .map(|name| intern_owner_name(&name)),
+        gname: decode_optional(src, decode_str)?
+            .map(|name| intern_owner_name(&name)),
+        mtime: src.u64()?,
+    })
+}
+
+/// Paths are stored as strings, so layers with non-UTF-8 paths are not cached.
+fn encode_path(path: &Path, dst: &mut impl Write) -> anyhow::Result<()> {
+    let path = path
+        .to_str()
+        .with_context(|| format!("non-UTF-8 path {}", path.display()))?;
+    encode_str(path, dst)
+}
+
+fn decode_path(src: &mut Decoder<'_>) -> anyhow::Result<PathBuf> {
+    decode_str(src).map(PathBuf::from)
+}
+
+fn encode_str(value: &str, dst: &mut impl Write) -> anyhow::Result<()> {
+    dst.write_all(&(value.len() as u64).to_le_bytes())?;
+    dst.write_all(value.as_bytes())?;
+    Ok(())
+}
+
+fn decode_str(src: &mut Decoder<'_>) -> anyhow::Result<String> {
+    let len = src.u64()?;
+    let bytes = src.take(usize::try_from(len)?)?;
+    String::from_utf8(bytes.to_vec()).context("invalid UTF-8 string")
+}
+
+fn encode_optional<T: ?Sized, W: Write>(
+    value: Option<&T>,
+    dst: &mut W,
+    encode: impl Fn(&T, &mut W) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    match value {
+        Some(value) => {
+            dst.write_all(&[1])?;
+            encode(value, dst)
+        }
+        None => Ok(dst.write_all(&[0])?),
+    }
+}
+
+fn decode_optional<'a, T>(
+    src: &mut Decoder<'a>,
+    decode: impl Fn(&mut Decoder<'a>) -> anyhow::Result<T>,
+) -> anyhow::Result<Option<T>> {
+    match src.u8()? {
+        0 => Ok(None),
+        1 => decode(src).map(Some),
+        tag => anyhow::bail!("unknown option tag {}", tag),
+    }
+}
+
+/// Reads primitive values from a cached layer, failing on truncated data instead of panicking.
+struct Decoder<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let Some((taken, rest)) = self.data.split_at_checked(len) else {
+            anyhow::bail!("unexpected end of data");
+        };
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::parser::RestorablePath;
+
+    fn make_changeset() -> LayerChangeSet {
+        let mut changeset = LayerChangeSet::new(0);
+        let metadata = NodeMetadata {
+            mode: 0o4755,
+            uid: 1000,
+            gid: 1000,
+            uname: Some("user".into()),
+            gname: None,
+            mtime: 1_700_000_000,
+        };
+        for (path, node) in [
+            (
+                "usr/bin/tool",
+                InnerNode::File(
+                    FileState::new(NodeStatus::Added(42), None)
+                        .with_metadata(metadata)
+                        .with_content_offset(1536),
+                ),
+            ),
+            (
+                "usr/bin/link",
+                InnerNode::File(
+                    FileState::new(NodeStatus::Added(0), Some("tool".into()))
+                        .with_kind(NodeKind::Symlink),
+                ),
+            ),
+            (
+                "etc/removed",
+                InnerNode::File(FileState::new(NodeStatus::Deleted(0), None)),
+            ),
+            ("var/opaque", InnerNode::new_opaque_dir()),
+        ] {
+            changeset
+                .insert(&mut RestorablePath::new(Path::new(path)), node, 0)
+                .unwrap();
+        }
+        changeset
+    }
+
+    fn encode(changeset: &LayerChangeSet) -> Vec<u8> {
+        let mut data = Vec::new();
+        encode_node(changeset, &mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn cached_layer_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = LayerCache::new(dir.path());
+        let changeset = make_changeset();
+
+        assert!(cache.get(&[1; 32]).is_none());
+        cache.insert(&[1; 32], &changeset, 42);
+
+        let (cached_changeset, size) = cache.get(&[1; 32]).unwrap();
+        assert_eq!(size, 42);
+        assert_eq!(encode(&cached_changeset), encode(&changeset));
+
+        let children = cached_changeset.inner.children().unwrap();
+        let tool = &children[Path::new("usr")].inner.children().unwrap()
+            [Path::new("bin")]
+        .inner
+        .children()
+        .unwrap()[Path::new("tool")];
+        assert_eq!(tool.inner.size(), 42);
+        assert_eq!(tool.inner.metadata().unwrap().mode, 0o4755);
+        assert_eq!(tool.inner.content_offset(), Some(1536));
+    }
+
+    #[test]
+    fn invalid_cached_layers_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = LayerCache::new(dir.path());
+        cache.insert(&[1; 32], &make_changeset(), 42);
+
+        let path = cache.layer_path(&[1; 32]);
+        let mut data = std::fs::read(&path).unwrap();
+
+        // Truncated
+        std::fs::write(&path, &data[..data.len() - 1]).unwrap();
+        assert!(cache.get(&[1; 32]).is_none());
+        assert!(!path.exists());
+
+        // Written by a different version
+        data[CACHE_FILE_MAGIC.len()] += 1;
+        std::fs::write(&path, &data).unwrap();
+        assert!(cache.get(&[1; 32]).is_none());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn least_recently_used_layers_are_evicted() {
+        let dir = tempfile::tempdir().unwrap();
+        let changeset = make_changeset();
+        let layer_size = {
+            let cache = LayerCache::new(dir.path());
+            cache.insert(&[0; 32], &changeset, 0);
+            std::fs::metadata(cache.layer_path(&[0; 32])).unwrap().len()
+        };
+
+        let cache = LayerCache::new(dir.path()).with_max_size(layer_size * 2);
+        let now = SystemTime::now();
+        for (idx, digest) in [[1; 32], [2; 32], [3; 32]].iter().enumerate() {
+            cache.insert(digest, &changeset, 0);
+            File::options()
+                .write(true)
+                .open(cache.layer_path(digest))
+                .unwrap()
+                .set_modified(now + std::time::Duration::from_secs(idx as u64))
+                .unwrap();
+        }
+        std::fs::remove_file(cache.layer_path(&[0; 32])).unwrap();
+
+        cache.evict();
+        assert!(!cache.layer_path(&[1; 32]).exists());
+        assert!(cache.layer_path(&[2; 32]).exists());
+        assert!(cache.layer_path(&[3; 32]).exists());
+    }
+}
@@ -0,0 +1,487 @@
+//! Contains the logic related to comparing two images.
+
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use sha2::{Digest as _, Sha256};
+
+use super::util::sha256_digest_to_hex;
+use super::{
+    DirMap, DirectoryState, FileState, Image, ImageEfficiency, InnerNode,
+    Layer, LayerChangeSet, Node, NodeStatus, Sha256Digest, VerificationError,
+};
+
+/// Index of the layer that contains the final file tree of the base image in a comparison.
+const BASE_LAYER_IDX: u8 = 0;
+/// Index of the layer that turns the final file tree of the base image into the one of the target image.
+const TARGET_LAYER_IDX: u8 = 1;
+
+/// Describes how two images that were [compared](Image::compare) relate to each other.
+#[derive(Debug)]
+pub struct ImageComparison {
+    /// Name and tag of the image that the target image is compared against.
+    pub base_image: String,
+    /// Name and tag of the image that is compared against the base image.
+    pub target_image: String,
+    /// All layers of both images.
+    ///
+    /// Layers of the base image come first in their original order, followed by the layers that only exist in the
+    /// target image.
+    pub layers: Vec<ComparedLayer>,
+    /// Efficiency of the target image, as it can't be calculated from the layers of the comparison.
+    pub target_efficiency: ImageEfficiency,
+}
+
+/// A single layer from one of the compared images.
+#[derive(Debug)]
+pub struct ComparedLayer {
+    pub digest: Sha256Digest,
+    pub created_by: String,
+    pub presence: LayerPresence,
+}
+
+/// Which of the compared images contain a layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerPresence {
+    /// Both images contain the layer.
+    Shared,
+    /// Only the base image contains the layer.
+    OnlyInBase,
+    /// Only the target image contains the layer.
+    OnlyInTarget,
+}
+
+impl Image {
+    /// Compares this image with the `target` one and returns a new [Image] that represents the difference between them.
+    ///
+    /// The returned image has two layers. The first one contains the final file tree of this image, while the second
+    /// one contains all changes that turn it into the final file tree of the `target` image. Files are considered to
+    /// be changed if their size, type, link, or metadata are different.
+    ///
+    /// Everything else (e.g. the configuration) is taken from the `target` image.
+    pub fn compare(self, target: Image) -> anyhow::Result<Image> {
+        let base_chain_id = chain_id(&self);
+        let target_chain_id = chain_id(&target);
+        if base_chain_id == target_chain_id {
+            anyhow::bail!(
+                "both images have the same layers, there is nothing to compare"
+            )
+        }
+
+        let base_name = format!("{}:{}", self.image_name, self.tag);
+        let target_name = format!("{}:{}", target.image_name, target.tag);
+
+        let mut base_tree = final_tree(&self);
+        mark_as_added(&mut base_tree, BASE_LAYER_IDX);
+        let target_tree = final_tree(&target);
+        let changes = diff_trees(&base_tree, &target_tree);
+
+        let layers = compare_layers(&self, &target);
+        let shared_layers = layers
+            .iter()
+            .filter(|layer| layer.presence == LayerPresence::Shared)
+            .count();
+        let comment = format!(
+            "Shares {} out of {} layers with {}",
+            shared_layers,
+            target.layers.len(),
+            base_name
+        );
+
+        let target_efficiency = target.efficiency();
+        let comparison_layers = IndexMap::from([
+            (
+                base_chain_id,
+                Layer {
+                    changeset: Some(base_tree),
+                    size: self.size,
+                    created_by: base_name.clone(),
+                    comment: None,
+                    diff_id: None,
+                    history_mismatch: false,
+                    verification_errors: verification_errors(self.layers),
+                    blob: None,
+                },
+            ),
+            (
+                target_chain_id,
+                Layer {
+                    changeset: Some(changes),
+                    size: target.size,
+                    created_by: target_name.clone(),
+                    comment: Some(comment),
+                    diff_id: None,
+                    history_mismatch: false,
+                    verification_errors: verification_errors(target.layers),
+                    blob: None,
+                },
+            ),
+        ]);
+
+        Ok(Image {
+            image_name: target.image_name,
+            tag: target.tag,
+            size: target.size,
+            architecture: target.architecture,
+            os: target.os,
+            variant: target.variant,
+            os_version: target.os_version,
+            created: target.created,
+            author: target.author,
+            runtime_config: target.runtime_config,
+            total_layers: comparison_layers.len(),
+            non_empty_layers: comparison_layers.len(),
+            layers: comparison_layers,
+            comparison: Some(ImageComparison {
+                base_image: base_name,
+                target_image: target_name,
+                layers,
+                target_efficiency,
+            }),
+        })
+    }
+}
+
+/// Calculates the [ChainID](https://github.com/opencontainers/image-spec/blob/main/config.md#layer-chainid) of the
+/// image's topmost layer, which uniquely identifies the final file tree of the image.
+///
+/// Layer digests are used instead of diff IDs if the latter are missing.
+fn chain_id(image: &Image) -> Sha256Digest {
+    let mut chain_id: Option<Sha256Digest> = None;
+    for (digest, layer) in image.layers.iter() {
+        let diff_id = layer.diff_id.as_ref().unwrap_or(digest);
+        chain_id = Some(match chain_id {
+            Some(chain_id) => Sha256::digest(format!(
+                "sha256:{} sha256:{}",
+                sha256_digest_to_hex(&chain_id),
+                sha256_digest_to_hex(diff_id)
+            ))
+            .into(),
+            None => *diff_id,
+        });
+    }
+    chain_id.unwrap_or_default()
+}
+
+/// Merges all layers of the image and removes all deleted nodes from the result.
+fn final_tree(image: &Image) -> LayerChangeSet {
+    let mut tree = image
+        .layers
+        .values()
+        .filter_map(|layer| layer.changeset.clone())
+        .reduce(LayerChangeSet::merge)
+        .unwrap_or_else(|| LayerChangeSet::new(BASE_LAYER_IDX));
+    remove_deleted_nodes(&mut tree);
+    tree
+}
+
+/// Recursively removes all nodes with [NodeStatus::Deleted] from the tree.
+fn remove_deleted_nodes(node: &mut Node) {
+    if let InnerNode::Directory(state) = &mut node.inner {
+        let children = Arc::make_mut(&mut state.children);
+        children.retain(|_, child| !child.inner.is_deleted());
+        children.values_mut().for_each(remove_deleted_nodes);
+    }
+}
+
+/// Marks the node and its children as [NodeStatus::Added] in the provided layer.
+///
+/// This makes the nodes from the final trees look like they were added by a single layer, so that merging the
+/// compared trees calculates the correct statuses.
+fn mark_as_added(node: &mut Node, layer_idx: u8) {
+    node.updated_in = layer_idx;
+    let size = node.inner.size();
+    match &mut node.inner {
+        InnerNode::File(state) => state.status = NodeStatus::Added(size),
+        InnerNode::Directory(state) => {
+            state.status = NodeStatus::Added(size);
+            // The base tree must only be affected by the explicit whiteouts
+            state.opaque = false;
+            Arc::make_mut(&mut state.children)
+                .values_mut()
+                .for_each(|child| mark_as_added(child, layer_idx));
+        }
+    }
+}
+
+/// Returns a changeset that turns the `base` tree into the `target` one when merged with it.
+///
+/// Neither tree should contain deleted nodes.
+fn diff_trees(base: &Node, target: &Node) -> LayerChangeSet {
+    let mut changes = LayerChangeSet::new(TARGET_LAYER_IDX);
+    if let (Some(base_children), Some(target_children)) =
+        (base.inner.children(), target.inner.children())
+        && let InnerNode::Directory(state) = &mut changes.inner
+    {
+        state.children =
+            Arc::new(diff_children(base_children, target_children));
+    }
+    changes
+}
+
+/// Returns the nodes that should be put into a directory to turn its `base` children into the `target` ones.
+fn diff_children(base: &DirMap, target: &DirMap) -> DirMap {
+    let mut changes = DirMap::new();
+
+    for (name, target_node) in target.iter() {
+        let base_node = base.get(name);
+        let changed_node = match base_node
+            .map(|node| (&node.inner, &target_node.inner))
+        {
+            Some((
+                InnerNode::Directory(base_state),
+                InnerNode::Directory(target_state),
+            )) => {
+                let children =
+                    diff_children(&base_state.children, &target_state.children);
+                if children.is_empty()
+                    && base_state.metadata == target_state.metadata
+                {
+                    continue;
+                }
+
+                let mut state = DirectoryState::new_empty();
+                state.children = Arc::new(children);
+                state.metadata = target_state.metadata.clone();
+                Node::new_with_inner(
+                    TARGET_LAYER_IDX,
+                    InnerNode::Directory(state),
+                )
+            }
+            Some((
+                InnerNode::File(base_state),
+                InnerNode::File(target_state),
+            )) if is_same_file(base_state, target_state) => {
+                continue;
+            }
+            // The node is either new or has changed its type
+            _ => {
+                let mut node = target_node.clone();
+                mark_as_added(&mut node, TARGET_LAYER_IDX);
+                node
+            }
+        };
+
+        changes.insert(name.clone(), changed_node);
+    }
+
+    // Everything that is missing in the target directory is deleted using whiteouts
+    for name in base.keys().filter(|name| !target.contains_key(*name)) {
+        changes.insert(
+            name.clone(),
+            Node::new_with_inner(
+                TARGET_LAYER_IDX,
+                InnerNode::File(FileState::new(NodeStatus::Deleted(0), None)),
+            ),
+        );
+    }
+
+    changes
+}
+
+/// Returns `true` if both files have the same size, type, link, and metadata.
+fn is_same_file(base: &FileState, target: &FileState) -> bool {
+    let size = |state: &FileState| match state.status {
+        NodeStatus::Added(size) | NodeStatus::Modified(size) => size,
+        NodeStatus::Deleted(_) => 0,
+    };
+
+    size(base) == size(target)
+        && base.kind == target.kind
+        && base.actual_file == target.actual_file
+        && base.metadata == target.metadata
+}
+
+/// Compares the layers of both images using their digests and diff IDs.
+fn compare_layers(base: &Image, target: &Image) -> Vec<ComparedLayer> {
+    let is_same_layer =
+        |(a_digest, a): (&Sha256Digest, &Layer),
+         (b_digest, b): (&Sha256Digest, &Layer)| {
+            a_digest == b_digest
+                || (a.diff_id.is_some() && a.diff_id == b.diff_id)
+        };
+
+    let mut layers = Vec::new();
+    for base_layer in base.layers.iter() {
+        let presence = if target
+            .layers
+            .iter()
+            .any(|target_layer| is_same_layer(base_layer, target_layer))
+        {
+            LayerPresence::Shared
+        } else {
+            LayerPresence::OnlyInBase
+        };
+        layers.push(ComparedLayer {
+            digest: *base_layer.0,
+            created_by: base_layer.1.created_by.clone(),
+            presence,
+        });
+    }
+    for target_layer in target.layers.iter() {
+        if !base
+            .layers
+            .iter()
+            .any(|base_layer| is_same_layer(base_layer, target_layer))
+        {
+            layers.push(ComparedLayer {
+                digest: *target_layer.0,
+                created_by: target_layer.1.created_by.clone(),
+                presence: LayerPresence::OnlyInTarget,
+            });
+        }
+    }
+
+    layers
+}
+
+/// Collects verification errors of all layers of an image, so that they are still visible after comparing it.
+fn verification_errors(
+    layers: IndexMap<Sha256Digest, Layer>,
+) -> Option<Vec<VerificationError>> {
+    let mut errors: Option<Vec<_>> = None;
+    for (_, layer) in layers {
+        if let Some(layer_errors) = layer.verification_errors {
+            errors.get_or_insert_default().extend(layer_errors);
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::parser::test_util::{Entry, make_image};
+
+    /// Returns the paths, statuses, and layer indexes of all nodes in the aggregated tree of the image.
+    fn summary(image: &Image) -> Vec<(PathBuf, String, u8)> {
+        fn collect(
+            node: &Node,
+            path: &mut PathBuf,
+            nodes: &mut Vec<(PathBuf, String, u8)>,
+        ) {
+            for (name, child) in node.inner.children().into_iter().flatten() {
+                path.push(name);
+                let inner = &child.inner;
+                let status = if let Some(size) = inner.deleted_size() {
+                    format!("deleted {size}")
+                } else if inner.is_modified() {
+                    format!("modified {}", inner.size())
+                } else {
+                    format!("added {}", inner.size())
+                };
+                nodes.push((path.clone(), status, child.updated_in));
+                collect(child, path, nodes);
+                path.pop();
+            }
+        }
+
+        let tree = image
+            .layers
+            .values()
+            .filter_map(|layer| layer.changeset.clone())
+            .reduce(LayerChangeSet::merge)
+            .unwrap();
+        let mut nodes = Vec::new();
+        collect(&tree, &mut PathBuf::from("/"), &mut nodes);
+        nodes
+    }
+
+    #[test]
+    fn compare_final_trees() {
+        let base = make_image(
+            "base",
+            &[
+                (
+                    1,
+                    &[
+                        Entry::File("a", 10),
+                        Entry::File("b", 20),
+                        Entry::File("dir/x", 5),
+                        Entry::File("dir/y", 5),
+                        Entry::File("gone", 1),
+                    ],
+                ),
+                (2, &[Entry::Whiteout("gone")]),
+            ],
+        );
+        let target = make_image(
+            "target",
+            &[
+                (
+                    1,
+                    &[
+                        Entry::File("a", 10),
+                        Entry::File("b", 20),
+                        Entry::File("dir/x", 5),
+                        Entry::File("dir/y", 5),
+                        Entry::File("gone", 1),
+                    ],
+                ),
+                (
+                    3,
+                    &[
+                        Entry::File("b", 25),
+                        Entry::Whiteout("dir/y"),
+                        Entry::Whiteout("gone"),
+                        Entry::File("new/c", 3),
+                    ],
+                ),
+            ],
+        );
+
+        let comparison = base.compare(target).unwrap();
+        assert_eq!(comparison.layers.len(), 2);
+        assert_eq!(comparison.image_name, "target");
+
+        let expected = [
+            ("/a", "added 10", 0),
+            ("/b", "modified 25", 1),
+            ("/dir", "modified 5", 1),
+            ("/dir/x", "added 5", 0),
+            ("/dir/y", "deleted 5", 1),
+            ("/new", "added 3", 1),
+            ("/new/c", "added 3", 1),
+        ]
+        .map(|(path, status, layer)| {
+            (PathBuf::from(path), status.to_string(), layer)
+        });
+        assert_eq!(summary(&comparison), expected);
+    }
+
+    #[test]
+    fn compare_layers_by_digest() {
+        let base = make_image(
+            "base",
+            &[(1, &[Entry::File("a", 1)]), (2, &[Entry::File("b", 1)])],
+        );
+        let target = make_image(
+            "target",
+            &[(1, &[Entry::File("a", 1)]), (3, &[Entry::File("c", 1)])],
+        );
+
+        let comparison = base.compare(target).unwrap().comparison.unwrap();
+        assert_eq!(comparison.base_image, "base:latest");
+        assert_eq!(comparison.target_image, "target:latest");
+        assert_eq!(
+            comparison
+                .layers
+                .iter()
+                .map(|layer| (layer.digest[0], layer.presence))
+                .collect::<Vec<_>>(),
+            vec![
+                (1, LayerPresence::Shared),
+                (2, LayerPresence::OnlyInBase),
+                (3, LayerPresence::OnlyInTarget),
+            ]
+        );
+    }
+
+    #[test]
+    fn compare_images_with_same_layers() {
+        let image = || make_image("image", &[(1, &[Entry::File("a", 1)])]);
+        assert!(image().compare(image()).is_err());
+    }
+}
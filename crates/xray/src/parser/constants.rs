@@ -1,6 +1,17 @@
 pub(super) const BLOB_PATH_PREFIX: &[u8] = b"blobs/sha256/";
+pub(super) const LEGACY_LAYER_PATH_SUFFIX: &[u8] = b"/layer.tar";
+pub(super) const LEGACY_CONFIG_PATH_SUFFIX: &[u8] = b".json";
 
 pub(super) const GZIP_MAGIC_NUMBER: [u8; 2] = [0x1f, 0x8b];
+pub(super) const ZSTD_MAGIC_NUMBER: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+pub(super) const XZ_MAGIC_NUMBER: [u8; 6] =
+    [0xfd, b'7', b'z', b'X', b'Z', 0x00];
+pub(super) const BZIP2_MAGIC_NUMBER: [u8; 3] = *b"BZh";
+
+pub(super) const OCI_LAYER_MEDIA_TYPE_PREFIX: &str =
+    "application/vnd.oci.image.layer.";
+pub(super) const DOCKER_LAYER_MEDIA_TYPE_PREFIX: &str =
+    "application/vnd.docker.image.rootfs.";
 
 pub(super) const TAR_BLOCK_SIZE: usize = 512;
 pub(super) const TAR_MAGIC_NUMBER_START_IDX: usize = 257;
@@ -9,5 +20,12 @@ pub(super) const TAR_MAGIC_NUMBER: &[u8] = b"ustar";
 pub(super) const SHA256_DIGEST_PREFIX: &[u8] = b"sha256:";
 pub(super) const SHA256_DIGEST_LENGTH: usize = 32;
 
+pub(super) const WHITEOUT_PREFIX: &[u8] = b".wh.";
+pub(super) const OPAQUE_WHITEOUT_FILE_NAME: &[u8] = b".wh..wh..opq";
+
 pub(super) const IMAGE_MANIFEST_PATH: &[u8] = b"manifest.json";
 pub(super) const IMAGE_INDEX_PATH: &[u8] = b"index.json";
+
+pub(super) const IMAGE_LAYOUT_INDEX_PATH: &str = "index.json";
+pub(super) const IMAGE_LAYOUT_BLOBS_PATH: &str = "blobs/sha256";
+pub(super) const DEFAULT_IMAGE_TAG: &str = "latest";
@@ -0,0 +1,363 @@
+//! Contains the logic related to reading the contents of files from the image's layers after parsing them.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Context;
+use bzip2::read::MultiBzDecoder;
+use flate2::read::GzDecoder;
+use xz2::read::XzDecoder;
+
+use super::remote::RemoteImage;
+use super::util::sha256_digest_to_hex;
+use super::{BlobType, Sha256Digest};
+
+/// A reader that can be used to read the image.
+pub(super) trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// Opens a new independent reader over the image each time it's called.
+pub(super) type ImageOpener =
+    Arc<dyn Fn() -> std::io::Result<Box<dyn ReadSeek>> + Send + Sync>;
+
+/// Location of a layer's blob, which is used to read the contents of the layer's files after parsing the image.
+///
+/// The contents of files within uncompressed blobs are read by seeking to them, while compressed blobs are decoded up
+/// to the file's position.
+#[derive(Clone)]
+pub struct LayerBlob {
+    source: BlobSource,
+    blob_type: BlobType,
+}
+
+/// Where a [LayerBlob] is stored.
+#[derive(Clone)]
+enum BlobSource {
+    /// The blob is stored within the image.
+    Image {
+        open: ImageOpener,
+        /// Absolute position of the blob's contents within the image.
+        offset: u64,
+        /// Size of the blob in bytes.
+        size: u64,
+    },
+    /// The blob is stored in its own file (e.g. in an OCI Image Layout directory).
+    File(PathBuf),
+    /// The blob is stored remotely (e.g. in a registry) and is downloaded each time it's read.
+    Remote {
+        remote: Arc<dyn RemoteImage>,
+        digest: Sha256Digest,
+    },
+}
+
+impl LayerBlob {
+    /// Creates a new [LayerBlob] that is stored within the image at the provided offset.
+    pub(super) fn in_image(
+        open: ImageOpener,
+        offset: u64,
+        size: u64,
+        blob_type: BlobType,
+    ) -> Self {
+        LayerBlob {
+            source: BlobSource::Image { open, offset, size },
+            blob_type,
+        }
+    }
+
+    /// Creates a new [LayerBlob] that is stored in its own file.
+    pub(super) fn in_file(path: PathBuf, blob_type: BlobType) -> Self {
+        LayerBlob {
+            source: BlobSource::File(path),
+            blob_type,
+        }
+    }
+
+    /// Creates a new [LayerBlob] that is stored remotely.
+    pub(super) fn remote(
+        remote: Arc<dyn RemoteImage>,
+        digest: Sha256Digest,
+        blob_type: BlobType,
+    ) -> Self {
+        LayerBlob {
+            source: BlobSource::Remote { remote, digest },
+            blob_type,
+        }
+    }
+
+    /// Reads up to `limit` bytes of a file's contents that start at `content_offset` within the uncompressed layer and
+    /// are `size` bytes long.
+    ///
+    /// Check [InnerNode::content_offset](super::InnerNode::content_offset) for how to get the file's offset.
+    pub fn read_file(
+        &self,
+        content_offset: u64,
+        size: u64,
+        limit: u64,
+    ) -> anyhow::Result<Vec<u8>> {
+        let to_read = size.min(limit);
+        let mut contents = Vec::with_capacity(to_read as usize);
+        self.reader()?
+            .file(content_offset, to_read)?
+            .read_to_end(&mut contents)
+            .context("failed to read the file's contents")?;
+
+        if (contents.len() as u64) < to_read {
+            anyhow::bail!("the layer blob ended before the end of the file")
+        }
+
+        Ok(contents)
+    }
+
+    /// Opens the blob to read the contents of several files from it.
+    ///
+    /// This is cheaper than calling [Self::read_file] for each file, as compressed blobs are only decoded once.
+    pub fn reader(&self) -> anyhow::Result<LayerReader> {
+        let (mut src, blob_offset, blob_size): (Box<dyn ReadSeek>, _, _) =
+            match &self.source {
+                BlobSource::Image { open, offset, size } => (
+                    open().context("failed to open the image")?,
+                    *offset,
+                    *size,
+                ),
+                BlobSource::File(path) => {
+                    let blob = File::open(path).with_context(|| {
+                        format!("failed to open the '{}' blob", path.display())
+                    })?;
+                    let size = blob
+                        .metadata()
+                        .context("failed to get the blob's size")?
+                        .len();
+                    (Box::new(BufReader::new(blob)), 0, size)
+                }
+                BlobSource::Remote { remote, digest } => {
+                    // Remote blobs are streamed, so even uncompressed ones can only be read sequentially
+                    let blob = remote
+                        .fetch_blob(digest)
+                        .context("failed to download the layer blob")?;
+                    return Ok(LayerReader(ReaderKind::Decoded {
+                        src: decoder(self.blob_type, BufReader::new(blob))?,
+                        position: 0,
+                    }));
+                }
+            };
+
+        if let BlobType::Tar = self.blob_type {
+            // Uncompressed blobs can be read directly
+            return Ok(LayerReader(ReaderKind::Seekable { src, blob_offset }));
+        }
+
+        src.seek(SeekFrom::Start(blob_offset))
+            .context("failed to seek to the layer blob")?;
+        Ok(LayerReader(ReaderKind::Decoded {
+            src: decoder(self.blob_type, src.take(blob_size))?,
+            position: 0,
+        }))
+    }
+}
+
+/// Reads the contents of files from a single [LayerBlob].
+pub struct LayerReader(ReaderKind);
+
+/// How a [LayerReader] reads its blob.
+enum ReaderKind {
+    /// An uncompressed blob, which allows reading files in any order.
+    Seekable {
+        src: Box<dyn ReadSeek>,
+        /// Absolute position of the blob's contents within the source.
+        blob_offset: u64,
+    },
+    /// A compressed blob, which only allows reading files in the order they are stored in the layer.
+    Decoded {
+        src: Box<dyn Read>,
+        /// Number of bytes that were already decoded.
+        position: u64,
+    },
+}
+
+impl LayerReader {
+    /// Returns a reader over the contents of a file that start at `content_offset` within the uncompressed layer and
+    /// are `size` bytes long.
+    ///
+    /// Files from compressed blobs must be read in the order of their offsets.
+    pub fn file(
+        &mut self,
+        content_offset: u64,
+        size: u64,
+    ) -> anyhow::Result<impl Read + '_> {
+        let src: Box<dyn Read + '_> = match &mut self.0 {
+            ReaderKind::Seekable { src, blob_offset } => {
+                src.seek(SeekFrom::Start(*blob_offset + content_offset))
+                    .context("failed to seek to the file's contents")?;
+                Box::new(src)
+            }
+            ReaderKind::Decoded { src, position } => {
+                let Some(to_skip) = content_offset.checked_sub(*position)
+                else {
+                    anyhow::bail!(
+                        "files from compressed layers must be read in the order of their offsets"
+                    )
+                };
+                // Compressed blobs don't support seeking, so everything before the file's contents has to be decoded
+                let skipped =
+                    std::io::copy(&mut src.take(to_skip), &mut std::io::sink())
+                        .context("failed to decode the layer blob")?;
+                *position += skipped;
+                if skipped != to_skip {
+                    anyhow::bail!(
+                        "the layer blob ended before the file's contents"
+                    )
+                }
+                Box::new(CountingReader {
+                    src,
+                    count: position,
+                })
+            }
+        };
+        Ok(src.take(size))
+    }
+}
+
+/// Keeps track of the number of bytes read from a compressed blob.
+struct CountingReader<'a, R> {
+    src: R,
+    count: &'a mut u64,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.src.read(buf)?;
+        *self.count += read as u64;
+        Ok(read)
+    }
+}
+
+impl std::fmt::Debug for LayerBlob {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("LayerBlob");
+        match &self.source {
+            BlobSource::Image { offset, size, .. } => {
+                debug.field("offset", offset).field("size", size)
+            }
+            BlobSource::File(path) => debug.field("path", path),
+            BlobSource::Remote { digest, .. } => {
+                debug.field("digest", &sha256_digest_to_hex(digest))
+            }
+        };
+        debug.field("blob_type", &self.blob_type).finish()
+    }
+}
+
+/// Wraps the provided reader into a decoder that matches the compression of a layer blob of the provided [BlobType].
+fn decoder<'a>(
+    blob_type: BlobType,
+    src: impl Read + 'a,
+) -> anyhow::Result<Box<dyn Read + 'a>> {
+    Ok(match blob_type {
+        BlobType::Tar => Box::new(src),
+        BlobType::GzippedTar => Box::new(GzDecoder::new(src)),
+        BlobType::ZstdTar => Box::new(
+            zstd::Decoder::new(src)
+                .context("failed to create a zstd decoder")?,
+        ),
+        BlobType::XzTar => Box::new(XzDecoder::new_multi_decoder(src)),
+        BlobType::Bzip2Tar => Box::new(MultiBzDecoder::new(src)),
+        BlobType::Empty | BlobType::Json | BlobType::Unknown => {
+            anyhow::bail!("only Tar blobs contain files")
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::path::Path;
+
+    use super::*;
+    use crate::parser::Parser;
+    use crate::parser::test_util::{TarEntry, gzip, make_tar};
+
+    fn make_layer() -> Vec<u8> {
+        make_tar(&[
+            TarEntry::File("etc/hostname", 0o644, b"xray\n"),
+            TarEntry::File("bin/tool", 0o644, &[0, 1, 2, 3, 0xff]),
+        ])
+    }
+
+    fn read_files(blob_type: BlobType, blob: &[u8]) -> Vec<Vec<u8>> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(blob).unwrap();
+
+        let (changeset, ..) =
+            Parser::new().parse_layer_blob(blob_type, blob).unwrap();
+        let layer_blob = LayerBlob::in_file(file.path().into(), blob_type);
+        ["etc/hostname", "bin/tool"]
+            .into_iter()
+            .map(|path| {
+                let node = changeset.get(Path::new(path)).unwrap();
+                layer_blob
+                    .read_file(
+                        node.inner.content_offset().unwrap(),
+                        node.inner.size(),
+                        u64::MAX,
+                    )
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn read_file_from_tar_blob() {
+        let files = read_files(BlobType::Tar, &make_layer());
+        assert_eq!(files, [b"xray\n".to_vec(), vec![0, 1, 2, 3, 0xff]]);
+    }
+
+    #[test]
+    fn read_file_from_gzipped_tar_blob() {
+        let files = read_files(BlobType::GzippedTar, &gzip(&make_layer()));
+        assert_eq!(files, [b"xray\n".to_vec(), vec![0, 1, 2, 3, 0xff]]);
+    }
+
+    #[test]
+    fn read_several_files_from_gzipped_tar_blob() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&gzip(&make_layer())).unwrap();
+        let layer_blob =
+            LayerBlob::in_file(file.path().into(), BlobType::GzippedTar);
+
+        let mut reader = layer_blob.reader().unwrap();
+        let mut contents = Vec::new();
+        // Only read a part of the first file to check that the rest of it is skipped
+        reader
+            .file(512, 5)
+            .unwrap()
+            .take(2)
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(contents, b"xr");
+
+        contents.clear();
+        reader
+            .file(1536, 5)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(contents, [0, 1, 2, 3, 0xff]);
+
+        // Going back is not possible without decoding the blob again
+        assert!(reader.file(512, 5).is_err());
+    }
+
+    #[test]
+    fn read_file_respects_limit() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&make_layer()).unwrap();
+        let layer_blob = LayerBlob::in_file(file.path().into(), BlobType::Tar);
+
+        // The first file's contents start right after its header
+        assert_eq!(layer_blob.read_file(512, 5, 2).unwrap(), b"xr");
+        assert!(layer_blob.read_file(512, 1 << 20, u64::MAX).is_err());
+    }
+}
@@ -0,0 +1,419 @@
+//! Contains the logic related to finding the space that is wasted by the image's layers.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::{Image, InnerNode, LayerChangeSet, Node};
+
+/// Results of analyzing how efficiently an [Image] uses the space taken by its layers.
+#[derive(Debug, Clone)]
+pub struct ImageEfficiency {
+    /// A score from `0` to `1`, where `1` means that no space is wasted.
+    ///
+    /// It's the share of bytes written by all layers that are still present in the final image.
+    pub score: f64,
+    /// The total size of all files that were written by the layers, including the ones that are hidden by later layers.
+    pub total_bytes: u64,
+    /// The total size of files that are overwritten or deleted by later layers.
+    ///
+    /// Such files take space in the image, but are not present in the final filesystem.
+    pub wasted_bytes: u64,
+    /// All paths that waste space, sorted by the number of wasted bytes in descending order.
+    pub inefficiencies: Vec<Inefficiency>,
+}
+
+/// A single path that wastes space in an [Image].
+#[derive(Debug, Clone)]
+pub struct Inefficiency {
+    /// Absolute path to the file or directory.
+    pub path: PathBuf,
+    pub kind: InefficiencyKind,
+    /// The total size of all versions of this path that are hidden by later layers.
+    pub wasted_bytes: u64,
+    /// Indexes of all layers that added, modified or deleted this path, in ascending order.
+    pub layers: Vec<usize>,
+}
+
+/// Explains why an [Inefficiency] wastes space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InefficiencyKind {
+    /// The path was deleted by a later layer.
+    Deleted,
+    /// The path was overwritten by later layers with files of the same size.
+    ///
+    /// This usually means that the same file was copied again or that only its metadata was changed (e.g. using
+    /// `chmod` or `chown`).
+    Duplicated,
+    /// The path was overwritten by later layers with a different file or directory.
+    Overwritten,
+}
+
+impl InefficiencyKind {
+    /// Returns a short human-readable name of this kind.
+    pub fn name(&self) -> &'static str {
+        match self {
+            InefficiencyKind::Deleted => "deleted",
+            InefficiencyKind::Duplicated => "duplicated",
+            InefficiencyKind::Overwritten => "overwritten",
+        }
+    }
+}
+
+impl Image {
+    /// Finds files that are overwritten or deleted by later layers and calculates how much space they waste.
+    ///
+    /// # Note
+    ///
+    /// This requires merging all layers together, so it's about as expensive as selecting the last layer in the UI.
+    pub fn efficiency(&self) -> ImageEfficiency {
+        analyze_efficiency(
+            self.layers
+                .values()
+                .filter_map(|layer| layer.changeset.as_ref()),
+        )
+    }
+}
+
+/// Analyzes the provided layer changesets, which must be sorted from the lowest layer to the highest one.
+fn analyze_efficiency<'a>(
+    changesets: impl IntoIterator<Item = &'a LayerChangeSet>,
+) -> ImageEfficiency {
+    let mut analyzer = EfficiencyAnalyzer::default();
+
+    let mut aggregated_changeset: Option<LayerChangeSet> = None;
+    for changeset in changesets {
+        let next_aggregated_changeset = match aggregated_changeset.as_ref() {
+            Some(lower) => lower.clone().merge(changeset.clone()),
+            None => changeset.clone(),
+        };
+        // The merged tree already contains all changes made by the layer, including the ones that come from the
+        // opaque directories, so there is no need to handle the layer's whiteouts separately
+        analyzer.visit_children(
+            &mut PathBuf::from("/"),
+            aggregated_changeset.as_ref(),
+            &next_aggregated_changeset,
+            changeset.updated_in,
+        );
+        aggregated_changeset = Some(next_aggregated_changeset);
+    }
+
+    analyzer.finish()
+}
+
+/// What happened to a path that already existed in the lower layers.
+#[derive(Debug, Clone, Copy)]
+enum PathChange {
+    Deleted,
+    /// Contains the size of the new file.
+    ReplacedWithFile(u64),
+    ReplacedWithDir,
+}
+
+/// Everything that is known about a path that has hidden versions.
+#[derive(Debug, Default)]
+struct PathRecord {
+    wasted_bytes: u64,
+    layers: Vec<usize>,
+    /// Size of the latest version of the path, including the deleted one.
+    last_size: u64,
+    is_deleted: bool,
+    sizes_differ: bool,
+}
+
+#[derive(Debug, Default)]
+struct EfficiencyAnalyzer {
+    paths: HashMap<PathBuf, PathRecord>,
+    total_bytes: u64,
+}
+
+impl EfficiencyAnalyzer {
+    /// Visits the children of `upper` that were changed in the layer with the provided index.
+    ///
+    /// `lower` must be the same directory before applying the layer (if it existed).
+    fn visit_children(
+        &mut self,
+        path: &mut PathBuf,
+        lower: Option<&Node>,
+        upper: &Node,
+        layer_idx: u8,
+    ) {
+        let Some(children) = upper.inner.children() else {
+            return;
+        };
+        let lower_children = lower.and_then(|node| node.inner.children());
+
+        for (name, node) in children
+            .iter()
+            // All parents of the changed nodes are updated as well, so it's enough to check only the updated ones
+            .filter(|(_, node)| node.updated_in == layer_idx)
+        {
+            path.push(name);
+            let lower =
+                lower_children.and_then(|children| children.get(&**name));
+            self.visit(path, lower, node, layer_idx);
+            path.pop();
+        }
+    }
+
+    /// Compares a node that was changed in the layer with the provided index with its previous version (if any).
+    fn visit(
+        &mut self,
+        path: &mut PathBuf,
+        lower: Option<&Node>,
+        upper: &Node,
+        layer_idx: u8,
+    ) {
+        let lower_was_deleted =
+            lower.is_some_and(|node| node.inner.is_deleted());
+        let visible_lower = lower.filter(|node| !node.inner.is_deleted());
+
+        if upper.inner.is_deleted() {
+            if let Some(lower) = visible_lower {
+                self.record(path, lower, layer_idx, PathChange::Deleted);
+            }
+            return;
+        }
+
+        match (&upper.inner, visible_lower) {
+            (InnerNode::Directory(_), Some(lower)) if !lower.inner.is_dir() => {
+                self.record(
+                    path,
+                    lower,
+                    layer_idx,
+                    PathChange::ReplacedWithDir,
+                );
+                self.visit_children(path, None, upper, layer_idx);
+            }
+            (InnerNode::Directory(_), _) => {
+                // Children of a deleted directory are deleted as well, which allows detecting the re-added ones
+                self.visit_children(path, lower, upper, layer_idx);
+            }
+            (InnerNode::File(_), lower) => {
+                let size = upper.inner.size();
+                self.total_bytes += size;
+
+                if let Some(lower) = lower {
+                    self.record(
+                        path,
+                        lower,
+                        layer_idx,
+                        PathChange::ReplacedWithFile(size),
+                    );
+                } else if lower_was_deleted
+                    && let Some(record) = self.paths.get_mut(path.as_path())
+                {
+                    // The path was added back after being deleted
+                    record.layers.push(layer_idx.into());
+                    record.sizes_differ |= record.last_size != size;
+                    record.last_size = size;
+                    record.is_deleted = false;
+                }
+            }
+        }
+    }
+
+    /// Records that the provided node was hidden by the layer with the provided index.
+    fn record(
+        &mut self,
+        path: &Path,
+        hidden: &Node,
+        layer_idx: u8,
+        change: PathChange,
+    ) {
+        let (hidden_size, hidden_layers) = hidden_files(hidden);
+        if hidden_size == 0 && !self.paths.contains_key(path) {
+            // Nothing is wasted (e.g. an empty file or a link was overwritten)
+            return;
+        }
+
+        let record = self.paths.entry(path.to_owned()).or_default();
+        record.wasted_bytes += hidden_size;
+        record.layers.extend(hidden_layers);
+        record.layers.push(layer_idx.into());
+        record.layers.sort_unstable();
+        record.layers.dedup();
+
+        match change {
+            PathChange::Deleted => {
+                record.last_size = hidden_size;
+                record.is_deleted = true;
+            }
+            PathChange::ReplacedWithFile(size) => {
+                record.sizes_differ |= hidden_size != size;
+                record.last_size = size;
+                record.is_deleted = false;
+            }
+            PathChange::ReplacedWithDir => {
+                record.sizes_differ = true;
+                record.last_size = 0;
+                record.is_deleted = false;
+            }
+        }
+    }
+
+    fn finish(self) -> ImageEfficiency {
+        let mut inefficiencies = self
+            .paths
+            .into_iter()
+            .map(|(path, record)| {
+                let kind = if record.is_deleted {
+                    InefficiencyKind::Deleted
+                } else if record.sizes_differ {
+                    InefficiencyKind::Overwritten
+                } else {
+                    InefficiencyKind::Duplicated
+                };
+                Inefficiency {
+                    path,
+                    kind,
+                    wasted_bytes: record.wasted_bytes,
+                    layers: record.layers,
+                }
+            })
+            .collect::<Vec<_>>();
+        inefficiencies.sort_unstable_by(|a, b| {
+            b.wasted_bytes
+                .cmp(&a.wasted_bytes)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+
+        let wasted_bytes = inefficiencies
+            .iter()
+            .map(|inefficiency| inefficiency.wasted_bytes)
+            .sum();
+        let score = if self.total_bytes == 0 {
+            1.0
+        } else {
+            self.total_bytes.saturating_sub(wasted_bytes) as f64
+                / self.total_bytes as f64
+        };
+
+        ImageEfficiency {
+            score,
+            total_bytes: self.total_bytes,
+            wasted_bytes,
+            inefficiencies,
+        }
+    }
+}
+
+/// Returns the total size of all files that are present in the provided tree and the layers they came from.
+fn hidden_files(node: &Node) -> (u64, Vec<usize>) {
+    if !node.inner.is_dir() {
+        return (node.inner.size(), vec![node.updated_in.into()]);
+    }
+
+    let mut size = 0;
+    let mut layers = Vec::new();
+    for (_, node, _, _) in node.iter() {
+        if node.inner.is_dir() || node.inner.is_deleted() {
+            continue;
+        }
+        size += node.inner.size();
+        layers.push(node.updated_in.into());
+    }
+    layers.sort_unstable();
+    layers.dedup();
+
+    (size, layers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_util::{Entry, make_layer};
+
+    fn summary(
+        efficiency: &ImageEfficiency,
+    ) -> Vec<(&str, InefficiencyKind, u64, &[usize])> {
+        efficiency
+            .inefficiencies
+            .iter()
+            .map(|inefficiency| {
+                (
+                    inefficiency.path.to_str().unwrap(),
+                    inefficiency.kind,
+                    inefficiency.wasted_bytes,
+                    inefficiency.layers.as_slice(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn overwritten_duplicated_and_deleted_files() {
+        let layers = [
+            make_layer(
+                0,
+                &[
+                    Entry::File("a", 100),
+                    Entry::File("b", 50),
+                    Entry::File("dir/x", 30),
+                    Entry::File("dir/y", 20),
+                    Entry::File("keep", 10),
+                ],
+            ),
+            make_layer(
+                1,
+                &[
+                    Entry::File("a", 200),
+                    Entry::File("b", 50),
+                    Entry::Whiteout("dir"),
+                ],
+            ),
+            make_layer(2, &[Entry::Whiteout("a"), Entry::Whiteout("missing")]),
+        ];
+
+        let efficiency = analyze_efficiency(&layers);
+        assert_eq!(
+            summary(&efficiency),
+            vec![
+                ("/a", InefficiencyKind::Deleted, 300, [0, 1, 2].as_slice()),
+                ("/b", InefficiencyKind::Duplicated, 50, &[0, 1]),
+                ("/dir", InefficiencyKind::Deleted, 50, &[0, 1]),
+            ]
+        );
+        assert_eq!(efficiency.total_bytes, 460);
+        assert_eq!(efficiency.wasted_bytes, 400);
+        assert_eq!(efficiency.score, 60.0 / 460.0);
+    }
+
+    #[test]
+    fn opaque_directories_and_readded_files() {
+        let layers = [
+            make_layer(
+                0,
+                &[Entry::File("etc/conf", 10), Entry::File("etc/other", 5)],
+            ),
+            make_layer(
+                1,
+                &[Entry::OpaqueDir("etc"), Entry::File("etc/conf", 10)],
+            ),
+            make_layer(2, &[Entry::File("etc/other", 7)]),
+        ];
+
+        let efficiency = analyze_efficiency(&layers);
+        assert_eq!(
+            summary(&efficiency),
+            vec![
+                (
+                    "/etc/conf",
+                    InefficiencyKind::Duplicated,
+                    10,
+                    [0, 1].as_slice()
+                ),
+                ("/etc/other", InefficiencyKind::Overwritten, 5, &[0, 1, 2]),
+            ]
+        );
+        assert_eq!(efficiency.total_bytes, 32);
+        assert_eq!(efficiency.wasted_bytes, 15);
+    }
+
+    #[test]
+    fn efficiency_without_files() {
+        let efficiency = analyze_efficiency(&[make_layer(0, &[])]);
+        assert!(efficiency.inefficiencies.is_empty());
+        assert_eq!(efficiency.wasted_bytes, 0);
+        assert_eq!(efficiency.score, 1.0);
+    }
+}
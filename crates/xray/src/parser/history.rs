@@ -0,0 +1,187 @@
+//! Contains the logic related to matching the image's history with its layers.
+
+use super::json::{HistoryEntry, ImageHistory};
+
+/// Prefix that the legacy Docker builder adds to the instructions that are not `RUN`.
+const LEGACY_BUILDER_NOP_PREFIX: &str = "/bin/sh -c #(nop)";
+
+/// Dockerfile instructions that only update the image config and never produce a layer.
+const METADATA_ONLY_INSTRUCTIONS: &[&str] = &[
+    "ARG",
+    "CMD",
+    "ENTRYPOINT",
+    "ENV",
+    "EXPOSE",
+    "HEALTHCHECK",
+    "LABEL",
+    "MAINTAINER",
+    "ONBUILD",
+    "SHELL",
+    "STOPSIGNAL",
+    "USER",
+    "VOLUME",
+];
+
+/// History entries that were matched with the image's layers.
+pub(super) struct MatchedHistory {
+    /// A history entry for each layer, in the same order as the layers.
+    ///
+    /// All entries are missing if the history doesn't match the layers.
+    pub entries: Vec<Option<HistoryEntry>>,
+    /// Is `true` if the history doesn't match the layers, which means that it's unknown which entry belongs to which
+    /// layer.
+    pub is_mismatched: bool,
+}
+
+/// Matches the non-empty entries of the image's history with the image's layers by their indexes.
+///
+/// If the history has more non-empty entries than there are layers (e.g. when some tool didn't set the `empty_layer`
+/// flag), the entries that were created by metadata-only instructions are skipped as well, given that it makes the
+/// numbers match.
+pub(super) fn match_history_to_layers(
+    history: ImageHistory,
+    layers: usize,
+) -> MatchedHistory {
+    let non_empty_entries =
+        history.iter().filter(|entry| !entry.empty_layer).count();
+
+    let (entries, is_mismatched) = if non_empty_entries == layers {
+        (
+            history
+                .into_iter()
+                .filter(|entry| !entry.empty_layer)
+                .map(Some)
+                .collect::<Vec<_>>(),
+            false,
+        )
+    } else if non_empty_entries > layers
+        && history.iter().filter(|entry| is_layer_entry(entry)).count()
+            == layers
+    {
+        tracing::debug!(
+            "Some history entries are missing the empty layer flag, skipping the metadata-only ones"
+        );
+        (
+            history
+                .into_iter()
+                .filter(is_layer_entry)
+                .map(Some)
+                .collect(),
+            false,
+        )
+    } else {
+        tracing::warn!(
+            "The image's history has {} non-empty entries, but the image has {} layers, layer commands are unknown",
+            non_empty_entries,
+            layers
+        );
+        // Entries can't be attributed by their positions, as each entry after the first gap would belong to another layer
+        (std::iter::repeat_with(|| None).take(layers).collect(), true)
+    };
+
+    MatchedHistory {
+        entries,
+        is_mismatched,
+    }
+}
+
+/// Checks if the history entry is expected to have produced a layer.
+fn is_layer_entry(entry: &HistoryEntry) -> bool {
+    !entry.empty_layer && !is_metadata_only_command(&entry.created_by)
+}
+
+/// Checks if the command was produced by a Dockerfile instruction that doesn't create a layer.
+fn is_metadata_only_command(created_by: &str) -> bool {
+    let command = created_by
+        .trim_start()
+        .strip_prefix(LEGACY_BUILDER_NOP_PREFIX)
+        .unwrap_or(created_by)
+        .trim_start();
+    let instruction = command.split_whitespace().next().unwrap_or_default();
+
+    METADATA_ONLY_INSTRUCTIONS.contains(&instruction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_entry(created_by: &str, empty_layer: bool) -> HistoryEntry {
+        HistoryEntry {
+            created_by: created_by.to_owned(),
+            comment: None,
+            empty_layer,
+        }
+    }
+
+    fn commands(matched: &MatchedHistory) -> Vec<Option<&str>> {
+        matched
+            .entries
+            .iter()
+            .map(|entry| entry.as_ref().map(|entry| entry.created_by.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn metadata_only_commands() {
+        assert!(is_metadata_only_command("ENV PATH=/usr/bin"));
+        assert!(is_metadata_only_command("/bin/sh -c #(nop)  CMD [\"sh\"]"));
+        assert!(!is_metadata_only_command("/bin/sh -c #(nop) ADD file:1 /"));
+        assert!(!is_metadata_only_command("RUN /bin/sh -c echo ENV"));
+        assert!(!is_metadata_only_command("WORKDIR /app"));
+    }
+
+    #[test]
+    fn matching_history() {
+        let history = vec![
+            make_entry("ADD rootfs /", false),
+            make_entry("ENV A=1", true),
+            make_entry("RUN make", false),
+        ];
+
+        let matched = match_history_to_layers(history, 2);
+        assert!(!matched.is_mismatched);
+        assert_eq!(
+            commands(&matched),
+            vec![Some("ADD rootfs /"), Some("RUN make")]
+        );
+    }
+
+    #[test]
+    fn history_with_missing_empty_layer_flags() {
+        let history = vec![
+            make_entry("ADD rootfs /", false),
+            make_entry("ENV A=1", false),
+            make_entry("RUN make", false),
+            make_entry("CMD [\"app\"]", false),
+        ];
+
+        let matched = match_history_to_layers(history, 2);
+        assert!(!matched.is_mismatched);
+        assert_eq!(
+            commands(&matched),
+            vec![Some("ADD rootfs /"), Some("RUN make")]
+        );
+    }
+
+    #[test]
+    fn mismatching_history() {
+        // E.g. a squashed image
+        let history = vec![
+            make_entry("ADD rootfs /", false),
+            make_entry("RUN make", false),
+        ];
+        let matched = match_history_to_layers(history, 1);
+        assert!(matched.is_mismatched);
+        assert_eq!(commands(&matched), vec![None]);
+
+        // E.g. a rewritten history, where the positions of the entries can't be trusted
+        let history = vec![
+            make_entry("ADD rootfs /", false),
+            make_entry("RUN make", false),
+        ];
+        let matched = match_history_to_layers(history, 3);
+        assert!(matched.is_mismatched);
+        assert_eq!(commands(&matched), vec![None, None, None]);
+    }
+}
@@ -1,12 +1,13 @@
 //! Contains all the stuff related to parsing JSON blobs.
 
+use std::collections::BTreeMap;
 use std::io::Read;
 
 use anyhow::Context;
-use serde::de::Visitor;
+use serde::de::{IgnoredAny, Unexpected, Visitor};
 use serde::{Deserialize, Deserializer};
 
-use super::Sha256Digest;
+use super::{Platform, Sha256Digest};
 use crate::parser::constants::{SHA256_DIGEST_LENGTH, SHA256_DIGEST_PREFIX};
 use crate::parser::util::sha256_digest_from_hex;
 
@@ -15,23 +16,29 @@ pub(super) type ImageHistory = Vec<HistoryEntry>;
 
 /// Docker-specific manifest that can be found in the root of an image.
 ///
-/// In in our case, it's used to extract the image name and tag, but it's not guaranteed to be present in an image,
-/// especially when using non-Docker container runtimes.
+/// It's used to extract the image name and tag, as well as the list of layers for images that were exported using the legacy
+/// `docker save` format, where layers are stored as `<id>/layer.tar` instead of OCI blobs.
+/// It's not guaranteed to be present in an image, especially when using non-Docker container runtimes.
 #[derive(Deserialize)]
 pub(super) struct DockerManifest {
     #[serde(rename = "RepoTags")]
-    repo_tags: Option<Vec<String>>,
+    pub repo_tags: Option<Vec<String>>,
+    /// Path to the image config within the image.
+    #[serde(rename = "Config")]
+    pub config: Option<String>,
+    /// Paths to the image layers within the image, ordered from the bottom layer to the top one.
+    #[serde(rename = "Layers", default)]
+    pub layers: Vec<String>,
 }
 
 impl DockerManifest {
-    pub fn from_reader(src: impl Read) -> anyhow::Result<Option<String>> {
-        let mut manifest =
+    /// Parses the Docker's manifest and returns the first manifest in it.
+    pub fn from_reader(src: impl Read) -> anyhow::Result<Option<Self>> {
+        let manifest =
             // Docker manifest contains an array of manifest objects, so we need a vec here
             serde_json::from_reader::<_, Vec<DockerManifest>>(src).context("failed to parse the Docker's manifest")?;
 
-        Ok(manifest.get_mut(0).and_then(|manifest| {
-            manifest.repo_tags.as_mut().and_then(|tags| tags.pop())
-        }))
+        Ok(manifest.into_iter().next())
     }
 }
 
@@ -40,21 +47,98 @@ impl DockerManifest {
 #[serde(untagged)]
 pub(super) enum JsonBlob {
     /// Image Index.
-    ///
-    /// Source: [OCI Image Index Specification](https://github.com/opencontainers/image-spec/blob/main/image-index.md)
-    Index { manifests: Vec<ImageIndexManifest> },
+    Index(ImageIndex),
     /// Image Manifest.
-    ///
-    /// Source: [OCI Image Manifest Specification](https://github.com/opencontainers/image-spec/blob/main/manifest.md).
-    Manifest { layers: ImageLayerConfigs },
+    Manifest(ImageManifest),
     /// Image Config.
-    ///
-    /// Sourcce: [OCI Image Configuration](https://github.com/opencontainers/image-spec/blob/main/config.md)
-    Config {
-        architecture: String,
-        os: String,
-        history: ImageHistory,
-    },
+    Config(ImageConfig),
+}
+
+/// Represents a subset of fields of an Image Index.
+///
+/// Source: [OCI Image Index Specification](https://github.com/opencontainers/image-spec/blob/main/image-index.md)
+#[derive(Debug, Deserialize)]
+pub(super) struct ImageIndex {
+    pub manifests: Vec<ImageIndexManifest>,
+}
+
+/// Represents a subset of fields of an Image Manifest.
+///
+/// Source: [OCI Image Manifest Specification](https://github.com/opencontainers/image-spec/blob/main/manifest.md).
+#[derive(Debug, Deserialize)]
+pub(super) struct ImageManifest {
+    pub config: ManifestConfig,
+    pub layers: ImageLayerConfigs,
+}
+
+/// Represents a subset of fields of an Image Config.
+///
+/// Sourcce: [OCI Image Configuration](https://github.com/opencontainers/image-spec/blob/main/config.md)
+#[derive(Debug, Deserialize)]
+pub(super) struct ImageConfig {
+    pub architecture: String,
+    pub os: String,
+    pub variant: Option<String>,
+    #[serde(rename = "os.version")]
+    pub os_version: Option<String>,
+    /// Creation date in the RFC 3339 format.
+    pub created: Option<String>,
+    pub author: Option<String>,
+    pub config: Option<Box<RuntimeConfig>>,
+    #[serde(default)]
+    pub rootfs: RootFs,
+    pub history: ImageHistory,
+}
+
+impl ImageConfig {
+    /// Returns the [Platform] this config was built for.
+    pub fn platform(&self) -> Platform {
+        Platform::new(&self.os, &self.architecture, self.variant.as_deref())
+    }
+}
+
+/// Execution parameters that should be used when running a container using the image.
+///
+/// Source: [OCI Image Configuration](https://github.com/opencontainers/image-spec/blob/main/config.md#properties)
+#[derive(Debug, Default, Deserialize)]
+pub struct RuntimeConfig {
+    #[serde(rename = "User")]
+    pub user: Option<String>,
+    #[serde(
+        rename = "ExposedPorts",
+        default,
+        deserialize_with = "deserialize_object_keys"
+    )]
+    pub exposed_ports: Option<Vec<String>>,
+    /// Environment variables in the `VARNAME=VARVALUE` format.
+    #[serde(rename = "Env")]
+    pub env: Option<Vec<String>>,
+    #[serde(rename = "Entrypoint")]
+    pub entrypoint: Option<Vec<String>>,
+    #[serde(rename = "Cmd")]
+    pub cmd: Option<Vec<String>>,
+    #[serde(
+        rename = "Volumes",
+        default,
+        deserialize_with = "deserialize_object_keys"
+    )]
+    pub volumes: Option<Vec<String>>,
+    #[serde(rename = "WorkingDir")]
+    pub working_dir: Option<String>,
+    #[serde(rename = "Labels")]
+    pub labels: Option<BTreeMap<String, String>>,
+    #[serde(rename = "StopSignal")]
+    pub stop_signal: Option<String>,
+}
+
+/// Represents a subset of fields of the `rootfs` object that can be found in an OCI Image Config.
+///
+/// Source: [OCI Image Configuration](https://github.com/opencontainers/image-spec/blob/main/config.md#properties)
+#[derive(Debug, Default, Deserialize)]
+pub(super) struct RootFs {
+    /// Digests of the uncompressed layers, ordered from the bottom layer to the top one.
+    #[serde(default, deserialize_with = "deserialize_sha256_digests")]
+    pub diff_ids: Vec<Sha256Digest>,
 }
 
 /// Represents a subset of fields of a single entry in the `history` array that can be found in an OCI Image Config.
@@ -75,6 +159,18 @@ pub(super) struct HistoryEntry {
 pub(super) struct LayerConfig {
     #[serde(deserialize_with = "deserialize_sha256_digest")]
     pub digest: Sha256Digest,
+    /// Used to determine the layer's compression without sniffing its contents.
+    #[serde(rename = "mediaType")]
+    pub media_type: Option<String>,
+}
+
+/// Represents a subset of fields of the `config` descriptor that can be found in an OCI Image Manifest.
+///
+/// Source: [OCI Image Manifest Specification](https://github.com/opencontainers/image-spec/blob/main/manifest.md#image-manifest-property-descriptions)
+#[derive(Debug, Deserialize)]
+pub(super) struct ManifestConfig {
+    #[serde(deserialize_with = "deserialize_sha256_digest")]
+    pub digest: Sha256Digest,
 }
 
 /// Deserializes a hex string with SHA256 digest that is prepended with the `sha256:` prefix.
@@ -99,8 +195,10 @@ where
         where
             E: serde::de::Error,
         {
-            // Strip the `sha256:` prefix
-            let raw = &v[SHA256_DIGEST_PREFIX.len()..];
+            let raw = v
+                .as_bytes()
+                .strip_prefix(SHA256_DIGEST_PREFIX)
+                .ok_or_else(|| E::invalid_value(Unexpected::Str(v), &self))?;
 
             // multiply by 2 because we are dealing with a hex str
             if raw.len() != SHA256_DIGEST_LENGTH * 2 {
@@ -118,9 +216,43 @@ where
     de.deserialize_str(Sha256HashVisitor)
 }
 
+/// Deserializes an array of hex strings with SHA256 digests that are prepended with the `sha256:` prefix.
+fn deserialize_sha256_digests<'de, D>(
+    de: D,
+) -> Result<Vec<Sha256Digest>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct PrefixedSha256Digest(
+        #[serde(deserialize_with = "deserialize_sha256_digest")] Sha256Digest,
+    );
+
+    let digests = Vec::<PrefixedSha256Digest>::deserialize(de)?;
+    Ok(digests
+        .into_iter()
+        .map(|PrefixedSha256Digest(digest)| digest)
+        .collect())
+}
+
+/// Deserializes keys of a JSON object that is used as a set (e.g. `{"80/tcp": {}}`), ignoring the values.
+fn deserialize_object_keys<'de, D>(
+    de: D,
+) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let object = Option::<BTreeMap<String, IgnoredAny>>::deserialize(de)?;
+    Ok(object.map(|object| object.into_keys().collect()))
+}
+
 /// Represents a subset of fields of a single manifest that can be found in the Image Index.
 #[derive(Debug, Deserialize)]
 pub(super) struct ImageIndexManifest {
+    #[serde(deserialize_with = "deserialize_sha256_digest")]
+    pub digest: Sha256Digest,
+    /// Is missing for manifests that are not tied to a specific platform.
+    pub platform: Option<Platform>,
     /// Source: [Annotations](https://github.com/opencontainers/image-spec/blob/main/annotations.md).
     pub annotations: Option<ImageIndexManifestAnnotations>,
 }
@@ -133,4 +265,112 @@ pub(super) struct ImageIndexManifestAnnotations {
     /// Fully qualified image name.
     #[serde(rename = "io.containerd.image.name")]
     pub fully_qualified_image_name: Option<String>,
+    /// Image reference within an OCI Image Layout, which is usually a tag.
+    #[serde(rename = "org.opencontainers.image.ref.name")]
+    pub image_ref_name: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_config_with_runtime_config() {
+        let raw = r#"{
+            "architecture": "amd64",
+            "os": "linux",
+            "os.version": "10.0.17763",
+            "created": "2024-01-01T00:00:00Z",
+            "config": {
+                "User": "nginx",
+                "ExposedPorts": {"80/tcp": {}, "443/tcp": {}},
+                "Env": ["PATH=/usr/bin"],
+                "Cmd": ["nginx", "-g", "daemon off;"],
+                "Volumes": null,
+                "Labels": {"maintainer": "me"}
+            },
+            "history": []
+        }"#;
+
+        let JsonBlob::Config(config) = serde_json::from_str(raw).unwrap()
+        else {
+            panic!("expected an image config");
+        };
+        assert_eq!(config.os_version.as_deref(), Some("10.0.17763"));
+
+        let runtime_config = config.config.unwrap();
+        assert_eq!(runtime_config.user.as_deref(), Some("nginx"));
+        assert_eq!(
+            runtime_config.exposed_ports.unwrap(),
+            vec!["443/tcp".to_owned(), "80/tcp".to_owned()]
+        );
+        assert!(runtime_config.volumes.is_none());
+        assert!(runtime_config.entrypoint.is_none());
+        assert_eq!(runtime_config.cmd.unwrap().len(), 3);
+        assert_eq!(runtime_config.labels.unwrap()["maintainer"], "me");
+    }
+
+    #[test]
+    fn image_config_without_runtime_config() {
+        let raw = r#"{"architecture": "amd64", "os": "linux", "history": []}"#;
+
+        let JsonBlob::Config(config) = serde_json::from_str(raw).unwrap()
+        else {
+            panic!("expected an image config");
+        };
+        assert!(config.config.is_none());
+        assert!(config.created.is_none());
+        assert!(config.rootfs.diff_ids.is_empty());
+    }
+
+    #[test]
+    fn image_config_with_diff_ids() {
+        let raw = format!(
+            r#"{{
+                "architecture": "amd64",
+                "os": "linux",
+                "rootfs": {{
+                    "type": "layers",
+                    "diff_ids": ["sha256:{}", "sha256:{}"]
+                }},
+                "history": []
+            }}"#,
+            "ab".repeat(32),
+            "cd".repeat(32)
+        );
+
+        let JsonBlob::Config(config) = serde_json::from_str(&raw).unwrap()
+        else {
+            panic!("expected an image config");
+        };
+        assert_eq!(config.rootfs.diff_ids, vec![[0xab; 32], [0xcd; 32]]);
+    }
+
+    #[test]
+    fn digest_without_sha256_prefix() {
+        for digest in [
+            String::new(),
+            "sha256".into(),
+            "sha25é".into(),
+            format!("md5:{}", "ab".repeat(16)),
+            format!("md5:{}", "ab".repeat(32)),
+        ] {
+            let raw = format!(r#"{{"digest": "{digest}"}}"#);
+            let error = serde_json::from_str::<ManifestConfig>(&raw)
+                .expect_err(&format!("'{digest}' is not a sha256 digest"));
+            assert!(error.to_string().contains("invalid value"), "{error}");
+        }
+    }
+
+    #[test]
+    fn image_config_with_invalid_diff_ids() {
+        let raw = r#"{
+            "architecture": "amd64",
+            "os": "linux",
+            "rootfs": {"type": "layers", "diff_ids": [""]},
+            "history": []
+        }"#;
+
+        assert!(serde_json::from_str::<ImageConfig>(raw).is_err());
+    }
 }
@@ -0,0 +1,447 @@
+//! Contains the logic related to parsing images stored as [OCI Image Layout](https://github.com/opencontainers/image-spec/blob/main/image-layout.md) directories.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use super::constants::{
+    DEFAULT_IMAGE_TAG, IMAGE_LAYOUT_BLOBS_PATH, IMAGE_LAYOUT_INDEX_PATH,
+    TAR_BLOCK_SIZE,
+};
+use super::json::{ImageIndex, ImageIndexManifest, JsonBlob};
+use super::parallel::run_in_parallel;
+use super::util::{determine_blob_type, sha256_digest_to_hex};
+use super::{
+    BlobType, Image, LayerBlob, ParsedLayerBlob, Parser, Sha256Digest,
+};
+
+impl Parser {
+    /// Parses an OCI-compliant container image from the provided OCI Image Layout directory.
+    ///
+    /// Uses the `org.opencontainers.image.ref.name` annotations to select the image with the provided tag if the layout
+    /// contains several images. Only the blobs that belong to the selected image are read.
+    pub fn parse_image_layout(
+        mut self,
+        layout: &Path,
+        tag: Option<&str>,
+    ) -> anyhow::Result<Image> {
+        let image_index = File::open(layout.join(IMAGE_LAYOUT_INDEX_PATH))
+            .context("failed to open the image index")?;
+        let mut image_index = serde_json::from_reader::<_, ImageIndex>(
+            BufReader::new(image_index),
+        )
+        .context("failed to parse the image index")?;
+
+        if let Some(image_ref) = retain_tagged_manifests(&mut image_index, tag)?
+        {
+            self.tagged_name =
+                tagged_name_from_ref(layout, &image_ref).or(self.tagged_name);
+        }
+
+        // Load all the indexes, manifests and configs that are reachable from the image index
+        let mut pending = image_index
+            .manifests
+            .iter()
+            .map(|manifest| manifest.digest)
+            .collect::<Vec<_>>();
+        self.process_image_index(image_index);
+        while let Some(digest) = pending.pop() {
+            if self.indexes.contains_key(&digest)
+                || self.manifests.contains_key(&digest)
+                || self.configs.contains_key(&digest)
+            {
+                continue;
+            }
+
+            let Some(mut blob) = open_blob(layout, &digest)? else {
+                // Layouts may contain only some of the platforms from a multi-platform image
+                continue;
+            };
+            let Some(json_blob) =
+                self.parse_json_blob::<JsonBlob>(&mut blob)?
+            else {
+                continue;
+            };
+
+            match &json_blob {
+                JsonBlob::Index(index) => pending.extend(
+                    index.manifests.iter().map(|manifest| manifest.digest),
+                ),
+                JsonBlob::Manifest(manifest) => {
+                    pending.push(manifest.config.digest)
+                }
+                JsonBlob::Config(_) => {}
+            }
+            self.process_json_blob(digest, json_blob);
+        }
+
+        let (layer_configs, config) = self
+            .resolve_image_manifest()
+            .context("failed to find the image manifest")?;
+
+        let mut digests = Vec::with_capacity(layer_configs.len());
+        for layer_config in layer_configs.iter() {
+            // The same layer can be used several times within an image
+            if !digests.contains(&layer_config.digest) {
+                digests.push(layer_config.digest);
+            }
+        }
+
+        let parsed_layers =
+            run_in_parallel(&digests, self.threads(), |digest| {
+                self.read_layout_layer(layout, digest)
+            });
+        // Merge the layers in the order they are used in to keep the results deterministic
+        for (digest, parsed) in digests.into_iter().zip(parsed_layers) {
+            if let Some((parsed, layer_blob)) = parsed
+                .context("failed to parse a layer from the image layout")?
+            {
+                self.insert_layer_blob(digest, parsed);
+                self.layer_blobs.insert(digest, layer_blob);
+            }
+        }
+
+        self.build_image(layer_configs, config)
+    }
+
+    /// Reads a single layer blob from the OCI Image Layout directory.
+    ///
+    /// Returns `None` if the layer is not a Tar blob. Missing layers are only allowed if [Self::verify] is set, as they
+    /// are reported as verification errors then.
+    fn read_layout_layer(
+        &self,
+        layout: &Path,
+        digest: &Sha256Digest,
+    ) -> anyhow::Result<Option<(ParsedLayerBlob, LayerBlob)>> {
+        let Some(mut blob) = open_blob(layout, digest)? else {
+            if self.verify {
+                return Ok(None);
+            }
+            anyhow::bail!(
+                "layer {} is missing in the image layout",
+                sha256_digest_to_hex(digest)
+            )
+        };
+
+        // A buffer used for determining the blob type
+        let mut buf = [0u8; TAR_BLOCK_SIZE];
+        let (blob_type, offset) = determine_blob_type(&mut buf, &mut blob)
+            .context("failed to determine the blob type of a layer")?;
+
+        let blob_type = self.layer_blob_type(digest, blob_type);
+        let layer_blob =
+            LayerBlob::in_file(blob_path(layout, digest), blob_type);
+        if let Some(layer) = self
+            .layer_cache(digest, blob_type)
+            .and_then(|cache| cache.get(digest))
+        {
+            return Ok(Some((
+                ParsedLayerBlob {
+                    layer: Some(layer),
+                    digests: None,
+                },
+                layer_blob,
+            )));
+        }
+
+        let parsed = match blob_type {
+            // Seeking over the files' contents is not possible when the whole blob needs to be hashed
+            BlobType::Tar if !self.verify => {
+                blob.rewind().context("failed to wind back the layer")?;
+                let blob_size = blob
+                    .get_ref()
+                    .metadata()
+                    .context("failed to get the layer's size")?
+                    .len();
+                let (layer_changeset, layer_size) = self
+                    .parse_tar_blob(&mut blob, blob_size)
+                    .context("error while parsing a tar layer")?;
+                ParsedLayerBlob {
+                    layer: Some((layer_changeset, layer_size)),
+                    digests: None,
+                }
+            }
+            BlobType::Json | BlobType::Unknown => {
+                tracing::debug!(
+                    "Layer {} is not a Tar blob",
+                    sha256_digest_to_hex(digest)
+                );
+                return Ok(None);
+            }
+            blob_type => {
+                // Restore the blob (as we've read some bytes from it to determine the blob type)
+                let blob = buf[..offset].chain(blob);
+                self.read_layer_blob(digest, blob_type, blob)?
+            }
+        };
+
+        Ok(Some((parsed, layer_blob)))
+    }
+}
+
+/// Opens a blob with the provided digest from the OCI Image Layout directory.
+///
+/// Returns `None` if the blob is missing.
+fn open_blob(
+    layout: &Path,
+    digest: &Sha256Digest,
+) -> anyhow::Result<Option<BufReader<File>>> {
+    let blob_path = blob_path(layout, digest);
+    match File::open(&blob_path) {
+        Ok(blob) => Ok(Some(BufReader::new(blob))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| {
+            format!("failed to open the '{}' blob", blob_path.display())
+        }),
+    }
+}
+
+/// Returns the path to a blob with the provided digest within the OCI Image Layout directory.
+fn blob_path(layout: &Path, digest: &Sha256Digest) -> PathBuf {
+    [
+        layout,
+        Path::new(IMAGE_LAYOUT_BLOBS_PATH),
+        Path::new(&sha256_digest_to_hex(digest)),
+    ]
+    .iter()
+    .collect()
+}
+
+/// Returns the value of the `org.opencontainers.image.ref.name` annotation of a manifest from the Image Index.
+fn image_ref_name(manifest: &ImageIndexManifest) -> Option<&str> {
+    manifest.annotations.as_ref()?.image_ref_name.as_deref()
+}
+
+/// Checks if the image reference from an OCI Image Layout has the provided tag.
+///
+/// References can be either plain tags or fully qualified image names.
+fn image_ref_has_tag(image_ref: &str, tag: &str) -> bool {
+    image_ref == tag
+        || image_ref
+            .rsplit_once(':')
+            .is_some_and(|(name, ref_tag)| ref_tag == tag && !name.is_empty())
+}
+
+/// Leaves only the manifests with the provided tag in the Image Index.
+///
+/// Uses the `latest` tag if no tag is provided and the layout contains several tagged images.
+///
+/// Returns the reference of the selected image if it has one.
+fn retain_tagged_manifests(
+    image_index: &mut ImageIndex,
+    tag: Option<&str>,
+) -> anyhow::Result<Option<String>> {
+    let mut image_refs = Vec::new();
+    for image_ref in image_index.manifests.iter().filter_map(image_ref_name) {
+        if !image_refs.contains(&image_ref) {
+            image_refs.push(image_ref);
+        }
+    }
+
+    let tag = match tag {
+        Some(tag) => tag,
+        None if image_refs.len() <= 1 => {
+            return Ok(image_refs.first().map(ToString::to_string));
+        }
+        None => DEFAULT_IMAGE_TAG,
+    };
+
+    let Some(image_ref) = image_refs
+        .iter()
+        .find(|image_ref| image_ref_has_tag(image_ref, tag))
+        .map(ToString::to_string)
+    else {
+        if image_refs.is_empty() {
+            anyhow::bail!("the image layout doesn't contain any tagged images")
+        }
+        anyhow::bail!(
+            "the image layout doesn't contain an image with the '{}' tag, available images: {}",
+            tag,
+            image_refs.join(", ")
+        )
+    };
+
+    image_index
+        .manifests
+        .retain(|manifest| image_ref_name(manifest) == Some(&image_ref));
+
+    Ok(Some(image_ref))
+}
+
+/// Turns an image reference from an OCI Image Layout into an image name with a tag.
+///
+/// Plain tags are combined with the name of the layout directory.
+fn tagged_name_from_ref(layout: &Path, image_ref: &str) -> Option<String> {
+    if image_ref.contains(['/', ':']) {
+        // A fully qualified image name, remove the registry if present
+        let image_name = image_ref
+            .rsplit_once('/')
+            .map_or(image_ref, |(_, image_name)| image_name);
+        if image_name.contains(':') {
+            return Some(image_name.to_owned());
+        }
+        return Some(format!("{}:{}", image_name, DEFAULT_IMAGE_TAG));
+    }
+
+    let layout = layout.canonicalize().ok()?;
+    let layout_name = layout.file_name()?.to_string_lossy();
+    Some(format!("{}:{}", layout_name, image_ref))
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::{Digest as _, Sha256};
+
+    use super::*;
+    use crate::parser::VerificationError;
+
+    fn make_index(refs: &[Option<&str>]) -> ImageIndex {
+        let manifests = refs
+            .iter()
+            .enumerate()
+            .map(|(idx, image_ref)| {
+                let annotations = image_ref.map(|image_ref| {
+                    serde_json::json!({
+                        "org.opencontainers.image.ref.name": image_ref
+                    })
+                });
+                serde_json::json!({
+                    "digest": format!("sha256:{:064x}", idx),
+                    "annotations": annotations,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        serde_json::from_value(serde_json::json!({ "manifests": manifests }))
+            .unwrap()
+    }
+
+    // --- retain_tagged_manifests ---
+
+    #[test]
+    fn retain_manifests_with_tag() {
+        let mut index = make_index(&[Some("1.0"), Some("2.0"), Some("1.0")]);
+        let image_ref =
+            retain_tagged_manifests(&mut index, Some("1.0")).unwrap();
+        assert_eq!(image_ref.as_deref(), Some("1.0"));
+        assert_eq!(index.manifests.len(), 2);
+    }
+
+    #[test]
+    fn retain_manifests_with_fully_qualified_ref() {
+        let mut index =
+            make_index(&[Some("docker.io/library/alpine:3.20"), Some("3.21")]);
+        let image_ref =
+            retain_tagged_manifests(&mut index, Some("3.20")).unwrap();
+        assert_eq!(image_ref.as_deref(), Some("docker.io/library/alpine:3.20"));
+        assert_eq!(index.manifests.len(), 1);
+    }
+
+    #[test]
+    fn retain_manifests_without_tag() {
+        // A single image is used as is
+        let mut index = make_index(&[Some("1.0"), None]);
+        let image_ref = retain_tagged_manifests(&mut index, None).unwrap();
+        assert_eq!(image_ref.as_deref(), Some("1.0"));
+        assert_eq!(index.manifests.len(), 2);
+
+        // `latest` is used if there are several images
+        let mut index = make_index(&[Some("1.0"), Some("latest")]);
+        let image_ref = retain_tagged_manifests(&mut index, None).unwrap();
+        assert_eq!(image_ref.as_deref(), Some("latest"));
+        assert_eq!(index.manifests.len(), 1);
+    }
+
+    #[test]
+    fn retain_manifests_missing_tag() {
+        let mut index = make_index(&[Some("1.0"), Some("2.0")]);
+        let error = retain_tagged_manifests(&mut index, None)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("'latest'"));
+        assert!(error.contains("1.0, 2.0"));
+    }
+
+    // --- tagged_name_from_ref ---
+
+    #[test]
+    fn tagged_name_from_fully_qualified_ref() {
+        let layout = Path::new("/nonexistent");
+        assert_eq!(
+            tagged_name_from_ref(layout, "ghcr.io/org/app:v1").as_deref(),
+            Some("app:v1")
+        );
+        assert_eq!(
+            tagged_name_from_ref(layout, "localhost:5000/app").as_deref(),
+            Some("app:latest")
+        );
+    }
+
+    // --- parse_image_layout ---
+
+    /// Creates an image layout with a single-layer image whose layer blob is missing.
+    fn make_layout_without_layer(layout: &Path) {
+        let blobs = layout.join(IMAGE_LAYOUT_BLOBS_PATH);
+        std::fs::create_dir_all(&blobs).unwrap();
+        let add_blob = |json: serde_json::Value| {
+            let blob = serde_json::to_vec(&json).unwrap();
+            let digest = sha256_digest_to_hex(&Sha256::digest(&blob).into());
+            std::fs::write(blobs.join(&digest), blob).unwrap();
+            format!("sha256:{digest}")
+        };
+
+        let config = add_blob(serde_json::json!({
+            "architecture": "amd64",
+            "os": "linux",
+            "rootfs": { "diff_ids": [format!("sha256:{:064x}", 2)] },
+            "history": [{ "created_by": "COPY app /app" }],
+        }));
+        let manifest = add_blob(serde_json::json!({
+            "config": { "digest": config },
+            "layers": [{
+                "mediaType": "application/vnd.oci.image.layer.v1.tar",
+                "digest": format!("sha256:{:064x}", 1),
+            }],
+        }));
+        std::fs::write(
+            layout.join(IMAGE_LAYOUT_INDEX_PATH),
+            serde_json::to_vec(&serde_json::json!({
+                "manifests": [{ "digest": manifest }],
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn parse_layout_with_missing_layer() {
+        let layout = tempfile::tempdir().unwrap();
+        make_layout_without_layer(layout.path());
+
+        let Err(error) = Parser::new().parse_image_layout(layout.path(), None)
+        else {
+            panic!("missing layers must not be ignored");
+        };
+        assert!(
+            format!("{error:#}").contains("is missing in the image layout")
+        );
+    }
+
+    #[test]
+    fn verify_layout_with_missing_layer() {
+        let layout = tempfile::tempdir().unwrap();
+        make_layout_without_layer(layout.path());
+
+        let image = Parser::new()
+            .with_verification(true)
+            .parse_image_layout(layout.path(), None)
+            .unwrap();
+        let layer = image.layers.values().next().unwrap();
+        assert_eq!(
+            layer.verification_errors.as_deref(),
+            Some([VerificationError::MissingBlob].as_slice())
+        );
+    }
+}
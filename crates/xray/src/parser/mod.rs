@@ -1,41 +1,88 @@
 //! Contains all the logic related to parsing and processing of OCI-compliant container images represented as Tar blobs.
 
+mod cache;
+mod compare;
 mod constants;
+mod content;
+mod efficiency;
+mod history;
 mod json;
+mod layout;
 mod node;
+mod parallel;
+mod platform;
+mod remote;
 mod seeker;
 mod util;
+mod verification;
 
 use std::borrow::Cow;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::OsStr;
 use std::io::{Read, Seek};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use anyhow::Context;
+use bzip2::read::MultiBzDecoder;
+pub use cache::LayerCache;
+pub use compare::{ImageComparison, LayerPresence};
 use constants::{
-    BLOB_PATH_PREFIX, IMAGE_INDEX_PATH, IMAGE_MANIFEST_PATH,
-    SHA256_DIGEST_LENGTH, TAR_BLOCK_SIZE, TAR_MAGIC_NUMBER,
-    TAR_MAGIC_NUMBER_START_IDX,
+    IMAGE_INDEX_PATH, IMAGE_MANIFEST_PATH, LEGACY_LAYER_PATH_SUFFIX,
+    OPAQUE_WHITEOUT_FILE_NAME, SHA256_DIGEST_LENGTH, TAR_BLOCK_SIZE,
+    TAR_MAGIC_NUMBER, TAR_MAGIC_NUMBER_START_IDX, WHITEOUT_PREFIX,
 };
+pub use content::LayerBlob;
+use content::{ImageOpener, ReadSeek};
+pub use efficiency::{ImageEfficiency, Inefficiency};
 use flate2::read::GzDecoder;
+use history::match_history_to_layers;
 use indexmap::IndexMap;
-use json::{DockerManifest, ImageHistory, ImageLayerConfigs, JsonBlob};
-pub use node::NodeFilters;
-use node::{InnerNode, Node, RestorablePath};
+pub use json::RuntimeConfig;
+use json::{
+    DockerManifest, ImageConfig, ImageIndex, ImageLayerConfigs, ImageManifest,
+    JsonBlob, LayerConfig,
+};
+#[cfg(test)]
+pub(crate) use node::test_util;
+use node::{InnerNode, RestorablePath};
+pub use node::{Node, NodeFilters, NodeKind, NodeMetadata, NodeName};
+use parallel::DeferredBlob;
+pub use platform::Platform;
+use platform::select_platform;
+pub use remote::RemoteImage;
 use seeker::SeekerWithOffset;
 use serde::de::DeserializeOwned;
 use tar::{Archive, Header};
 use util::{
-    determine_blob_type, get_entry_size_in_blocks, sha256_digest_from_hex,
+    blob_type_from_media_type, determine_blob_type, get_entry_size_in_blocks,
+    is_legacy_config_path, layer_digest_from_path, resolve_layer_blob_type,
+    sha256_digest_to_hex,
 };
+pub use verification::VerificationError;
+use verification::{DigestReader, LayerDigests, verify_layer};
+use xz2::read::XzDecoder;
 
 pub type Sha256Digest = [u8; SHA256_DIGEST_LENGTH];
 pub type LayerChangeSet = Node;
-pub type DirMap = BTreeMap<PathBuf, Node>;
+pub type DirMap = BTreeMap<NodeName, Node>;
 
 type LayerSize = u64;
 
+/// A layer blob that was read by [Parser::read_layer_blob].
+struct ParsedLayerBlob {
+    /// Is `None` for empty blobs, as well as for blobs that failed to parse during verification.
+    layer: Option<(LayerChangeSet, LayerSize)>,
+    /// Is only present if the layers are verified.
+    digests: Option<LayerDigests>,
+}
+
+/// Used instead of the command that created a layer if the image's history doesn't have an entry for it.
+const MISSING_HISTORY_PLACEHOLDER: &str = "<missing>";
+/// Used instead of the command that created a layer if the image's history doesn't match its layers.
+const MISMATCHED_HISTORY_PLACEHOLDER: &str = "<unknown (history mismatch)>";
+
 /// Represents state of a [Node] in a layer.
 #[derive(Debug, Clone, Copy)]
 pub enum NodeStatus {
@@ -44,46 +91,99 @@ pub enum NodeStatus {
     /// A node that was updated in the current layer
     Modified(u64),
     /// A node that was deleted in the current layer
-    Deleted,
+    ///
+    /// Holds the size of the node that was deleted, which is only known after merging the layer with the lower ones.
+    Deleted(u64),
 }
 
 /// Represents state of a file in a layer.
 #[derive(Debug, Clone)]
 pub struct FileState {
     status: NodeStatus,
+    /// Can be anything besides [NodeKind::Directory].
+    kind: NodeKind,
     /// Is `Some` if file is a hardlink/symlink that links to the contained [PathBuf].
     actual_file: Option<PathBuf>,
+    /// Is `None` for whiteouts, as they don't carry any meaningful metadata.
+    metadata: Option<NodeMetadata>,
+    /// Position of the file's contents within the uncompressed layer that last updated it.
+    ///
+    /// Is only meaningful for regular files, check [LayerBlob] for how it's used.
+    content_offset: u64,
 }
 
 impl FileState {
     pub fn new(status: NodeStatus, actual_file: Option<PathBuf>) -> Self {
         FileState {
             status,
+            kind: NodeKind::File,
             actual_file,
+            metadata: None,
+            content_offset: 0,
         }
     }
+
+    pub fn with_content_offset(mut self, content_offset: u64) -> Self {
+        self.content_offset = content_offset;
+        self
+    }
+
+    pub fn with_kind(mut self, kind: NodeKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: NodeMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
 }
 
 /// Represents state of a directory in a layer.
 #[derive(Debug, Clone)]
 pub struct DirectoryState {
     status: NodeStatus,
-    children: DirMap,
+    /// Is shared between file trees (e.g. a layer and the aggregated view of several layers) and is only copied when
+    /// one of them modifies it.
+    children: Arc<DirMap>,
+    /// Is `true` if the directory contains an opaque whiteout (`.wh..wh..opq`) in this layer.
+    ///
+    /// Such directories hide everything that the lower layers put into them.
+    opaque: bool,
+    /// Is `None` for directories that don't have their own entry in a layer (i.e. parents of other entries).
+    metadata: Option<NodeMetadata>,
 }
 
 impl DirectoryState {
     pub fn new_empty() -> Self {
         DirectoryState {
             status: NodeStatus::Added(0),
-            children: DirMap::default(),
+            children: Arc::default(),
+            opaque: false,
+            metadata: None,
         }
     }
     pub fn new_with_size(size: u64) -> Self {
         DirectoryState {
             status: NodeStatus::Added(size),
-            children: DirMap::default(),
+            children: Arc::default(),
+            opaque: false,
+            metadata: None,
+        }
+    }
+    pub fn new_opaque() -> Self {
+        DirectoryState {
+            status: NodeStatus::Added(0),
+            children: Arc::default(),
+            opaque: true,
+            metadata: None,
         }
     }
+
+    pub fn with_metadata(mut self, metadata: NodeMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
 }
 
 /// A parsed OCI-compliant container image.
@@ -99,12 +199,24 @@ pub struct Image {
     pub architecture: String,
     /// The OS of the image.
     pub os: String,
+    /// The variant of the CPU architecture (e.g. `v8` for `arm64`).
+    pub variant: Option<String>,
+    /// The version of the OS (mostly used by Windows images).
+    pub os_version: Option<String>,
+    /// The date and time when the image was created.
+    pub created: Option<String>,
+    /// The author of the image.
+    pub author: Option<String>,
+    /// Default parameters for running a container from this image.
+    pub runtime_config: RuntimeConfig,
     /// The total number of layers.
     pub total_layers: usize,
     /// The total number of non-empty layers.
     pub non_empty_layers: usize,
     /// All [Layers](Layer) of this image.
     pub layers: IndexMap<Sha256Digest, Layer>,
+    /// Is `Some` if this image is the result of [comparing](Image::compare) two images.
+    pub comparison: Option<ImageComparison>,
 }
 
 /// A single layer within the [Image].
@@ -119,6 +231,20 @@ pub struct Layer {
     pub created_by: String,
     /// Comment to the command from [Layer::created_by].
     pub comment: Option<String>,
+    /// Digest of the uncompressed layer from the image config.
+    pub diff_id: Option<Sha256Digest>,
+    /// Is `true` if the image's history doesn't match its layers.
+    ///
+    /// [Layer::created_by] and [Layer::comment] are unknown in this case.
+    pub history_mismatch: bool,
+    /// Problems found while verifying the layer's digests.
+    ///
+    /// Is `None` if verification wasn't requested, an empty [Vec] means that the layer was verified successfully.
+    pub verification_errors: Option<Vec<VerificationError>>,
+    /// Location of the layer's blob, which is used to read the contents of its files.
+    ///
+    /// Is `None` if the image can't be read again after parsing it.
+    pub blob: Option<LayerBlob>,
 }
 
 /// A parser for OCI-compliant container images represented as Tar blobs.
@@ -127,10 +253,44 @@ pub struct Layer {
 #[derive(Default)]
 pub struct Parser {
     parsed_layers: HashMap<Sha256Digest, (LayerChangeSet, LayerSize)>,
-    layer_configs: Option<ImageLayerConfigs>,
-    history: Option<ImageHistory>,
-    architecture: Option<String>,
-    os: Option<String>,
+    /// Blob types of layers that are known from their media types in the Image Manifest.
+    ///
+    /// Only contains layers whose media type was seen before the layer itself.
+    layer_blob_types: HashMap<Sha256Digest, BlobType>,
+    /// Image Index from the root of the image.
+    image_index: Option<ImageIndex>,
+    /// Nested Image Indexes (e.g. multi-platform images exported by Docker), keyed by their digests.
+    indexes: HashMap<Sha256Digest, ImageIndex>,
+    /// Image Manifests, keyed by their digests.
+    manifests: HashMap<Sha256Digest, ImageManifest>,
+    /// Image Configs, keyed by their digests.
+    configs: HashMap<Sha256Digest, ImageConfig>,
+    /// Docker-specific manifest from the root of the image.
+    docker_manifest: Option<DockerManifest>,
+    /// Image configs from the legacy `docker save` archives, keyed by their paths within the image.
+    legacy_configs: HashMap<String, ImageConfig>,
+    /// Layers that link to other layers in the legacy `docker save` archives.
+    layer_aliases: HashMap<Sha256Digest, Sha256Digest>,
+    /// The platform to use for multi-platform images.
+    platform: Option<Platform>,
+    /// Whether the layers' digests should be calculated and verified while parsing them.
+    verify: bool,
+    /// The number of threads to parse the layers with, the layers are parsed on the current thread if it's `None`.
+    threads: Option<NonZeroUsize>,
+    /// The cache that is used to avoid parsing the same compressed layers on every run.
+    cache: Option<LayerCache>,
+    /// Digests of the layers that were calculated while parsing them, keyed by the digests the layers are referenced by.
+    ///
+    /// Only populated if [Self::verify] is set.
+    layer_digests: HashMap<Sha256Digest, LayerDigests>,
+    /// Layers from the legacy `docker save` archives, which are stored under their IDs instead of their digests.
+    legacy_layers: HashSet<Sha256Digest>,
+    /// Opens the image again to read the contents of the layers' files after parsing it.
+    ///
+    /// Is only present if the image was parsed using [Self::parse_image_concurrently].
+    image_opener: Option<ImageOpener>,
+    /// Locations of the layer blobs that were found while parsing the image, keyed by their digests.
+    layer_blobs: HashMap<Sha256Digest, LayerBlob>,
     tagged_name: Option<String>,
 }
 
@@ -139,27 +299,82 @@ impl Parser {
         Parser::default()
     }
 
-    pub fn new_with_image(image: impl Into<String>) -> Self {
+    /// Uses the provided image name if the image itself doesn't specify one.
+    pub fn with_image(mut self, image: impl Into<String>) -> Self {
         let mut image = image.into();
         if !image.contains(':') {
             image.push_str(":latest");
         }
-        Parser {
-            tagged_name: Some(image),
-            ..Default::default()
-        }
+        self.tagged_name = Some(image);
+        self
+    }
+
+    /// Selects the image for the provided platform from multi-platform images.
+    ///
+    /// Check [Platform::host] to see which platform is used by default.
+    pub fn with_platform(mut self, platform: Option<Platform>) -> Self {
+        self.platform = platform;
+        self
+    }
+
+    /// Enables verification of the layers' digests.
+    ///
+    /// Each layer blob is hashed while it's being parsed and compared to its digest, while the uncompressed layer is
+    /// compared to its diff ID from the image config. Check [Layer::verification_errors] for the results.
+    ///
+    /// # Note
+    ///
+    /// Verification requires reading the layers in full, so it makes parsing uncompressed images considerably slower.
+    pub fn with_verification(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Sets the number of threads that are used to parse the layers.
+    ///
+    /// Only [Self::parse_image_concurrently], [Self::parse_image_layout] and [Self::parse_remote_image] can use more than
+    /// one thread.
+    pub fn with_threads(mut self, threads: NonZeroUsize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Sets the [LayerCache] that is used to store the parsed compressed layers.
+    ///
+    /// The cache is bypassed if the layers are [verified](Self::with_verification), as verification needs to read them.
+    pub fn with_cache(mut self, cache: Option<LayerCache>) -> Self {
+        self.cache = cache;
+        self
     }
 
     /// Parses an OCI-compliant container image from the provided image Tar blob.
+    ///
+    /// All layers are parsed on the current thread, use [Self::parse_image_concurrently] to parse them in parallel.
     pub fn parse_image<R: Read + Seek>(
         mut self,
         src: R,
     ) -> anyhow::Result<Image> {
+        self.scan_image(src, false)?;
+        self.finalize()
+    }
+
+    /// Reads all entries of the provided image Tar blob, parsing the layers and JSON blobs along the way.
+    ///
+    /// If `defer_layers` is set, the layers that are expensive to parse are skipped and returned instead, so that they
+    /// can be parsed in parallel later.
+    fn scan_image<R: Read + Seek>(
+        &mut self,
+        src: R,
+        defer_layers: bool,
+    ) -> anyhow::Result<Vec<DeferredBlob>> {
         let seeker = SeekerWithOffset::new(src);
         let mut archive = Archive::new(seeker);
         let mut entries = archive
             .entries_with_seek()
             .context("failed to get entries from the archive")?;
+        // Positions of entries are relative to the position where the current archive was created
+        let mut archive_offset = 0;
+        let mut deferred_blobs = Vec::new();
 
         // A reusable buffer used for determining the blob type
         let mut buf = [0u8; TAR_BLOCK_SIZE];
@@ -168,54 +383,130 @@ impl Parser {
 
             // Parse the image's manifest and extract name and tag if they are present
             if entry.header().path_bytes().as_ref() == IMAGE_MANIFEST_PATH {
-                self.tagged_name = DockerManifest::from_reader(&mut entry)?;
+                let mut docker_manifest =
+                    DockerManifest::from_reader(&mut entry)?;
+                self.tagged_name = docker_manifest
+                    .as_mut()
+                    .and_then(|manifest| manifest.repo_tags.as_mut())
+                    .and_then(|tags| tags.pop());
+                self.docker_manifest = docker_manifest;
                 // We are done with this entry
                 continue;
             }
 
-            // Parse the image's index and extract the name and tag if we don't already have them and they are present in the Index
-            if entry.header().path_bytes().as_ref() == IMAGE_INDEX_PATH
-                && self.tagged_name.is_none()
-            {
+            // Parse the image's index, which is used to find the image manifest
+            if entry.header().path_bytes().as_ref() == IMAGE_INDEX_PATH {
                 let json_blob = self.parse_json_blob::<JsonBlob>(&mut entry)?;
-                if let Some(known_json_blob) = json_blob {
-                    self.process_json_blob(known_json_blob);
+                if let Some(JsonBlob::Index(image_index)) = json_blob {
+                    self.process_image_index(image_index);
                 };
                 // We are done with this entry
                 continue;
             }
 
+            // Legacy `docker save` archives store image configs as `<id>.json` files in the root of the image
+            if is_legacy_config_path(&entry.header().path_bytes()) {
+                let json_blob = self.parse_json_blob::<JsonBlob>(&mut entry)?;
+                if let Some(JsonBlob::Config(config)) = json_blob {
+                    let path =
+                        String::from_utf8_lossy(&entry.header().path_bytes())
+                            .into_owned();
+                    // We don't know which config is the right one until we parse the Docker's manifest
+                    self.legacy_configs.insert(path, config);
+                }
+                // We are done with this entry
+                continue;
+            }
+
             let header = entry.header();
 
+            let Some(layer_sha256_digest) =
+                layer_digest_from_path(&header.path_bytes()).context(
+                    "failed to parse the layer's sha256 digest from the path",
+                )?
+            else {
+                // Skip the current entry if it's not a blob
+                continue;
+            };
+
+            if header.path_bytes().ends_with(LEGACY_LAYER_PATH_SUFFIX) {
+                // Legacy layers are stored under their IDs, so there is no digest to compare their blobs to
+                self.legacy_layers.insert(layer_sha256_digest);
+            }
+
+            if header.entry_type().is_symlink()
+                || header.entry_type().is_hard_link()
+            {
+                // Legacy `docker save` archives link duplicate layers to the already exported ones
+                if let Some(target_digest) = header
+                    .link_name_bytes()
+                    .map(|link| layer_digest_from_path(&link))
+                    .transpose()
+                    .context("failed to parse the linked layer's digest")?
+                    .flatten()
+                {
+                    self.layer_aliases
+                        .insert(layer_sha256_digest, target_digest);
+                }
+                continue;
+            }
+
             let entry_size_in_blocks = get_entry_size_in_blocks(header)
                 .context(
                     "failed to determine the entry's size in TAR blocks",
                 )?;
 
-            if !header.path_bytes().starts_with(BLOB_PATH_PREFIX)
-                || entry_size_in_blocks == 0
-            {
-                // Skip the current entry if it's not a blob or if it's size is 0
+            if entry_size_in_blocks == 0 {
+                // Skip the current entry if it's size is 0
                 continue;
             }
 
-            let layer_sha256_digest = sha256_digest_from_hex(
-                header
-                    .path_bytes()
-                    .strip_prefix(BLOB_PATH_PREFIX)
-                    // SAFETY: checked above
-                    .expect("should start with a blob path prefix"),
-            )
-            .context(
-                "failed to parse the layer's sha256 digest from the path",
-            )?;
+            let (mut blob_type, offset) =
+                determine_blob_type(&mut buf, &mut entry)
+                    .context("failed to determine the blob type of an entry")?;
+            blob_type = self.layer_blob_type(&layer_sha256_digest, blob_type);
+
+            if let Some(open) = self.image_opener.as_ref()
+                && (matches!(blob_type, BlobType::Tar)
+                    || blob_type.is_compressed_layer())
+            {
+                // Remember where the blob is, so that the contents of its files can be read later
+                self.layer_blobs.insert(
+                    layer_sha256_digest,
+                    LayerBlob::in_image(
+                        open.clone(),
+                        archive_offset + entry.raw_file_position(),
+                        entry.size(),
+                        blob_type,
+                    ),
+                );
+            }
+
+            if let Some(layer) = self
+                .layer_cache(&layer_sha256_digest, blob_type)
+                .and_then(|cache| cache.get(&layer_sha256_digest))
+            {
+                // The rest of the entry is skipped by the archive
+                self.parsed_layers.insert(layer_sha256_digest, layer);
+                continue;
+            }
 
-            let (blob_type, offset) = determine_blob_type(&mut buf, &mut entry)
-                .context("failed to determine the blob type of an entry")?;
+            if defer_layers && self.is_expensive_to_parse(blob_type) {
+                // The rest of the entry is skipped by the archive
+                deferred_blobs.push(DeferredBlob {
+                    digest: layer_sha256_digest,
+                    blob_type,
+                    offset: archive_offset + entry.raw_file_position(),
+                    size: entry.size(),
+                });
+                continue;
+            }
 
             match blob_type {
-                BlobType::Empty => {}
-                BlobType::Tar => {
+                // Empty blobs still need to be hashed when verifying the layers
+                BlobType::Empty if !self.verify => {}
+                // Seeking over the files' contents is not possible when the whole blob needs to be hashed
+                BlobType::Tar if !self.verify => {
                     // HACK: turn archive back into a reader to preserve the `Seek` trait and optimize parsing of the image layer
                     let mut reader = archive.into_inner();
 
@@ -243,38 +534,226 @@ impl Parser {
 
                     // Mark offset before restoring the outer archive below
                     reader.mark_offset();
+                    archive_offset = reader.offset();
                     // Restore the archive and the iterator
                     archive = Archive::new(reader);
                     entries = archive.entries_with_seek()?;
                 }
-                BlobType::GzippedTar => {
-                    // Restore the GZIP blob (as we've read some bytes from it to determine the blob type)
-                    let mut gzip_blob = buf[..offset].chain(entry);
-                    let (layer_changeset, layer_size) = self
-                        .parse_gzip_tar_blob(&mut gzip_blob)
-                        .context("error while parsing a gzipped tar layer")?;
-                    self.parsed_layers.insert(
-                        layer_sha256_digest,
-                        (layer_changeset, layer_size),
-                    );
+                _ => {
+                    // Restore the blob (as we've read some bytes from it to determine the blob type)
+                    let blob = buf[..offset].chain(entry);
+                    self.process_blob(layer_sha256_digest, blob_type, blob)?;
                 }
-                BlobType::Json => {
-                    let json_blob = self.parse_json_blob::<JsonBlob>(
-                        &mut buf[..offset].chain(entry),
-                    )?;
-                    if let Some(known_json_blob) = json_blob {
-                        self.process_json_blob(known_json_blob);
-                    };
-                }
-                BlobType::Unknown => {
-                    tracing::debug!(
-                        "Unknown blob type was encountered while parsing the image"
-                    )
+            }
+        }
+
+        Ok(deferred_blobs)
+    }
+
+    /// Checks if a blob of the provided [BlobType] is worth parsing in parallel with other blobs.
+    ///
+    /// Uncompressed layers are cheap to parse, as the files' contents are skipped by seeking over them, unless the
+    /// layers need to be hashed.
+    fn is_expensive_to_parse(&self, blob_type: BlobType) -> bool {
+        match blob_type {
+            BlobType::Empty | BlobType::Tar => self.verify,
+            blob_type => blob_type.is_compressed_layer(),
+        }
+    }
+
+    /// Returns the [LayerCache] if the layer with the provided digest and [BlobType] can be cached.
+    ///
+    /// Only compressed layers are cached, as uncompressed ones are parsed by seeking over the files' contents, which is
+    /// about as fast as reading them from the cache. Legacy layers are not cached, as they are not content-addressed.
+    fn layer_cache(
+        &self,
+        digest: &Sha256Digest,
+        blob_type: BlobType,
+    ) -> Option<&LayerCache> {
+        self.cache.as_ref().filter(|_| {
+            !self.verify
+                && blob_type.is_compressed_layer()
+                && !self.legacy_layers.contains(digest)
+        })
+    }
+
+    /// Returns the [BlobType] of a layer, using its media type if sniffing is inconclusive (check
+    /// [resolve_layer_blob_type]).
+    fn layer_blob_type(
+        &self,
+        digest: &Sha256Digest,
+        sniffed_blob_type: BlobType,
+    ) -> BlobType {
+        resolve_layer_blob_type(
+            sniffed_blob_type,
+            self.layer_blob_types.get(digest).copied(),
+        )
+    }
+
+    /// Processes a single blob of the provided [BlobType] within the image.
+    ///
+    /// Uncompressed Tar blobs are read sequentially, so it's better to use [Self::parse_tar_blob] for them if the
+    /// source supports seeking.
+    fn process_blob(
+        &mut self,
+        digest: Sha256Digest,
+        blob_type: BlobType,
+        mut src: impl Read,
+    ) -> anyhow::Result<()> {
+        match blob_type {
+            BlobType::Json => {
+                let json_blob = self.parse_json_blob::<JsonBlob>(&mut src)?;
+                if let Some(known_json_blob) = json_blob {
+                    self.process_json_blob(digest, known_json_blob);
+                };
+            }
+            BlobType::Unknown => {
+                tracing::debug!(
+                    "Unknown blob type was encountered while parsing the image"
+                );
+            }
+            blob_type => {
+                let parsed = self.read_layer_blob(&digest, blob_type, src)?;
+                self.insert_layer_blob(digest, parsed);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a single layer blob of the provided [BlobType], verifying it if [Self::verify] is set.
+    ///
+    /// During verification, errors that happen while parsing the layer are recorded instead of being returned, as a
+    /// mismatching digest explains them much better than an error from the decoder or the Tar parser.
+    fn read_layer_blob(
+        &self,
+        digest: &Sha256Digest,
+        blob_type: BlobType,
+        src: impl Read,
+    ) -> anyhow::Result<ParsedLayerBlob> {
+        if !self.verify {
+            let layer = match blob_type {
+                BlobType::Empty => None,
+                blob_type => {
+                    let (layer_changeset, layer_size, _) =
+                        self.parse_layer_blob(blob_type, src)?;
+                    if let Some(cache) = self.layer_cache(digest, blob_type) {
+                        cache.insert(digest, &layer_changeset, layer_size);
+                    }
+                    Some((layer_changeset, layer_size))
                 }
+            };
+            return Ok(ParsedLayerBlob {
+                layer,
+                digests: None,
+            });
+        }
+
+        let mut src = DigestReader::new(src);
+        let parsed = match blob_type {
+            // There is nothing to parse, but the blob still needs to be hashed
+            BlobType::Empty => Ok(None),
+            blob_type => self.parse_layer_blob(blob_type, &mut src).map(Some),
+        };
+        src.drain().context("failed to read a layer blob")?;
+        let blob_digest = src.finalize();
+
+        let (layer, diff_id, error) = match parsed {
+            Ok(Some((layer_changeset, layer_size, diff_id))) => (
+                Some((layer_changeset, layer_size)),
+                // Uncompressed blobs are their own diff IDs
+                Some(diff_id.unwrap_or(blob_digest)),
+                None,
+            ),
+            Ok(None) => (None, Some(blob_digest), None),
+            Err(e) => {
+                tracing::debug!(
+                    "Failed to parse layer {}: {:#}",
+                    sha256_digest_to_hex(digest),
+                    e
+                );
+                (None, None, Some(format!("{e:#}")))
             }
+        };
+
+        Ok(ParsedLayerBlob {
+            layer,
+            digests: Some(LayerDigests {
+                blob: blob_digest,
+                diff_id,
+                error,
+            }),
+        })
+    }
+
+    /// Stores the results of [Self::read_layer_blob].
+    fn insert_layer_blob(
+        &mut self,
+        digest: Sha256Digest,
+        parsed: ParsedLayerBlob,
+    ) {
+        if let Some(layer) = parsed.layer {
+            self.parsed_layers.insert(digest, layer);
         }
+        if let Some(digests) = parsed.digests {
+            self.layer_digests.insert(digest, digests);
+        }
+    }
 
-        self.finalize()
+    /// Parses a single layer blob of the provided [BlobType] by reading it sequentially.
+    ///
+    /// Also returns the digest of the uncompressed layer for compressed blobs if [Self::verify] is set.
+    fn parse_layer_blob(
+        &self,
+        blob_type: BlobType,
+        src: impl Read,
+    ) -> anyhow::Result<(LayerChangeSet, LayerSize, Option<Sha256Digest>)> {
+        match blob_type {
+            BlobType::Tar => self
+                .parse_compressed_tar_blob(src)
+                .map(|(layer_changeset, layer_size)| {
+                    (layer_changeset, layer_size, None)
+                })
+                .context("error while parsing a tar layer"),
+            BlobType::GzippedTar => self
+                .parse_decompressed_tar_blob(GzDecoder::new(src))
+                .context("error while parsing a gzipped tar layer"),
+            BlobType::ZstdTar => {
+                let decoder = zstd::Decoder::new(src)
+                    .context("failed to create a zstd decoder")?;
+                self.parse_decompressed_tar_blob(decoder)
+                    .context("error while parsing a zstd tar layer")
+            }
+            BlobType::XzTar => self
+                .parse_decompressed_tar_blob(XzDecoder::new_multi_decoder(src))
+                .context("error while parsing a xz tar layer"),
+            BlobType::Bzip2Tar => self
+                .parse_decompressed_tar_blob(MultiBzDecoder::new(src))
+                .context("error while parsing a bzip2 tar layer"),
+            BlobType::Empty | BlobType::Json | BlobType::Unknown => {
+                unreachable!("only Tar blobs can be parsed as layers")
+            }
+        }
+    }
+
+    /// Parses a single layer from the output of a decoder, hashing it along the way if [Self::verify] is set.
+    fn parse_decompressed_tar_blob(
+        &self,
+        src: impl Read,
+    ) -> anyhow::Result<(LayerChangeSet, LayerSize, Option<Sha256Digest>)> {
+        if !self.verify {
+            let (layer_changeset, layer_size) =
+                self.parse_compressed_tar_blob(src)?;
+            return Ok((layer_changeset, layer_size, None));
+        }
+
+        let mut src = DigestReader::new(src);
+        let (layer_changeset, layer_size) =
+            self.parse_compressed_tar_blob(&mut src)?;
+        src.drain()
+            .context("failed to read the rest of the decompressed layer")?;
+
+        Ok((layer_changeset, layer_size, Some(src.finalize())))
     }
 
     /// Parses a single JSON blob within the image.
@@ -297,46 +776,56 @@ impl Parser {
     }
 
     /// Processes a single known JSON blob extracted from an image.
-    fn process_json_blob(&mut self, json_blob: JsonBlob) {
+    fn process_json_blob(&mut self, digest: Sha256Digest, json_blob: JsonBlob) {
         match json_blob {
-            JsonBlob::Manifest {
-                layers: parsed_layers,
-            } => {
-                self.layer_configs = Some(parsed_layers);
+            JsonBlob::Manifest(manifest) => {
+                for layer in manifest.layers.iter() {
+                    if let Some(blob_type) = layer
+                        .media_type
+                        .as_deref()
+                        .and_then(blob_type_from_media_type)
+                    {
+                        self.layer_blob_types.insert(layer.digest, blob_type);
+                    }
+                }
+                self.manifests.insert(digest, manifest);
             }
-            JsonBlob::Config {
-                architecture: parsed_architecture,
-                os: parsed_os,
-                history: parsed_history,
-            } => {
-                self.architecture = Some(parsed_architecture);
-                self.os = Some(parsed_os);
-                self.history = Some(parsed_history);
+            JsonBlob::Config(config) => {
+                self.configs.insert(digest, config);
             }
-            JsonBlob::Index {
-                manifests: parsed_manifests,
-            } => {
-                for annotations in parsed_manifests
-                    .into_iter()
-                    .flat_map(|manifest| manifest.annotations)
+            JsonBlob::Index(index) => {
+                self.indexes.insert(digest, index);
+            }
+        }
+    }
+
+    /// Processes the Image Index from the root of the image.
+    ///
+    /// Extracts the name and tag if we don't already have them and they are present in the Index.
+    fn process_image_index(&mut self, image_index: ImageIndex) {
+        if self.tagged_name.is_none() {
+            for annotations in image_index
+                .manifests
+                .iter()
+                .flat_map(|manifest| manifest.annotations.as_ref())
+            {
+                if let Some(image_ref) =
+                    annotations.fully_qualified_image_name.as_ref()
                 {
-                    if let Some(mut image_ref) =
-                        annotations.fully_qualified_image_name
-                    {
-                        if let Some(image_name_start_pos) = image_ref.rfind('/')
-                        {
-                            // Remove the registry if present
-                            image_ref
-                                .replace_range(0..=image_name_start_pos, "");
-                        }
-
-                        self.tagged_name = Some(image_ref);
-                        // No need to look further, as we've already found all the information that we might need from the Image Index
-                        break;
+                    let mut image_ref = image_ref.clone();
+                    if let Some(image_name_start_pos) = image_ref.rfind('/') {
+                        // Remove the registry if present
+                        image_ref.replace_range(0..=image_name_start_pos, "");
                     }
+
+                    self.tagged_name = Some(image_ref);
+                    // No need to look further, as we've already found all the information that we might need from the Image Index
+                    break;
                 }
             }
         }
+
+        self.image_index = Some(image_index);
     }
 
     /// Parses a single image layer represented as a Tar blob.
@@ -372,7 +861,11 @@ impl Parser {
             }
 
             layer_size += self
-                .process_layer_blob_entry_header(header, &mut change_set)
+                .process_layer_blob_entry_header(
+                    header,
+                    entry.raw_file_position(),
+                    &mut change_set,
+                )
                 .context("failed to process an entry in a Tar layer")?
                 .unwrap_or(0);
         }
@@ -380,16 +873,16 @@ impl Parser {
         Ok((change_set, layer_size))
     }
 
-    /// Parses a single image layer represented as a GZipped Tar blob.
+    /// Parses a single image layer represented as a compressed Tar blob.
     ///
     /// # Note
     ///
-    /// It wraps the passed reader in a [GzDecoder] before trying to read entries from it.
-    fn parse_gzip_tar_blob<R: Read>(
+    /// The passed reader must already be wrapped in a decoder (e.g. [GzDecoder]) that matches the blob's compression.
+    fn parse_compressed_tar_blob<R: Read>(
         &self,
-        src: &mut R,
+        src: R,
     ) -> anyhow::Result<(LayerChangeSet, LayerSize)> {
-        let mut archive = Archive::new(GzDecoder::new(src));
+        let mut archive = Archive::new(src);
 
         // We will set the actual layer idx later in [Self::finalize]
         let mut change_set = LayerChangeSet::new(0);
@@ -397,16 +890,22 @@ impl Parser {
         let mut layer_size = 0;
         for entry in archive
             .entries()
-            .context("failed to get entries from the gzipped tar blob")?
+            .context("failed to get entries from the compressed tar blob")?
         {
             let entry = entry.context(
-                "error while reading an entry from the gzipped tar blob",
+                "error while reading an entry from the compressed tar blob",
             )?;
             let header = entry.header();
 
             layer_size += self
-                .process_layer_blob_entry_header(header, &mut change_set)
-                .context("failed to process an entry in a GZipped Tar layer")?
+                .process_layer_blob_entry_header(
+                    header,
+                    entry.raw_file_position(),
+                    &mut change_set,
+                )
+                .context(
+                    "failed to process an entry in a compressed Tar layer",
+                )?
                 .unwrap_or(0);
         }
 
@@ -415,14 +914,17 @@ impl Parser {
 
     /// Processes a single Tar [Header] of an entry in a layer.
     ///
+    /// `content_offset` is the position of the entry's contents within the layer.
+    ///
     /// Returns the entry's size if it was successfully added to the provided [LayerChangeSet].
     fn process_layer_blob_entry_header(
         &self,
         header: &Header,
+        content_offset: u64,
         changeset: &mut LayerChangeSet,
     ) -> anyhow::Result<Option<u64>> {
         let Some((node_path, node, node_size)) = self
-            .process_layer_entry(header)
+            .process_layer_entry(header, content_offset)
             .context("failed to process an entry in the layer")?
         else {
             // A `None` means that we can safely skip this entry
@@ -448,6 +950,7 @@ impl Parser {
     fn process_layer_entry<'a>(
         &self,
         header: &'a Header,
+        content_offset: u64,
     ) -> anyhow::Result<Option<(Cow<'a, Path>, InnerNode, u64)>> {
         let Ok(path) = header.path() else {
             tracing::debug!(
@@ -463,8 +966,17 @@ impl Parser {
             return Ok(None);
         }
 
+        let kind = NodeKind::from(header.entry_type());
+        let metadata = NodeMetadata::from_header(header);
+
         if header.entry_type().is_dir() {
-            return Ok(Some((path, InnerNode::new_empty_dir(), 0)));
+            return Ok(Some((
+                path,
+                InnerNode::Directory(
+                    DirectoryState::new_empty().with_metadata(metadata),
+                ),
+                0,
+            )));
         }
 
         let size = header.size().unwrap_or(0);
@@ -476,10 +988,14 @@ impl Parser {
         {
             return Ok(Some((
                 path,
-                InnerNode::File(FileState::new(
-                    NodeStatus::Added(0),
-                    Some(link.into_owned()),
-                )),
+                InnerNode::File(
+                    FileState::new(
+                        NodeStatus::Added(0),
+                        Some(link.into_owned()),
+                    )
+                    .with_kind(kind)
+                    .with_metadata(metadata),
+                ),
                 size,
             )));
         }
@@ -489,8 +1005,19 @@ impl Parser {
             return Ok(None);
         };
 
-        let (path, status) =
-            if file_name.as_encoded_bytes().starts_with(b".wh.") {
+        if file_name.as_encoded_bytes() == OPAQUE_WHITEOUT_FILE_NAME {
+            // An opaque whiteout: mark the parent directory as opaque, so that its
+            // contents from the lower layers are hidden when merging the trees
+            let parent = path.parent().unwrap_or(Path::new("")).to_owned();
+            return Ok(Some((
+                Cow::Owned(parent),
+                InnerNode::new_opaque_dir(),
+                0,
+            )));
+        }
+
+        let (path, state) =
+            if file_name.as_encoded_bytes().starts_with(WHITEOUT_PREFIX) {
                 // A whiteout
 
                 // Strip the whiteout prefix
@@ -500,53 +1027,83 @@ impl Parser {
                         OsStr::from_encoded_bytes_unchecked(
                             file_name
                                 .as_encoded_bytes()
-                                .strip_prefix(b".wh.")
+                                .strip_prefix(WHITEOUT_PREFIX)
                                 .expect("prefix must exist at this point"),
                         )
                     },
                 ));
 
-                (path, NodeStatus::Deleted)
-            } else if file_name.as_encoded_bytes() != b".wh..wh..opq" {
-                // A regular file
-                (path, NodeStatus::Added(size))
+                (path, FileState::new(NodeStatus::Deleted(0), None))
             } else {
-                // An opaque whiteout
-
-                // FIXME: I need to mark a directory as one that contains an opaque whiteout file
-                // and then handle such directories correspondingly when merging the trees
-                return Ok(None);
+                // A regular file or a special one (e.g. a device or a FIFO)
+                (
+                    path,
+                    FileState::new(NodeStatus::Added(size), None)
+                        .with_kind(kind)
+                        .with_metadata(metadata)
+                        .with_content_offset(content_offset),
+                )
             };
 
-        Ok(Some((
-            path,
-            InnerNode::File(FileState::new(status, None)),
-            size,
-        )))
+        Ok(Some((path, InnerNode::File(state), size)))
     }
 
     /// Processes all the parsed data and turns it into an [Image].
-    fn finalize(self) -> anyhow::Result<Image> {
+    fn finalize(mut self) -> anyhow::Result<Image> {
+        let (layer_configs, config) = self
+            .resolve_image_manifest()
+            .context("failed to find the image manifest")?;
+
+        self.build_image(layer_configs, config)
+    }
+
+    /// Turns the parsed layers into an [Image] using the resolved layer configs and image config.
+    fn build_image(
+        mut self,
+        layer_configs: ImageLayerConfigs,
+        config: ImageConfig,
+    ) -> anyhow::Result<Image> {
+        if let Some(cache) = self.cache.as_ref() {
+            // New layers could've been added to the cache while parsing the image
+            cache.evict();
+        }
+
         // Use IndexMap so that layers are always in the correct order
         let mut layers = IndexMap::new();
 
-        let layer_configs = self
-            .layer_configs
-            .context("malformed container image: manifest is missing")?;
-        let layers_history = self
-            .history
-            .context("malformed container image: config is missing")?;
+        for (alias, target) in self.layer_aliases.iter() {
+            if let Some(layer) = self.parsed_layers.get(target).cloned() {
+                self.parsed_layers.insert(*alias, layer);
+            }
+            if let Some(digests) = self.layer_digests.get(target).cloned() {
+                self.layer_digests.insert(*alias, digests);
+            }
+            if let Some(blob) = self.layer_blobs.get(target).cloned() {
+                self.layer_blobs.insert(*alias, blob);
+            }
+        }
 
-        let total_layers = layers_history.len();
+        let total_layers = config.history.len();
         let non_empty_layers = layer_configs.len();
 
+        let diff_ids = config.rootfs.diff_ids;
+        if diff_ids.len() != non_empty_layers {
+            tracing::warn!(
+                "The image config has {} diff IDs, but the image has {} layers",
+                diff_ids.len(),
+                non_empty_layers
+            );
+        }
+        let layers_history =
+            match_history_to_layers(config.history, non_empty_layers);
+
         let mut per_layer_changeset = self.parsed_layers;
         let mut image_size = 0;
-        for (layer_config, layer_history) in layer_configs.into_iter().zip(
-            layers_history
-                .into_iter()
-                .filter(|entry| !entry.empty_layer),
-        ) {
+        for ((layer_idx, layer_config), layer_history) in layer_configs
+            .into_iter()
+            .enumerate()
+            .zip(layers_history.entries)
+        {
             let (mut layer_changeset, layer_size) = per_layer_changeset
                 .remove(&layer_config.digest)
                 .map(|(changeset, size)| (Some(changeset), size))
@@ -560,17 +1117,46 @@ impl Parser {
                 changeset.set_layer_recursively(layers.len() as u8)
             }
 
-            // Normalize the layer creation command
-            let created_by = layer_history.created_by.split_whitespace().fold(
-                String::with_capacity(layer_history.created_by.len()),
-                |mut output, word| {
-                    if !output.is_empty() {
-                        output.push(' ');
-                    }
-                    output.push_str(word);
-                    output
-                },
-            );
+            let (created_by, comment) = match layer_history {
+                Some(layer_history) => (
+                    // Normalize the layer creation command
+                    layer_history.created_by.split_whitespace().fold(
+                        String::with_capacity(layer_history.created_by.len()),
+                        |mut output, word| {
+                            if !output.is_empty() {
+                                output.push(' ');
+                            }
+                            output.push_str(word);
+                            output
+                        },
+                    ),
+                    layer_history.comment,
+                ),
+                None if layers_history.is_mismatched => {
+                    (MISMATCHED_HISTORY_PLACEHOLDER.to_owned(), None)
+                }
+                None => (MISSING_HISTORY_PLACEHOLDER.to_owned(), None),
+            };
+            let diff_id = diff_ids.get(layer_idx).copied();
+
+            let verification_errors = self.verify.then(|| {
+                let expected_blob =
+                    (!self.legacy_layers.contains(&layer_config.digest))
+                        .then_some(&layer_config.digest);
+                let errors = verify_layer(
+                    self.layer_digests.get(&layer_config.digest),
+                    expected_blob,
+                    diff_id.as_ref(),
+                );
+                for error in errors.iter() {
+                    tracing::debug!(
+                        "Layer {} failed verification: {}",
+                        sha256_digest_to_hex(&layer_config.digest),
+                        error
+                    );
+                }
+                errors
+            });
 
             image_size += layer_size;
             layers.insert(
@@ -579,7 +1165,11 @@ impl Parser {
                     changeset: layer_changeset,
                     size: layer_size,
                     created_by,
-                    comment: layer_history.comment,
+                    comment,
+                    diff_id,
+                    history_mismatch: layers_history.is_mismatched,
+                    verification_errors,
+                    blob: self.layer_blobs.get(&layer_config.digest).cloned(),
                 },
             );
         }
@@ -601,23 +1191,188 @@ impl Parser {
             image_name,
             tag,
             size: image_size,
-            architecture: self
-                .architecture
-                .context("malformed container image: missing architecture")?,
-            os: self.os.context("malformed container image: missing OS")?,
+            architecture: config.architecture,
+            os: config.os,
+            variant: config.variant,
+            os_version: config.os_version,
+            created: config.created,
+            author: config.author,
+            runtime_config: config
+                .config
+                .map(|config| *config)
+                .unwrap_or_default(),
             total_layers,
             non_empty_layers,
             layers,
+            comparison: None,
         })
     }
 }
 
+/// The maximum depth of nested Image Indexes that we are going to follow.
+const MAX_NESTED_INDEX_DEPTH: usize = 4;
+
+impl Parser {
+    /// Finds the Image Manifest and Image Config that should be used for the image.
+    ///
+    /// Follows the descriptors from the Image Index if it's present, selecting a single platform from multi-platform images.
+    /// Falls back to the Docker-specific manifest from legacy `docker save` archives otherwise.
+    fn resolve_image_manifest(
+        &mut self,
+    ) -> anyhow::Result<(ImageLayerConfigs, ImageConfig)> {
+        let mut candidates = Vec::new();
+        if let Some(image_index) = self.image_index.as_ref() {
+            self.collect_index_candidates(image_index, 0, &mut candidates);
+        } else {
+            for digest in self.manifests.keys() {
+                if let Some(platform) = self.manifest_platform(digest, None) {
+                    candidates.push((*digest, platform));
+                }
+            }
+        }
+
+        if candidates.is_empty() {
+            return self.resolve_docker_manifest();
+        }
+
+        let platforms = candidates
+            .iter()
+            .map(|(_, platform)| platform.clone())
+            .collect::<Vec<_>>();
+        let selected = select_platform(self.platform.as_ref(), &platforms)?;
+
+        let manifest = self
+            .manifests
+            .remove(&candidates[selected].0)
+            // SAFETY: we only use the manifests that we've parsed
+            .expect("candidate manifest must be present");
+        let config = self
+            .configs
+            .remove(&manifest.config.digest)
+            // SAFETY: checked when collecting the candidates
+            .expect("candidate config must be present");
+
+        Ok((manifest.layers, config))
+    }
+
+    /// Collects all Image Manifests from the provided Image Index (and its nested indexes) that can be used for the image.
+    fn collect_index_candidates(
+        &self,
+        image_index: &ImageIndex,
+        depth: usize,
+        candidates: &mut Vec<(Sha256Digest, Platform)>,
+    ) {
+        for entry in image_index.manifests.iter() {
+            if let Some(nested_index) = self.indexes.get(&entry.digest) {
+                if depth < MAX_NESTED_INDEX_DEPTH {
+                    self.collect_index_candidates(
+                        nested_index,
+                        depth + 1,
+                        candidates,
+                    );
+                }
+                continue;
+            }
+
+            if candidates.iter().any(|(digest, _)| *digest == entry.digest) {
+                continue;
+            }
+
+            if let Some(platform) =
+                self.manifest_platform(&entry.digest, entry.platform.as_ref())
+            {
+                candidates.push((entry.digest, platform));
+            }
+        }
+    }
+
+    /// Returns the [Platform] of an Image Manifest if it can be used for the image.
+    ///
+    /// The platform from the Image Index takes priority over the one from the image's config.
+    ///
+    /// Returns `None` if the manifest or its config are missing in the image (e.g. Docker only exports manifests for
+    /// the pulled platforms), as well as for manifests with the `unknown/unknown` platform (e.g. attestations).
+    fn manifest_platform(
+        &self,
+        digest: &Sha256Digest,
+        index_platform: Option<&Platform>,
+    ) -> Option<Platform> {
+        let manifest = self.manifests.get(digest)?;
+        let config = self.configs.get(&manifest.config.digest)?;
+
+        let platform =
+            index_platform.cloned().unwrap_or_else(|| config.platform());
+
+        (!platform.is_unknown()).then_some(platform)
+    }
+
+    /// Uses the Docker-specific manifest to find the image's layers and config.
+    fn resolve_docker_manifest(
+        &mut self,
+    ) -> anyhow::Result<(ImageLayerConfigs, ImageConfig)> {
+        let docker_manifest = self
+            .docker_manifest
+            .take()
+            .context("malformed container image: manifest is missing")?;
+
+        let config = docker_manifest
+            .config
+            .and_then(|path| {
+                self.legacy_configs.remove(&path).or_else(|| {
+                    let digest =
+                        layer_digest_from_path(path.as_bytes()).ok()??;
+                    self.configs.remove(&digest)
+                })
+            })
+            .context("malformed container image: config is missing")?;
+
+        // Ensure that we don't silently use an image for a different platform
+        select_platform(self.platform.as_ref(), &[config.platform()])?;
+
+        let layer_configs = docker_manifest
+            .layers
+            .iter()
+            .map(|path| {
+                layer_digest_from_path(path.as_bytes())?
+                    .map(|digest| LayerConfig {
+                        digest,
+                        media_type: None,
+                    })
+                    .with_context(|| {
+                        format!(
+                            "unexpected layer path in the Docker's manifest: {}",
+                            path
+                        )
+                    })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok((layer_configs, config))
+    }
+}
+
 /// Represents the type of a single TAR entry in an image.
 #[derive(Debug, Clone, Copy)]
 enum BlobType {
     Empty,
     Tar,
     GzippedTar,
+    ZstdTar,
+    XzTar,
+    Bzip2Tar,
     Json,
     Unknown,
 }
+
+impl BlobType {
+    /// Returns `true` if this is a compressed layer.
+    fn is_compressed_layer(&self) -> bool {
+        matches!(
+            self,
+            BlobType::GzippedTar
+                | BlobType::ZstdTar
+                | BlobType::XzTar
+                | BlobType::Bzip2Tar
+        )
+    }
+}
@@ -1,9 +1,10 @@
 use std::borrow::Cow;
 use std::path::Path;
+use std::sync::Arc;
 
 use anyhow::Context;
 
-use super::{Node, NodeFilters, RestorablePath};
+use super::{Node, NodeFilters, NodeKind, NodeMetadata, RestorablePath};
 use crate::parser::{DirMap, DirectoryState, FileState, NodeStatus};
 
 /// Represents the actual state of a file tree [nodes](super::Node).
@@ -26,6 +27,13 @@ impl InnerNode {
         InnerNode::Directory(DirectoryState::new_with_size(size))
     }
 
+    /// Creates a new empty node of type [InnerNode::Directory] with status [NodeStatus::Added] that is marked as opaque.
+    ///
+    /// Check [DirectoryState::opaque] for more details.
+    pub fn new_opaque_dir() -> Self {
+        InnerNode::Directory(DirectoryState::new_opaque())
+    }
+
     /// Returns `true` if this node's [status](NodeStatus) is [NodeStatus::Added].
     pub fn is_added(&self) -> bool {
         matches!(self.status(), NodeStatus::Added(_))
@@ -38,7 +46,15 @@ impl InnerNode {
 
     /// Returns `true` if this node's [status](NodeStatus) is [NodeStatus::Deleted].
     pub fn is_deleted(&self) -> bool {
-        matches!(self.status(), NodeStatus::Deleted)
+        matches!(self.status(), NodeStatus::Deleted(_))
+    }
+
+    /// Returns the size of the node that was deleted by this one or [Option::None] if this node isn't deleted.
+    pub fn deleted_size(&self) -> Option<u64> {
+        match self.status() {
+            NodeStatus::Deleted(size) => Some(size),
+            _ => None,
+        }
     }
 
     /// Returns the size of a node unless it's status is [NodeStatus::Deleted], in which case
@@ -67,13 +83,21 @@ impl InnerNode {
     pub fn get_n_of_child_nodes(&self) -> Option<usize> {
         let children = self.children()?;
         let mut n_of_children = children.len();
-        for (_, child_node) in children.iter() {
+        for child_node in children.values() {
             n_of_children +=
                 child_node.inner.get_n_of_child_nodes().unwrap_or(0)
         }
         Some(n_of_children)
     }
 
+    /// Returns the [NodeKind] of this node.
+    pub fn kind(&self) -> NodeKind {
+        match self {
+            InnerNode::File(state) => state.kind,
+            InnerNode::Directory(_) => NodeKind::Directory,
+        }
+    }
+
     /// Returns a reference to the [FileState::actual_file] for this [InnerNode::File] or [Option::None] if the node is a [InnerNode::Directory].
     pub fn get_link(&self) -> Option<&Path> {
         match self {
@@ -82,6 +106,28 @@ impl InnerNode {
         }
     }
 
+    /// Returns the [NodeMetadata] of this node or [Option::None] if it wasn't recorded in any layer.
+    pub fn metadata(&self) -> Option<&NodeMetadata> {
+        match self {
+            InnerNode::File(state) => state.metadata.as_ref(),
+            InnerNode::Directory(state) => state.metadata.as_ref(),
+        }
+    }
+
+    /// Returns the position of this node's contents within the layer that last updated it.
+    ///
+    /// Returns [Option::None] if the node doesn't have any contents (i.e. it's not a regular file or it was deleted).
+    pub fn content_offset(&self) -> Option<u64> {
+        match self {
+            InnerNode::File(state)
+                if state.kind == NodeKind::File && !self.is_deleted() =>
+            {
+                Some(state.content_offset)
+            }
+            _ => None,
+        }
+    }
+
     /// Returns a mutable reference to the [DirectoryState] of this [InnerNode::Directory] or [Option::None] if the node is a [InnerNode::File].
     pub(super) fn dir_state_mut(&mut self) -> Option<&mut DirectoryState> {
         match self {
@@ -105,7 +151,7 @@ impl InnerNode {
 
         // We ignore files here, as they are handled when processing children of directories
         if let InnerNode::Directory(state) = self {
-            state.children.retain(|path, child| {
+            Arc::make_mut(&mut state.children).retain(|path, child| {
                 // Size-based filtering
                 if let Some(node_size_filter) = filter.node_size_filter
                     && child.inner.size() < node_size_filter
@@ -271,6 +317,20 @@ impl InnerNode {
         layer_digest: u8,
     ) -> anyhow::Result<()> {
         let Some(current_path_component) = path.get_current_component() else {
+            if let (
+                InnerNode::Directory(state),
+                InnerNode::Directory(new_state),
+            ) = (&mut *self, &new_node)
+            {
+                // Don't drop the already inserted children of a directory (e.g. when an opaque whiteout
+                // comes after some other entries in the same directory), only update the opaque marker
+                // and the metadata (e.g. when the directory's own entry comes after its children)
+                state.opaque |= new_state.opaque;
+                if new_state.metadata.is_some() {
+                    state.metadata = new_state.metadata.clone();
+                }
+                return Ok(());
+            }
             // Replace the node, as there are no more path components
             *self = new_node;
             return Ok(());
@@ -278,7 +338,7 @@ impl InnerNode {
 
         if let InnerNode::Directory(state) = self {
             if !state.children.contains_key(current_path_component) {
-                state.children.insert(
+                Arc::make_mut(&mut state.children).insert(
                     current_path_component.into(),
                     Node::new_with_inner(
                         layer_digest,
@@ -293,7 +353,7 @@ impl InnerNode {
             //
             // Thus, we may need to replace a node before appending other nodes to it.
             let mut dir_state = DirectoryState::new_with_size(new_node.size());
-            dir_state.children.insert(
+            Arc::make_mut(&mut dir_state.children).insert(
                 current_path_component.into(),
                 Node::new_with_inner(
                     layer_digest,
@@ -315,25 +375,64 @@ impl InnerNode {
     }
 
     /// Recursively merges two [nodes](InnerNode) together and returns the result.
-    pub(super) fn merge(mut self, other: Self, digest: u8) -> Self {
+    ///
+    /// Updates `n_of_nodes_delta` with the change in the total number of nodes in the tree caused by the merge.
+    pub(super) fn merge(
+        mut self,
+        other: Self,
+        digest: u8,
+        n_of_nodes_delta: &mut isize,
+    ) -> Self {
         match (&mut self, other) {
             // Both nodes are directories
             (
                 InnerNode::Directory(left_state),
                 InnerNode::Directory(right_state),
             ) => {
-                for (path, right_node) in right_state.children {
+                let is_opaque = right_state.opaque;
+                let left_children = Arc::make_mut(&mut left_state.children);
+                if is_opaque {
+                    // An opaque directory hides everything that the lower layers put into it,
+                    // so all nodes that are missing in the right parent node are deleted
+                    for (path, left_node) in left_children.iter_mut() {
+                        if !left_node.inner.is_deleted()
+                            && !right_state.children.contains_key(path)
+                        {
+                            left_node.updated_in = digest;
+                            left_node.inner.mark_as_deleted(digest);
+                        }
+                    }
+                }
+                // Nodes are only copied if the right directory is shared with other trees
+                for (path, mut right_node) in
+                    Arc::unwrap_or_clone(right_state.children)
+                {
                     // If a node if present in both left and right parent node, we need to merge the two.
                     // Otherwise, we use the node from the right parent as is.
-                    let updated_node = if let Some(left_node) =
-                        left_state.children.remove(&path)
-                    {
-                        left_node.inner.merge(right_node.inner, digest)
-                    } else {
-                        right_node.inner
-                    };
+                    let updated_node =
+                        if let Some(left_node) = left_children.remove(&path) {
+                            if is_opaque
+                                && let Some(state) =
+                                    right_node.inner.dir_state_mut()
+                            {
+                                // Subdirectories of an opaque directory replace the lower ones wholesale as well
+                                state.opaque = true;
+                            }
+                            left_node.inner.merge(
+                                right_node.inner,
+                                digest,
+                                n_of_nodes_delta,
+                            )
+                        } else {
+                            *n_of_nodes_delta += 1 + right_node
+                                .inner
+                                .get_n_of_child_nodes()
+                                .unwrap_or(0)
+                                as isize;
+                            right_node.inner
+                        };
                     // Insert the updated node back into the left parent node
-                    left_state.children.insert(
+                    left_children.insert(
                         path,
                         Node::new_with_inner(
                             right_node.updated_in,
@@ -357,6 +456,10 @@ impl InnerNode {
                     (_, _) => right_state.status,
                 };
                 left_state.status = new_status;
+                // Keep the metadata from the lower layers if this directory doesn't have its own entry in the upper one
+                if right_state.metadata.is_some() {
+                    left_state.metadata = right_state.metadata;
+                }
             }
             // Both nodes are files
             (InnerNode::File(left_state), InnerNode::File(right_state)) => {
@@ -366,9 +469,23 @@ impl InnerNode {
                     (_, NodeStatus::Added(new_size)) => {
                         NodeStatus::Modified(*new_size)
                     }
+                    (
+                        NodeStatus::Added(size) | NodeStatus::Modified(size),
+                        NodeStatus::Deleted(_),
+                    ) => NodeStatus::Deleted(*size),
                     (_, _) => right_state.status,
                 };
                 left_state.status = new_status;
+                // Whiteouts don't carry anything besides the path, so the deleted file keeps its last known state
+                if !matches!(right_state.status, NodeStatus::Deleted(_)) {
+                    // A file can be replaced by a link, a device, etc.
+                    left_state.kind = right_state.kind;
+                    left_state.actual_file = right_state.actual_file;
+                    left_state.content_offset = right_state.content_offset;
+                }
+                if right_state.metadata.is_some() {
+                    left_state.metadata = right_state.metadata;
+                }
             }
             // Nodes are of different type
             (left_node, right_node) => {
@@ -378,6 +495,10 @@ impl InnerNode {
                 } else {
                     // Can only happen if the type of a node has changed.
                     // If this happens, then we simply want to replace the node altogether.
+                    *n_of_nodes_delta +=
+                        right_node.get_n_of_child_nodes().unwrap_or(0) as isize
+                            - left_node.get_n_of_child_nodes().unwrap_or(0)
+                                as isize;
                     *left_node = right_node
                 }
             }
@@ -388,18 +509,19 @@ impl InnerNode {
 
     /// Changes [NodeStatus] of this node and its children (if any) to [NodeStatus::Deleted].
     pub(super) fn mark_as_deleted(&mut self, digest_idx: u8) {
+        let size = self.size();
         match self {
             InnerNode::Directory(state) => {
                 // Mark the directory itself as deleted
-                state.status = NodeStatus::Deleted;
+                state.status = NodeStatus::Deleted(size);
                 // Mark each child as 'deleted' recursively
-                for tree in state.children.values_mut() {
+                for tree in Arc::make_mut(&mut state.children).values_mut() {
                     tree.updated_in = digest_idx;
                     tree.inner.mark_as_deleted(digest_idx);
                 }
             }
             InnerNode::File(state) => {
-                state.status = NodeStatus::Deleted;
+                state.status = NodeStatus::Deleted(size);
             }
         }
     }
@@ -407,7 +529,9 @@ impl InnerNode {
     /// Returns a mutable reference to the [DirMap] of children for this [InnerNode::Directory] or [Option::None] if the node is a [InnerNode::File].
     fn children_mut(&mut self) -> Option<&mut DirMap> {
         match self {
-            InnerNode::Directory(state) => Some(&mut state.children),
+            InnerNode::Directory(state) => {
+                Some(Arc::make_mut(&mut state.children))
+            }
             _ => None,
         }
     }
@@ -0,0 +1,86 @@
+use tar::EntryType;
+
+/// Kind of a Tar entry that a [node](super::Node) was created from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    /// A regular file.
+    File,
+    /// A directory.
+    Directory,
+    /// A symbolic link.
+    Symlink,
+    /// A hard link.
+    Hardlink,
+    /// A character device.
+    CharDevice,
+    /// A block device.
+    BlockDevice,
+    /// A named pipe.
+    Fifo,
+    /// Any other entry type (e.g. a PAX or GNU extension entry).
+    Other,
+}
+
+impl NodeKind {
+    /// Returns a human-readable name of this kind.
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeKind::File => "regular file",
+            NodeKind::Directory => "directory",
+            NodeKind::Symlink => "symbolic link",
+            NodeKind::Hardlink => "hard link",
+            NodeKind::CharDevice => "character device",
+            NodeKind::BlockDevice => "block device",
+            NodeKind::Fifo => "fifo",
+            NodeKind::Other => "other",
+        }
+    }
+
+    /// Returns the file type character used by `ls -l` for this kind.
+    pub(super) fn type_char(&self) -> char {
+        match self {
+            NodeKind::Directory => 'd',
+            NodeKind::Symlink => 'l',
+            NodeKind::CharDevice => 'c',
+            NodeKind::BlockDevice => 'b',
+            NodeKind::Fifo => 'p',
+            // `ls` doesn't distinguish hardlinks from the files they point to
+            NodeKind::File | NodeKind::Hardlink => '-',
+            NodeKind::Other => '?',
+        }
+    }
+}
+
+impl From<EntryType> for NodeKind {
+    fn from(entry_type: EntryType) -> Self {
+        match entry_type {
+            EntryType::Regular
+            | EntryType::Continuous
+            | EntryType::GNUSparse => NodeKind::File,
+            EntryType::Directory => NodeKind::Directory,
+            EntryType::Symlink => NodeKind::Symlink,
+            EntryType::Link => NodeKind::Hardlink,
+            EntryType::Char => NodeKind::CharDevice,
+            EntryType::Block => NodeKind::BlockDevice,
+            EntryType::Fifo => NodeKind::Fifo,
+            _ => NodeKind::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_kind_from_entry_type() {
+        assert_eq!(NodeKind::from(EntryType::Regular), NodeKind::File);
+        assert_eq!(NodeKind::from(EntryType::Directory), NodeKind::Directory);
+        assert_eq!(NodeKind::from(EntryType::Symlink), NodeKind::Symlink);
+        assert_eq!(NodeKind::from(EntryType::Link), NodeKind::Hardlink);
+        assert_eq!(NodeKind::from(EntryType::Char), NodeKind::CharDevice);
+        assert_eq!(NodeKind::from(EntryType::Block), NodeKind::BlockDevice);
+        assert_eq!(NodeKind::from(EntryType::Fifo), NodeKind::Fifo);
+        assert_eq!(NodeKind::from(EntryType::XHeader), NodeKind::Other);
+    }
+}
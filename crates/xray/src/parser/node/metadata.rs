@@ -0,0 +1,241 @@
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use tar::Header;
+
+use super::NodeKind;
+use super::name::intern_owner_name;
+
+const SECONDS_IN_MINUTE: u64 = 60;
+const SECONDS_IN_HOUR: u64 = 60 * SECONDS_IN_MINUTE;
+const SECONDS_IN_DAY: u64 = 24 * SECONDS_IN_HOUR;
+
+const SETUID_BIT: u32 = 0o4000;
+const SETGID_BIT: u32 = 0o2000;
+const STICKY_BIT: u32 = 0o1000;
+
+/// Ownership, permissions and other metadata of a [node](super::Node) taken from its Tar header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeMetadata {
+    /// Permission bits, including the setuid, setgid and sticky bits.
+    pub mode: u32,
+    /// Numeric ID of the owner.
+    pub uid: u64,
+    /// Numeric ID of the owning group.
+    pub gid: u64,
+    /// Name of the owner if it was recorded in the archive.
+    pub uname: Option<Arc<str>>,
+    /// Name of the owning group if it was recorded in the archive.
+    pub gname: Option<Arc<str>>,
+    /// Modification time as a UNIX timestamp.
+    pub mtime: u64,
+}
+
+impl NodeMetadata {
+    /// Extracts metadata from a Tar [Header].
+    ///
+    /// Fields that are malformed in the header are zeroed instead of failing the whole entry.
+    pub fn from_header(header: &Header) -> Self {
+        NodeMetadata {
+            // Only the permission bits are interesting, the file type is stored in [NodeKind]
+            mode: header.mode().unwrap_or(0) & 0o7777,
+            uid: header.uid().unwrap_or(0),
+            gid: header.gid().unwrap_or(0),
+            uname: header
+                .username()
+                .ok()
+                .flatten()
+                .filter(|name| !name.is_empty())
+                .map(intern_owner_name),
+            gname: header
+                .groupname()
+                .ok()
+                .flatten()
+                .filter(|name| !name.is_empty())
+                .map(intern_owner_name),
+            mtime: header.mtime().unwrap_or(0),
+        }
+    }
+
+    /// Returns `true` if the setuid or the setgid bit is set.
+    pub fn is_setuid_or_setgid(&self) -> bool {
+        self.mode & (SETUID_BIT | SETGID_BIT) != 0
+    }
+
+    /// Returns the permissions of a node of the provided [NodeKind] in the same format as `ls -l` does (e.g. `-rwsr-xr-x`).
+    pub fn permissions(&self, kind: NodeKind) -> String {
+        let mut permissions = String::with_capacity(10);
+        permissions.push(kind.type_char());
+
+        for (shift, special_bit, special_char) in [
+            (6, SETUID_BIT, 's'),
+            (3, SETGID_BIT, 's'),
+            (0, STICKY_BIT, 't'),
+        ] {
+            let bits = (self.mode >> shift) & 0o7;
+            permissions.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+            permissions.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+
+            let is_executable = bits & 0o1 != 0;
+            permissions.push(
+                match (self.mode & special_bit != 0, is_executable) {
+                    (true, true) => special_char,
+                    // Uppercase means that the special bit is set without the execute bit
+                    (true, false) => special_char.to_ascii_uppercase(),
+                    (false, true) => 'x',
+                    (false, false) => '-',
+                },
+            );
+        }
+
+        permissions
+    }
+
+    /// Returns the owner in the `user:group` format, preferring names over numeric IDs when they are present.
+    pub fn owner(&self) -> String {
+        let mut owner = String::new();
+        match self.uname.as_deref() {
+            Some(uname) => owner.push_str(uname),
+            None => write!(&mut owner, "{}", self.uid).unwrap(),
+        }
+        owner.push(':');
+        match self.gname.as_deref() {
+            Some(gname) => owner.push_str(gname),
+            None => write!(&mut owner, "{}", self.gid).unwrap(),
+        }
+        owner
+    }
+
+    /// Returns the modification time formatted as `YYYY-MM-DD HH:MM:SS` in UTC.
+    pub fn modified_at(&self) -> String {
+        format_unix_timestamp(self.mtime)
+    }
+}
+
+/// Formats a UNIX timestamp as `YYYY-MM-DD HH:MM:SS` in UTC.
+fn format_unix_timestamp(timestamp: u64) -> String {
+    let days = timestamp / SECONDS_IN_DAY;
+    let seconds_of_day = timestamp % SECONDS_IN_DAY;
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        seconds_of_day / SECONDS_IN_HOUR,
+        seconds_of_day % SECONDS_IN_HOUR / SECONDS_IN_MINUTE,
+        seconds_of_day % SECONDS_IN_MINUTE,
+    )
+}
+
+/// Converts the number of days since the UNIX epoch into a `(year, month, day)` date in the proleptic Gregorian calendar.
+///
+/// Source: [chrono-Compatible Low-Level Date Algorithms](https://howardhinnant.github.io/date_algorithms.html#civil_from_days)
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // Shift the epoch to 0000-03-01, so that leap days are at the end of each 400-year era
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+        - day_of_era / 146_096)
+        / 365;
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use tar::EntryType;
+
+    use super::*;
+
+    fn make_metadata(mode: u32) -> NodeMetadata {
+        NodeMetadata {
+            mode,
+            uid: 0,
+            gid: 0,
+            uname: None,
+            gname: None,
+            mtime: 0,
+        }
+    }
+
+    #[test]
+    fn permissions_regular_file() {
+        let metadata = make_metadata(0o644);
+        assert_eq!(metadata.permissions(NodeKind::File), "-rw-r--r--");
+        assert!(!metadata.is_setuid_or_setgid());
+    }
+
+    #[test]
+    fn permissions_special_bits() {
+        let setuid = make_metadata(0o4755);
+        assert_eq!(setuid.permissions(NodeKind::File), "-rwsr-xr-x");
+        assert!(setuid.is_setuid_or_setgid());
+
+        let setgid_without_exec = make_metadata(0o2640);
+        assert_eq!(
+            setgid_without_exec.permissions(NodeKind::File),
+            "-rw-r-S---"
+        );
+        assert!(setgid_without_exec.is_setuid_or_setgid());
+
+        let sticky_dir = make_metadata(0o1777);
+        assert_eq!(sticky_dir.permissions(NodeKind::Directory), "drwxrwxrwt");
+    }
+
+    #[test]
+    fn permissions_type_chars() {
+        let metadata = make_metadata(0o777);
+        assert!(metadata.permissions(NodeKind::Symlink).starts_with('l'));
+        assert!(metadata.permissions(NodeKind::Hardlink).starts_with('-'));
+        assert!(metadata.permissions(NodeKind::CharDevice).starts_with('c'));
+        assert!(metadata.permissions(NodeKind::BlockDevice).starts_with('b'));
+        assert!(metadata.permissions(NodeKind::Fifo).starts_with('p'));
+    }
+
+    #[test]
+    fn owner_prefers_names() {
+        let mut metadata = make_metadata(0o644);
+        metadata.uid = 1000;
+        metadata.gid = 100;
+        assert_eq!(metadata.owner(), "1000:100");
+
+        metadata.uname = Some("app".into());
+        assert_eq!(metadata.owner(), "app:100");
+    }
+
+    #[test]
+    fn format_unix_timestamp_known_dates() {
+        assert_eq!(format_unix_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_unix_timestamp(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(format_unix_timestamp(1_700_000_000), "2023-11-14 22:13:20");
+    }
+
+    #[test]
+    fn metadata_from_header() {
+        let mut header = Header::new_gnu();
+        header.set_entry_type(EntryType::Regular);
+        header.set_mode(0o104755);
+        header.set_uid(0);
+        header.set_gid(42);
+        header.set_username("root").unwrap();
+        header.set_mtime(1_700_000_000);
+
+        let metadata = NodeMetadata::from_header(&header);
+        assert_eq!(metadata.mode, 0o4755);
+        assert_eq!(metadata.gid, 42);
+        assert_eq!(metadata.uname.as_deref(), Some("root"));
+        // Empty names are treated as missing
+        assert_eq!(metadata.gname, None);
+        assert_eq!(metadata.mtime, 1_700_000_000);
+    }
+}
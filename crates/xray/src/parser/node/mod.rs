@@ -1,12 +1,24 @@
+use std::path::{Component, Path};
+use std::sync::Arc;
+
 mod filter;
 mod inner_node;
 mod iter;
+mod kind;
+mod metadata;
+mod name;
 mod util;
 
 pub use filter::NodeFilters;
 pub use inner_node::InnerNode;
 use iter::TreeIter;
+pub use kind::NodeKind;
+pub use metadata::NodeMetadata;
+pub use name::NodeName;
+pub(super) use name::intern_owner_name;
 pub(super) use util::RestorablePath;
+#[cfg(test)]
+pub(crate) use util::test_util;
 
 /// A single node in a file tree.
 #[derive(Clone)]
@@ -46,10 +58,23 @@ impl Node {
     }
 
     /// Merges two [Nodes](Node).
-    pub fn merge(mut self, other: Self) -> Self {
+    pub fn merge(self, other: Self) -> Self {
+        self.merge_and_count(other).0
+    }
+
+    /// Merges two [Nodes](Node) and returns the result together with the change in the total number of nodes in the
+    /// tree.
+    ///
+    /// This allows keeping track of the tree's size without iterating over the whole tree after each merge.
+    pub fn merge_and_count(mut self, other: Self) -> (Self, isize) {
+        let mut n_of_nodes_delta = 0;
         self.updated_in = other.updated_in;
-        self.inner = self.inner.merge(other.inner, other.updated_in);
-        self
+        self.inner = self.inner.merge(
+            other.inner,
+            other.updated_in,
+            &mut n_of_nodes_delta,
+        );
+        (self, n_of_nodes_delta)
     }
 
     /// Applies the provided filter to this node.
@@ -60,6 +85,21 @@ impl Node {
         self.inner.filter(self.updated_in, filter)
     }
 
+    /// Returns the node at the provided path relative to this one or [Option::None] if there is no such node.
+    ///
+    /// Absolute paths are treated as relative to this node.
+    pub fn get(&self, path: &Path) -> Option<&Node> {
+        path.components()
+            .try_fold(self, |node, component| match component {
+                Component::Normal(name) => {
+                    node.inner.children()?.get(Path::new(name))
+                }
+                Component::RootDir | Component::CurDir => Some(node),
+                // Going up the tree is not supported, as nodes don't know their parents
+                Component::ParentDir | Component::Prefix(_) => None,
+            })
+    }
+
     /// Creates a new [iterator](TreeIter).
     pub fn iter(&self) -> TreeIter<'_> {
         TreeIter::new(self, false)
@@ -75,8 +115,7 @@ impl Node {
         self.updated_in = new_layer_idx;
         // Update the child nodes (if any)
         if let Some(state) = self.inner.dir_state_mut() {
-            state
-                .children
+            Arc::make_mut(&mut state.children)
                 .values_mut()
                 .for_each(|node| node.set_layer_recursively(new_layer_idx));
         }
@@ -109,7 +148,7 @@ mod tests {
     use std::path::Path;
 
     use super::*;
-    use crate::parser::{FileState, NodeStatus};
+    use crate::parser::{DirectoryState, FileState, NodeStatus};
 
     // --- RestorablePath ---
 
@@ -327,7 +366,7 @@ mod tests {
         right
             .insert(
                 &mut RestorablePath::new(Path::new("dir")),
-                InnerNode::File(FileState::new(NodeStatus::Deleted, None)),
+                InnerNode::File(FileState::new(NodeStatus::Deleted(0), None)),
                 1,
             )
             .unwrap();
@@ -337,6 +376,291 @@ mod tests {
         assert!(dir.inner.is_deleted());
     }
 
+    #[test]
+    fn merge_opaque_dir_deletes_lower_children() {
+        let mut left = Node::new(0);
+        left.insert(
+            &mut RestorablePath::new(Path::new("dir/old")),
+            make_file_node(10),
+            0,
+        )
+        .unwrap();
+        left.insert(
+            &mut RestorablePath::new(Path::new("dir/sub/nested")),
+            make_file_node(20),
+            0,
+        )
+        .unwrap();
+
+        let mut right = Node::new(1);
+        right
+            .insert(
+                &mut RestorablePath::new(Path::new("dir")),
+                InnerNode::new_opaque_dir(),
+                1,
+            )
+            .unwrap();
+        right
+            .insert(
+                &mut RestorablePath::new(Path::new("dir/sub/new")),
+                make_file_node(30),
+                1,
+            )
+            .unwrap();
+
+        let merged = left.merge(right);
+        let dir = &merged.inner.children().unwrap()[Path::new("dir")];
+        assert!(!dir.inner.is_deleted());
+        let children = dir.inner.children().unwrap();
+
+        let old = &children[Path::new("old")];
+        assert!(old.inner.is_deleted());
+        assert_eq!(old.updated_in, 1);
+
+        // Directories that are present in both layers are replaced wholesale
+        let sub = children[Path::new("sub")].inner.children().unwrap();
+        assert!(sub[Path::new("nested")].inner.is_deleted());
+        assert!(sub[Path::new("new")].inner.is_added());
+    }
+
+    #[test]
+    fn merge_and_count_tracks_number_of_nodes() {
+        let mut left = Node::new(0);
+        for path in ["dir/a", "dir/b", "replaced/child", "file"] {
+            left.insert(
+                &mut RestorablePath::new(Path::new(path)),
+                make_file_node(10),
+                0,
+            )
+            .unwrap();
+        }
+
+        let mut right = Node::new(1);
+        for path in ["dir/a", "dir/c", "new/nested/child", "file/child"] {
+            right
+                .insert(
+                    &mut RestorablePath::new(Path::new(path)),
+                    make_file_node(20),
+                    1,
+                )
+                .unwrap();
+        }
+        // A directory that is replaced with a file loses its children
+        right
+            .insert(
+                &mut RestorablePath::new(Path::new("replaced")),
+                make_link_node("file"),
+                1,
+            )
+            .unwrap();
+
+        let n_of_nodes = left.iter().count();
+        let (merged, n_of_nodes_delta) = left.merge_and_count(right);
+        assert_eq!(
+            n_of_nodes.checked_add_signed(n_of_nodes_delta),
+            Some(merged.iter().count())
+        );
+        assert_eq!(n_of_nodes_delta, 4);
+    }
+
+    #[test]
+    fn merge_does_not_change_shared_trees() {
+        let mut left = Node::new(0);
+        left.insert(
+            &mut RestorablePath::new(Path::new("dir/file")),
+            make_file_node(10),
+            0,
+        )
+        .unwrap();
+
+        let mut right = Node::new(1);
+        right
+            .insert(
+                &mut RestorablePath::new(Path::new("dir/file")),
+                make_file_node(20),
+                1,
+            )
+            .unwrap();
+
+        let merged = left.clone().merge(right.clone());
+        let dir = &merged.inner.children().unwrap()[Path::new("dir")];
+        assert!(
+            dir.inner.children().unwrap()[Path::new("file")]
+                .inner
+                .is_modified()
+        );
+
+        // Both layers must stay the same, as their children are shared with the merged tree
+        for (layer, size) in [(&left, 10), (&right, 20)] {
+            let dir = &layer.inner.children().unwrap()[Path::new("dir")];
+            let file = &dir.inner.children().unwrap()[Path::new("file")];
+            assert!(file.inner.is_added());
+            assert_eq!(file.inner.size(), size);
+        }
+    }
+
+    #[test]
+    fn insert_opaque_marker_keeps_existing_children() {
+        let mut root = Node::new(0);
+        root.insert(
+            &mut RestorablePath::new(Path::new("dir/file")),
+            make_file_node(10),
+            0,
+        )
+        .unwrap();
+        root.insert(
+            &mut RestorablePath::new(Path::new("dir")),
+            InnerNode::new_opaque_dir(),
+            0,
+        )
+        .unwrap();
+
+        let dir = &root.inner.children().unwrap()[Path::new("dir")];
+        assert!(
+            dir.inner
+                .children()
+                .unwrap()
+                .contains_key(Path::new("file"))
+        );
+        assert!(
+            matches!(&dir.inner, InnerNode::Directory(state) if state.opaque)
+        );
+    }
+
+    fn make_metadata(mode: u32) -> NodeMetadata {
+        NodeMetadata {
+            mode,
+            uid: 0,
+            gid: 0,
+            uname: None,
+            gname: None,
+            mtime: 0,
+        }
+    }
+
+    #[test]
+    fn insert_dir_entry_after_children_keeps_metadata() {
+        let mut root = Node::new(0);
+        root.insert(
+            &mut RestorablePath::new(Path::new("tmp/file")),
+            make_file_node(10),
+            0,
+        )
+        .unwrap();
+        root.insert(
+            &mut RestorablePath::new(Path::new("tmp")),
+            InnerNode::Directory(
+                DirectoryState::new_empty()
+                    .with_metadata(make_metadata(0o1777)),
+            ),
+            0,
+        )
+        .unwrap();
+
+        let tmp = &root.inner.children().unwrap()[Path::new("tmp")];
+        assert_eq!(tmp.inner.metadata().unwrap().mode, 0o1777);
+        assert!(
+            tmp.inner
+                .children()
+                .unwrap()
+                .contains_key(Path::new("file"))
+        );
+    }
+
+    #[test]
+    fn merge_keeps_lower_metadata_unless_overridden() {
+        let mut left = Node::new(0);
+        left.insert(
+            &mut RestorablePath::new(Path::new("dir")),
+            InnerNode::Directory(
+                DirectoryState::new_empty().with_metadata(make_metadata(0o755)),
+            ),
+            0,
+        )
+        .unwrap();
+        left.insert(
+            &mut RestorablePath::new(Path::new("dir/bin")),
+            InnerNode::File(
+                FileState::new(NodeStatus::Added(10), None)
+                    .with_metadata(make_metadata(0o755)),
+            ),
+            0,
+        )
+        .unwrap();
+
+        let mut right = Node::new(1);
+        // The directory doesn't have its own entry in the upper layer
+        right
+            .insert(
+                &mut RestorablePath::new(Path::new("dir/bin")),
+                InnerNode::File(
+                    FileState::new(NodeStatus::Added(10), None)
+                        .with_metadata(make_metadata(0o4755)),
+                ),
+                1,
+            )
+            .unwrap();
+
+        let merged = left.merge(right);
+        let dir = &merged.inner.children().unwrap()[Path::new("dir")];
+        assert_eq!(dir.inner.metadata().unwrap().mode, 0o755);
+        let bin = &dir.inner.children().unwrap()[Path::new("bin")];
+        assert!(bin.inner.metadata().unwrap().is_setuid_or_setgid());
+    }
+
+    #[test]
+    fn merge_updates_kind_and_link_target() {
+        let mut left = Node::new(0);
+        left.insert(
+            &mut RestorablePath::new(Path::new("bin/sh")),
+            make_file_node(10),
+            0,
+        )
+        .unwrap();
+        left.insert(
+            &mut RestorablePath::new(Path::new("dev/null")),
+            InnerNode::File(
+                FileState::new(NodeStatus::Added(0), None)
+                    .with_kind(NodeKind::CharDevice),
+            ),
+            0,
+        )
+        .unwrap();
+
+        let mut right = Node::new(1);
+        right
+            .insert(
+                &mut RestorablePath::new(Path::new("bin/sh")),
+                InnerNode::File(
+                    FileState::new(NodeStatus::Added(0), Some("bash".into()))
+                        .with_kind(NodeKind::Hardlink),
+                ),
+                1,
+            )
+            .unwrap();
+        right
+            .insert(
+                &mut RestorablePath::new(Path::new("dev/null")),
+                InnerNode::File(FileState::new(NodeStatus::Deleted(0), None)),
+                1,
+            )
+            .unwrap();
+
+        let merged = left.merge(right);
+        let children = merged.inner.children().unwrap();
+
+        let bin = children[Path::new("bin")].inner.children().unwrap();
+        let sh = &bin[Path::new("sh")].inner;
+        assert_eq!(sh.kind(), NodeKind::Hardlink);
+        assert_eq!(sh.get_link(), Some(Path::new("bash")));
+
+        // Whiteouts keep the last known kind of the deleted node
+        let dev = children[Path::new("dev")].inner.children().unwrap();
+        let null = &dev[Path::new("null")].inner;
+        assert!(null.is_deleted());
+        assert_eq!(null.kind(), NodeKind::CharDevice);
+    }
+
     // --- InnerNode mark_as_deleted ---
 
     #[test]
@@ -402,6 +726,30 @@ mod tests {
         assert!(file.get_link().is_none());
     }
 
+    #[test]
+    fn get_node_by_path() {
+        let mut root = Node::new(0);
+        root.insert(
+            &mut RestorablePath::new(Path::new("usr/bin/tool")),
+            make_file_node(7),
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(
+            root.get(Path::new("usr/bin/tool")).unwrap().inner.size(),
+            7
+        );
+        assert_eq!(
+            root.get(Path::new("/usr/./bin/tool")).unwrap().inner.size(),
+            7
+        );
+        assert!(root.get(Path::new("usr/bin")).unwrap().inner.is_dir());
+        assert!(root.get(Path::new("usr/bin/missing")).is_none());
+        assert!(root.get(Path::new("usr/bin/tool/child")).is_none());
+        assert!(root.get(Path::new("usr/../usr/bin")).is_none());
+    }
+
     // --- TreeIter ---
 
     #[test]
@@ -0,0 +1,161 @@
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, Mutex, PoisonError};
+
+/// Number of independently locked shards in an [Interner].
+const INTERNER_SHARDS: usize = 64;
+
+/// All [node names](NodeName) that were created so far.
+///
+/// Interned values are never freed, as file trees usually live as long as the app itself.
+static NODE_NAMES: LazyLock<Interner<Path>> = LazyLock::new(Interner::new);
+/// All owner and group names from [NodeMetadata](super::NodeMetadata) that were created so far.
+static OWNER_NAMES: LazyLock<Interner<str>> = LazyLock::new(Interner::new);
+
+/// An interned name of a single [node](super::Node) within its parent directory.
+///
+/// The same file names repeat a lot across directories and layers (e.g. `bin`, `__init__.py`), so each unique name is
+/// only stored once and is shared between all file trees.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeName(Arc<Path>);
+
+impl NodeName {
+    pub fn new(name: &Path) -> Self {
+        NodeName(NODE_NAMES.intern(name))
+    }
+}
+
+impl From<&Path> for NodeName {
+    fn from(name: &Path) -> Self {
+        NodeName::new(name)
+    }
+}
+
+impl Deref for NodeName {
+    type Target = Path;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for NodeName {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+// `Ord` and `Hash` are derived from `Path`, so the names can be looked up using paths
+impl Borrow<Path> for NodeName {
+    fn borrow(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl PartialEq<Path> for NodeName {
+    fn eq(&self, other: &Path) -> bool {
+        *self.0 == *other
+    }
+}
+
+impl std::fmt::Debug for NodeName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Returns the interned version of the provided owner or group name.
+pub fn intern_owner_name(name: &str) -> Arc<str> {
+    OWNER_NAMES.intern(name)
+}
+
+/// A set of interned values that is split into shards by the values' hashes.
+///
+/// Layers are parsed in parallel, and each shard is locked separately, so that the parser threads rarely wait for each
+/// other.
+struct Interner<T: ?Sized> {
+    shards: [Mutex<HashSet<Arc<T>>>; INTERNER_SHARDS],
+}
+
+impl<T> Interner<T>
+where
+    T: Eq + Hash + ?Sized,
+    for<'a> Arc<T>: From<&'a T>,
+{
+    fn new() -> Self {
+        Interner {
+            shards: std::array::from_fn(|_| Mutex::default()),
+        }
+    }
+
+    /// Returns the interned version of the provided value, interning it if it wasn't seen before.
+    fn intern(&self, value: &T) -> Arc<T> {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let shard = &self.shards[hasher.finish() as usize % INTERNER_SHARDS];
+
+        // The set stays valid even if some other thread panicked while holding the lock
+        let mut interned = shard.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(value) = interned.get(value) {
+            return value.clone();
+        }
+
+        let value = Arc::from(value);
+        interned.insert(Arc::clone(&value));
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn equal_names_share_allocation() {
+        let name = NodeName::new(Path::new("__init__.py"));
+        // A separately allocated path with the same name
+        let other = NodeName::new(&PathBuf::from("__init__.py"));
+        assert!(Arc::ptr_eq(&name.0, &other.0));
+        assert!(!Arc::ptr_eq(&name.0, &NodeName::new(Path::new("bin")).0));
+
+        let owner = intern_owner_name("root");
+        assert!(Arc::ptr_eq(
+            &owner,
+            &intern_owner_name(&String::from("root"))
+        ));
+    }
+
+    #[test]
+    fn names_interned_concurrently_share_allocation() {
+        let names = std::thread::scope(|scope| {
+            let threads = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        (0..256)
+                            .map(|idx| {
+                                NodeName::new(Path::new(&format!(
+                                    "concurrent-{idx}"
+                                )))
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        for thread_names in names.iter().skip(1) {
+            for (name, other) in names[0].iter().zip(thread_names) {
+                assert!(Arc::ptr_eq(&name.0, &other.0));
+            }
+        }
+    }
+}
@@ -55,3 +55,186 @@ impl<'a> RestorablePath<'a> {
         self.is_using_relative_path
     }
 }
+
+/// Helpers for building file trees and images in tests.
+#[cfg(test)]
+pub mod test_util {
+    use std::io::Write as _;
+    use std::path::Path;
+
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use indexmap::IndexMap;
+    use tar::EntryType;
+    use tempfile::NamedTempFile;
+
+    use super::RestorablePath;
+    // Allows building arbitrary nodes outside of the parser
+    pub use crate::parser::node::InnerNode;
+    use crate::parser::{
+        BlobType, Image, Layer, LayerBlob, LayerChangeSet, Parser,
+    };
+    pub use crate::parser::{FileState, NodeStatus};
+
+    /// A single entry of a layer that is built by [make_layer].
+    pub enum Entry {
+        /// A regular file with the provided size.
+        File(&'static str, u64),
+        /// A whiteout that deletes the path from the lower layers.
+        Whiteout(&'static str),
+        /// A directory that hides everything the lower layers put into it.
+        OpaqueDir(&'static str),
+        /// An arbitrary node.
+        Node(&'static str, InnerNode),
+    }
+
+    /// A single entry of a tar blob that is built by [make_tar].
+    pub enum TarEntry<'a> {
+        /// A directory with the provided mode.
+        Dir(&'a str, u32),
+        /// A regular file with the provided mode and contents.
+        File(&'a str, u32, &'a [u8]),
+        /// A symlink to the provided target.
+        Symlink(&'a str, &'a str),
+        /// A hard link to the provided path.
+        Hardlink(&'a str, &'a str),
+    }
+
+    /// Builds an uncompressed tar blob from its entries.
+    pub fn make_tar(entries: &[TarEntry<'_>]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for entry in entries {
+            let mut header = tar::Header::new_gnu();
+            match entry {
+                TarEntry::Dir(path, mode) => {
+                    header.set_entry_type(EntryType::Directory);
+                    header.set_mode(*mode);
+                    header.set_size(0);
+                    builder.append_data(&mut header, path, &[][..]).unwrap();
+                }
+                TarEntry::File(path, mode, contents) => {
+                    header.set_mode(*mode);
+                    header.set_size(contents.len() as u64);
+                    builder.append_data(&mut header, path, *contents).unwrap();
+                }
+                TarEntry::Symlink(path, target) => {
+                    header.set_entry_type(EntryType::Symlink);
+                    header.set_mode(0o777);
+                    header.set_size(0);
+                    builder.append_link(&mut header, path, target).unwrap();
+                }
+                TarEntry::Hardlink(path, target) => {
+                    header.set_entry_type(EntryType::Link);
+                    header.set_mode(0o644);
+                    header.set_size(0);
+                    builder.append_link(&mut header, path, target).unwrap();
+                }
+            }
+        }
+        builder.into_inner().unwrap()
+    }
+
+    /// Compresses the provided blob with gzip.
+    pub fn gzip(blob: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(blob).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// Builds a [LayerChangeSet] with the provided index from its entries.
+    pub fn make_layer(idx: u8, entries: &[Entry]) -> LayerChangeSet {
+        let mut changeset = LayerChangeSet::new(0);
+        for entry in entries {
+            let (path, node) = match entry {
+                Entry::File(path, size) => (
+                    path,
+                    InnerNode::File(FileState::new(
+                        NodeStatus::Added(*size),
+                        None,
+                    )),
+                ),
+                Entry::Whiteout(path) => (
+                    path,
+                    InnerNode::File(FileState::new(
+                        NodeStatus::Deleted(0),
+                        None,
+                    )),
+                ),
+                Entry::OpaqueDir(path) => (path, InnerNode::new_opaque_dir()),
+                Entry::Node(path, node) => (path, node.clone()),
+            };
+            changeset
+                .insert(&mut RestorablePath::new(Path::new(path)), node, 0)
+                .unwrap();
+        }
+        changeset.set_layer_recursively(idx);
+        changeset
+    }
+
+    /// Builds a [Layer] with the provided index by parsing an uncompressed tar blob, so that its files can be read.
+    ///
+    /// The blob is stored in the returned file, which must be kept around as long as the layer is used.
+    pub fn make_layer_from_tar(idx: u8, blob: &[u8]) -> (Layer, NamedTempFile) {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(blob).unwrap();
+
+        let (mut changeset, ..) =
+            Parser::new().parse_layer_blob(BlobType::Tar, blob).unwrap();
+        changeset.set_layer_recursively(idx);
+        let layer = Layer {
+            changeset: Some(changeset),
+            size: blob.len() as u64,
+            created_by: format!("layer {idx}"),
+            comment: None,
+            diff_id: None,
+            history_mismatch: false,
+            verification_errors: None,
+            blob: Some(LayerBlob::in_file(file.path().into(), BlobType::Tar)),
+        };
+        (layer, file)
+    }
+
+    /// Builds an [Image] with the `latest` tag from its layers.
+    ///
+    /// Each layer is identified by a digest that consists of the provided byte, and its size is the total size of its
+    /// files.
+    pub fn make_image(name: &'static str, layers: &[(u8, &[Entry])]) -> Image {
+        let layers = layers
+            .iter()
+            .enumerate()
+            .map(|(idx, (digest, entries))| {
+                let size = entries
+                    .iter()
+                    .map(|entry| match entry {
+                        Entry::File(_, size) => *size,
+                        Entry::Node(_, node) => node.size(),
+                        _ => 0,
+                    })
+                    .sum();
+                (
+                    [*digest; 32],
+                    Layer {
+                        changeset: Some(make_layer(idx as u8, entries)),
+                        size,
+                        created_by: format!("layer {digest}"),
+                        comment: None,
+                        diff_id: None,
+                        history_mismatch: false,
+                        verification_errors: None,
+                        blob: None,
+                    },
+                )
+            })
+            .collect::<IndexMap<_, _>>();
+
+        Image {
+            image_name: name.into(),
+            tag: "latest".into(),
+            size: layers.values().map(|layer| layer.size).sum(),
+            total_layers: layers.len(),
+            non_empty_layers: layers.len(),
+            layers,
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,332 @@
+//! Contains the logic related to parsing the image's layers in parallel.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Context;
+
+use super::util::sha256_digest_to_hex;
+use super::{
+    BlobType, Image, ImageOpener, ParsedLayerBlob, Parser, ReadSeek,
+    Sha256Digest,
+};
+
+/// A layer blob that was found while reading the image, but was left to be parsed later.
+pub(super) struct DeferredBlob {
+    pub digest: Sha256Digest,
+    pub blob_type: BlobType,
+    /// Absolute position of the blob's contents within the image.
+    pub offset: u64,
+    /// Size of the blob in bytes.
+    pub size: u64,
+}
+
+impl Parser {
+    /// Parses an OCI-compliant container image from an image Tar blob, parsing its layers using
+    /// [several threads](Self::with_threads).
+    ///
+    /// Each thread reads the layers using its own reader, so `open` must return a new independent reader over the same
+    /// image each time it's called. `open` is also kept in the returned [Image] to read the contents of the layers'
+    /// files later (check [LayerBlob](super::LayerBlob)).
+    pub fn parse_image_concurrently<R, F>(
+        mut self,
+        open: F,
+    ) -> anyhow::Result<Image>
+    where
+        R: Read + Seek + 'static,
+        F: Fn() -> std::io::Result<R> + Send + Sync + 'static,
+    {
+        let open: ImageOpener = Arc::new(move || {
+            open().map(|src| Box::new(src) as Box<dyn ReadSeek>)
+        });
+        self.image_opener = Some(open.clone());
+
+        let src = open().context("failed to open the image")?;
+        let threads = self.threads();
+        let deferred_blobs = self.scan_image(src, threads.get() > 1)?;
+
+        let parsed_blobs = run_in_parallel(&deferred_blobs, threads, |blob| {
+            self.read_deferred_blob(blob, &open)
+        });
+        // Merge the layers in the order they were found in to keep the results deterministic
+        for (blob, parsed) in deferred_blobs.iter().zip(parsed_blobs) {
+            self.insert_layer_blob(blob.digest, parsed?);
+        }
+
+        self.finalize()
+    }
+
+    /// Returns the number of threads that can be used to parse the layers.
+    pub(super) fn threads(&self) -> NonZeroUsize {
+        self.threads.unwrap_or(NonZeroUsize::MIN)
+    }
+
+    /// Parses a single [DeferredBlob] using a new reader.
+    fn read_deferred_blob(
+        &self,
+        blob: &DeferredBlob,
+        open: &ImageOpener,
+    ) -> anyhow::Result<ParsedLayerBlob> {
+        let mut src = open().context("failed to open the image")?;
+        src.seek(SeekFrom::Start(blob.offset))
+            .context("failed to seek to a layer blob")?;
+
+        self.read_layer_blob(&blob.digest, blob.blob_type, src.take(blob.size))
+            .with_context(|| {
+                format!(
+                    "failed to parse layer {}",
+                    sha256_digest_to_hex(&blob.digest)
+                )
+            })
+    }
+}
+
+/// Runs the provided job for each item using up to `threads` threads.
+///
+/// Items are picked up by the threads one by one, so a single large item doesn't hold up the rest of them.
+/// Results are returned in the same order as the items.
+pub(super) fn run_in_parallel<T, R, F>(
+    items: &[T],
+    threads: NonZeroUsize,
+    job: F,
+) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let threads = threads.get().min(items.len());
+    if threads <= 1 {
+        return items.iter().map(job).collect();
+    }
+
+    let next_item_idx = AtomicUsize::new(0);
+    let mut results = std::thread::scope(|scope| {
+        let workers = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let idx = next_item_idx.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(idx) else {
+                            break results;
+                        };
+                        results.push((idx, job(item)));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect::<Vec<_>>()
+    });
+
+    results.sort_unstable_by_key(|(idx, _)| *idx);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::path::{Path, PathBuf};
+
+    use serde_json::json;
+    use sha2::{Digest as _, Sha256};
+
+    use super::*;
+    use crate::parser::test_util::{TarEntry, gzip, make_tar};
+
+    #[test]
+    fn run_in_parallel_keeps_order() {
+        let items = (0..100).collect::<Vec<u64>>();
+        for threads in [1, 4, 200] {
+            let results = run_in_parallel(
+                &items,
+                NonZeroUsize::new(threads).unwrap(),
+                |item| item * 2,
+            );
+            assert_eq!(
+                results,
+                items.iter().map(|item| item * 2).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn run_in_parallel_without_items() {
+        let results =
+            run_in_parallel(&[] as &[u8], NonZeroUsize::MIN, |item| *item);
+        assert!(results.is_empty());
+    }
+
+    // --- parse_image_concurrently ---
+
+    /// Builds an OCI image tar with an uncompressed layer followed by a gzipped one.
+    ///
+    /// The gzipped layer is found after the parser has already parsed the uncompressed one in place, so its position
+    /// depends on the offset of the rebuilt archive.
+    fn make_oci_image() -> Vec<u8> {
+        let mut blobs = Vec::new();
+        let mut add_blob = |blob: Vec<u8>| {
+            let digest = format!(
+                "sha256:{}",
+                sha256_digest_to_hex(&Sha256::digest(&blob).into())
+            );
+            blobs.push((digest.clone(), blob));
+            digest
+        };
+
+        let first_layer = add_blob(make_tar(&[
+            TarEntry::File("etc/hostname", 0o644, b"first\n"),
+            TarEntry::File("etc/os-release", 0o644, b"ID=test\n"),
+        ]));
+        let second_layer = add_blob(gzip(&make_tar(&[
+            TarEntry::File("etc/hostname", 0o644, b"second\n"),
+            TarEntry::File("app/data", 0o600, &[0, 1, 2, 3, 0xff]),
+        ])));
+        let config = add_blob(
+            serde_json::to_vec(&json!({
+                "architecture": "amd64",
+                "os": "linux",
+                "history": [
+                    { "created_by": "COPY etc /etc" },
+                    { "created_by": "COPY app /app" },
+                ],
+            }))
+            .unwrap(),
+        );
+        let manifest = add_blob(
+            serde_json::to_vec(&json!({
+                "config": { "digest": config },
+                "layers": [
+                    {
+                        "mediaType": "application/vnd.oci.image.layer.v1.tar",
+                        "digest": first_layer,
+                    },
+                    {
+                        "mediaType": "application/vnd.oci.image.layer.v1.tar+gzip",
+                        "digest": second_layer,
+                    },
+                ],
+            }))
+            .unwrap(),
+        );
+        let index = serde_json::to_vec(&json!({
+            "manifests": [{ "digest": manifest }],
+        }))
+        .unwrap();
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut append = |path: &str, contents: &[u8]| {
+            let mut header = tar::Header::new_gnu();
+            header.set_mode(0o644);
+            header.set_size(contents.len() as u64);
+            builder.append_data(&mut header, path, contents).unwrap();
+        };
+        append("oci-layout", br#"{"imageLayoutVersion": "1.0.0"}"#);
+        append("index.json", &index);
+        for (digest, blob) in &blobs {
+            append(
+                &digest.replace(':', "/").replace("sha256", "blobs/sha256"),
+                blob,
+            );
+        }
+        builder.into_inner().unwrap()
+    }
+
+    /// Path, layer index, size and content offset of a node.
+    type NodeSummary = (PathBuf, u8, u64, Option<u64>);
+
+    /// Returns the nodes of each layer in a comparable form.
+    fn layer_nodes(image: &Image) -> Vec<Vec<NodeSummary>> {
+        image
+            .layers
+            .values()
+            .map(|layer| {
+                layer
+                    .changeset
+                    .as_ref()
+                    .unwrap()
+                    .iter()
+                    .map(|(path, node, ..)| {
+                        (
+                            path.to_owned(),
+                            node.updated_in,
+                            node.inner.size(),
+                            node.inner.content_offset(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn read_file(image: &Image, layer_idx: usize, path: &str) -> Vec<u8> {
+        let (_, layer) = image.layers.get_index(layer_idx).unwrap();
+        let node = layer
+            .changeset
+            .as_ref()
+            .unwrap()
+            .get(Path::new(path))
+            .unwrap();
+        layer
+            .blob
+            .as_ref()
+            .unwrap()
+            .read_file(
+                node.inner.content_offset().unwrap(),
+                node.inner.size(),
+                u64::MAX,
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_image_concurrently_with_deferred_blobs() {
+        let image: Arc<[u8]> = make_oci_image().into();
+        for verify in [false, true] {
+            let sequential = {
+                let image = image.clone();
+                Parser::new()
+                    .with_verification(verify)
+                    .parse_image_concurrently(move || {
+                        Ok(Cursor::new(image.clone()))
+                    })
+                    .unwrap()
+            };
+            let concurrent = {
+                let image = image.clone();
+                Parser::new()
+                    .with_verification(verify)
+                    .with_threads(NonZeroUsize::new(4).unwrap())
+                    .parse_image_concurrently(move || {
+                        Ok(Cursor::new(image.clone()))
+                    })
+                    .unwrap()
+            };
+            let in_memory = Parser::new()
+                .with_verification(verify)
+                .parse_image(Cursor::new(image.clone()))
+                .unwrap();
+
+            assert_eq!(concurrent.layers.len(), 2);
+            assert_eq!(layer_nodes(&sequential), layer_nodes(&concurrent));
+            assert_eq!(layer_nodes(&in_memory), layer_nodes(&concurrent));
+
+            for image in [&sequential, &concurrent] {
+                assert_eq!(read_file(image, 0, "etc/hostname"), b"first\n");
+                assert_eq!(read_file(image, 0, "etc/os-release"), b"ID=test\n");
+                assert_eq!(read_file(image, 1, "etc/hostname"), b"second\n");
+                assert_eq!(read_file(image, 1, "app/data"), [0, 1, 2, 3, 0xff]);
+            }
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! Contains the logic related to selecting an image for a specific platform from multi-platform images.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::Context as _;
+use serde::Deserialize;
+
+const UNKNOWN_PLATFORM_COMPONENT: &str = "unknown";
+
+/// A platform that an image was built for.
+///
+/// Source: [OCI Image Index Specification](https://github.com/opencontainers/image-spec/blob/main/image-index.md#image-index-property-descriptions)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Platform {
+    os: String,
+    architecture: String,
+    variant: Option<String>,
+}
+
+impl Platform {
+    pub fn new(os: &str, architecture: &str, variant: Option<&str>) -> Self {
+        Platform {
+            os: os.to_owned(),
+            architecture: architecture.to_owned(),
+            variant: variant.map(ToOwned::to_owned),
+        }
+    }
+
+    /// Returns the platform of the current host using the architecture names from the
+    /// [Go Language Document](https://go.dev/doc/install/source#environment), which are used by the OCI spec.
+    ///
+    /// The OS is always `linux`, as that's what the container runtimes use by default on all hosts.
+    pub fn host() -> Self {
+        let architecture = match std::env::consts::ARCH {
+            "x86_64" => "amd64",
+            "x86" => "386",
+            "aarch64" => "arm64",
+            "powerpc64" if cfg!(target_endian = "little") => "ppc64le",
+            "powerpc64" => "ppc64",
+            "loongarch64" => "loong64",
+            other => other,
+        };
+
+        Platform::new("linux", architecture, None)
+    }
+
+    /// Checks if an image built for the `other` platform can be used when this platform is requested.
+    ///
+    /// A missing variant matches any variant.
+    pub fn matches(&self, other: &Platform) -> bool {
+        self.os == other.os
+            && self.architecture == other.architecture
+            && self
+                .variant
+                .as_ref()
+                .is_none_or(|variant| other.variant.as_ref() == Some(variant))
+    }
+
+    /// Returns `true` if this platform is `unknown/unknown`.
+    ///
+    /// Such platforms are used by BuildKit for attestation manifests, which don't contain any image layers.
+    pub fn is_unknown(&self) -> bool {
+        self.os == UNKNOWN_PLATFORM_COMPONENT
+            && self.architecture == UNKNOWN_PLATFORM_COMPONENT
+    }
+}
+
+impl FromStr for Platform {
+    type Err = anyhow::Error;
+
+    /// Parses a platform in the `os/arch[/variant]` format.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut components = s.split('/');
+        let (Some(os), Some(architecture)) =
+            (components.next(), components.next())
+        else {
+            anyhow::bail!(
+                "expected a platform in the 'os/arch[/variant]' format, got '{}'",
+                s
+            )
+        };
+        let variant = components.next();
+
+        if os.is_empty()
+            || architecture.is_empty()
+            || variant.is_some_and(str::is_empty)
+            || components.next().is_some()
+        {
+            anyhow::bail!(
+                "expected a platform in the 'os/arch[/variant]' format, got '{}'",
+                s
+            )
+        }
+
+        Ok(Platform::new(os, architecture, variant))
+    }
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.os, self.architecture)?;
+        if let Some(variant) = self.variant.as_ref() {
+            write!(f, "/{}", variant)?;
+        }
+        Ok(())
+    }
+}
+
+/// Selects a platform from the `available` ones.
+///
+/// Uses the `requested` platform if it's provided. Otherwise, uses the only available platform if there is
+/// just one of them, or falls back to the [host platform](Platform::host).
+///
+/// Returns an index of the selected platform.
+pub(super) fn select_platform(
+    requested: Option<&Platform>,
+    available: &[Platform],
+) -> anyhow::Result<usize> {
+    let host_platform;
+    let requested = match requested {
+        Some(requested) => requested,
+        None if available.len() == 1 => return Ok(0),
+        None => {
+            host_platform = Platform::host();
+            &host_platform
+        }
+    };
+
+    available
+        .iter()
+        .position(|platform| requested.matches(platform))
+        .with_context(|| {
+            let available = available
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!(
+                "the image doesn't support the '{}' platform, available platforms: {}",
+                requested, available
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // --- Platform parsing ---
+
+    #[test]
+    fn platform_from_str() {
+        let platform: Platform = "linux/arm64".parse().unwrap();
+        assert_eq!(platform, Platform::new("linux", "arm64", None));
+
+        let platform: Platform = "linux/arm/v7".parse().unwrap();
+        assert_eq!(platform, Platform::new("linux", "arm", Some("v7")));
+        assert_eq!(platform.to_string(), "linux/arm/v7");
+    }
+
+    #[test]
+    fn platform_from_str_invalid() {
+        for invalid in ["linux", "linux/", "/amd64", "linux/arm/", "a/b/c/d"] {
+            assert!(invalid.parse::<Platform>().is_err(), "{invalid}");
+        }
+    }
+
+    // --- Platform matching ---
+
+    #[test]
+    fn platform_without_variant_matches_any_variant() {
+        let requested = Platform::new("linux", "arm64", None);
+        assert!(requested.matches(&Platform::new(
+            "linux",
+            "arm64",
+            Some("v8")
+        )));
+        assert!(requested.matches(&Platform::new("linux", "arm64", None)));
+        assert!(!requested.matches(&Platform::new("linux", "amd64", None)));
+
+        let requested = Platform::new("linux", "arm", Some("v7"));
+        assert!(!requested.matches(&Platform::new("linux", "arm", Some("v6"))));
+        assert!(!requested.matches(&Platform::new("linux", "arm", None)));
+    }
+
+    // --- select_platform ---
+
+    #[test]
+    fn select_requested_platform() {
+        let available = [
+            Platform::new("linux", "amd64", None),
+            Platform::new("linux", "arm64", Some("v8")),
+        ];
+        let requested = Platform::new("linux", "arm64", None);
+        assert_eq!(select_platform(Some(&requested), &available).unwrap(), 1);
+    }
+
+    #[test]
+    fn select_single_platform_by_default() {
+        let available = [Platform::new("linux", "s390x", None)];
+        assert_eq!(select_platform(None, &available).unwrap(), 0);
+
+        // An explicitly requested platform must match though
+        let requested = Platform::new("linux", "amd64", None);
+        assert!(select_platform(Some(&requested), &available).is_err());
+    }
+
+    #[test]
+    fn select_missing_platform_lists_available_ones() {
+        let available = [
+            Platform::new("linux", "amd64", None),
+            Platform::new("linux", "arm", Some("v7")),
+        ];
+        let requested = Platform::new("windows", "amd64", None);
+        let error = select_platform(Some(&requested), &available)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("windows/amd64"));
+        assert!(error.contains("linux/amd64, linux/arm/v7"));
+    }
+}
@@ -0,0 +1,434 @@
+//! Contains the logic related to parsing images that are stored remotely (e.g. in a registry) blob by blob.
+
+use std::io::{BufReader, Read};
+use std::sync::Arc;
+
+use anyhow::Context;
+use sha2::{Digest as _, Sha256};
+
+use super::constants::TAR_BLOCK_SIZE;
+use super::json::{ImageIndex, JsonBlob};
+use super::parallel::run_in_parallel;
+use super::platform::select_platform;
+use super::util::{determine_blob_type, sha256_digest_to_hex};
+use super::{
+    BlobType, Image, LayerBlob, ParsedLayerBlob, Parser, Sha256Digest,
+};
+
+/// A remote image that provides its manifests and blobs by their digests.
+pub trait RemoteImage: Send + Sync {
+    /// Fetches the manifest (an Image Manifest or an Image Index) with the provided digest.
+    fn fetch_manifest(&self, digest: &Sha256Digest) -> anyhow::Result<Vec<u8>>;
+
+    /// Starts fetching the blob with the provided digest, streaming its contents from the returned reader.
+    fn fetch_blob(
+        &self,
+        digest: &Sha256Digest,
+    ) -> anyhow::Result<Box<dyn Read + Send>>;
+}
+
+/// A JSON blob that needs to be fetched from the remote image.
+///
+/// Registries serve manifests and other blobs from different endpoints.
+enum PendingBlob {
+    Manifest(Sha256Digest),
+    Config(Sha256Digest),
+}
+
+impl PendingBlob {
+    fn digest(&self) -> &Sha256Digest {
+        match self {
+            PendingBlob::Manifest(digest) | PendingBlob::Config(digest) => {
+                digest
+            }
+        }
+    }
+}
+
+impl Parser {
+    /// Parses an OCI-compliant container image from a remote source, starting from its root manifest (an Image
+    /// Manifest or an Image Index).
+    ///
+    /// Only the manifest for the [selected platform](Self::with_platform) is fetched from multi-platform images, and the
+    /// layers are streamed into the parser without storing them. The returned [Image] fetches the layer blobs again to
+    /// read the contents of their files (check [LayerBlob]).
+    pub fn parse_remote_image(
+        mut self,
+        root_manifest: &[u8],
+        remote: Arc<dyn RemoteImage>,
+    ) -> anyhow::Result<Image> {
+        let root_digest: Sha256Digest = Sha256::digest(root_manifest).into();
+        let root_manifest = self
+            .parse_json_blob::<JsonBlob>(&mut &root_manifest[..])?
+            .context("the root manifest is neither an index nor a manifest")?;
+
+        let mut pending = Vec::new();
+        match root_manifest {
+            JsonBlob::Index(index) => {
+                pending.extend(
+                    self.remote_index_entries(&index)?
+                        .into_iter()
+                        .map(PendingBlob::Manifest),
+                );
+                self.process_image_index(index);
+            }
+            JsonBlob::Manifest(manifest) => {
+                pending.push(PendingBlob::Config(manifest.config.digest));
+                self.process_json_blob(
+                    root_digest,
+                    JsonBlob::Manifest(manifest),
+                );
+            }
+            JsonBlob::Config(_) => {
+                anyhow::bail!("the root manifest is an image config")
+            }
+        }
+
+        // Load the indexes, manifests and configs of the selected platform
+        while let Some(pending_blob) = pending.pop() {
+            let digest = *pending_blob.digest();
+            if self.indexes.contains_key(&digest)
+                || self.manifests.contains_key(&digest)
+                || self.configs.contains_key(&digest)
+            {
+                continue;
+            }
+
+            let blob = match pending_blob {
+                PendingBlob::Manifest(digest) => {
+                    remote.fetch_manifest(&digest)?
+                }
+                PendingBlob::Config(digest) => {
+                    let mut blob = Vec::new();
+                    remote
+                        .fetch_blob(&digest)?
+                        .read_to_end(&mut blob)
+                        .context("failed to download an image config")?;
+                    blob
+                }
+            };
+            let Some(json_blob) =
+                self.parse_json_blob::<JsonBlob>(&mut blob.as_slice())?
+            else {
+                continue;
+            };
+
+            match &json_blob {
+                JsonBlob::Index(index) => pending.extend(
+                    self.remote_index_entries(index)?
+                        .into_iter()
+                        .map(PendingBlob::Manifest),
+                ),
+                JsonBlob::Manifest(manifest) => {
+                    pending.push(PendingBlob::Config(manifest.config.digest))
+                }
+                JsonBlob::Config(_) => {}
+            }
+            self.process_json_blob(digest, json_blob);
+        }
+
+        let (layer_configs, config) = self
+            .resolve_image_manifest()
+            .context("failed to find the image manifest")?;
+
+        let mut digests = Vec::with_capacity(layer_configs.len());
+        for layer_config in layer_configs.iter() {
+            // The same layer can be used several times within an image
+            if !digests.contains(&layer_config.digest) {
+                digests.push(layer_config.digest);
+            }
+        }
+
+        let parsed_layers =
+            run_in_parallel(&digests, self.threads(), |digest| {
+                self.read_remote_layer(&remote, digest)
+            });
+        // Merge the layers in the order they are used in to keep the results deterministic
+        for (digest, parsed) in digests.into_iter().zip(parsed_layers) {
+            if let Some((parsed, layer_blob)) =
+                parsed.context("failed to parse a remote layer")?
+            {
+                self.insert_layer_blob(digest, parsed);
+                self.layer_blobs.insert(digest, layer_blob);
+            }
+        }
+
+        self.build_image(layer_configs, config)
+    }
+
+    /// Returns the digests of the manifests from the provided Image Index that need to be fetched.
+    ///
+    /// If all manifests specify their platforms, only the one for the selected platform is returned, as fetching the
+    /// rest is a waste of requests. Otherwise, all of them are returned, as some of them can be nested indexes.
+    fn remote_index_entries(
+        &self,
+        index: &ImageIndex,
+    ) -> anyhow::Result<Vec<Sha256Digest>> {
+        let all_entries = || {
+            index
+                .manifests
+                .iter()
+                .map(|manifest| manifest.digest)
+                .collect()
+        };
+        if index
+            .manifests
+            .iter()
+            .any(|manifest| manifest.platform.is_none())
+        {
+            return Ok(all_entries());
+        }
+
+        // Skip the manifests that are not tied to any platform (e.g. attestations)
+        let (digests, platforms): (Vec<_>, Vec<_>) = index
+            .manifests
+            .iter()
+            .filter_map(|manifest| {
+                let platform = manifest.platform.clone()?;
+                (!platform.is_unknown()).then_some((manifest.digest, platform))
+            })
+            .unzip();
+        if platforms.is_empty() {
+            return Ok(all_entries());
+        }
+
+        let selected = select_platform(self.platform.as_ref(), &platforms)?;
+        Ok(vec![digests[selected]])
+    }
+
+    /// Fetches and parses a single layer blob from the remote image.
+    ///
+    /// Returns `None` if the layer is not a Tar blob.
+    fn read_remote_layer(
+        &self,
+        remote: &Arc<dyn RemoteImage>,
+        digest: &Sha256Digest,
+    ) -> anyhow::Result<Option<(ParsedLayerBlob, LayerBlob)>> {
+        // Avoid downloading the layer at all if it's already cached
+        if let Some(&blob_type) = self.layer_blob_types.get(digest)
+            && let Some(layer) = self
+                .layer_cache(digest, blob_type)
+                .and_then(|cache| cache.get(digest))
+        {
+            return Ok(Some((
+                ParsedLayerBlob {
+                    layer: Some(layer),
+                    digests: None,
+                },
+                LayerBlob::remote(remote.clone(), *digest, blob_type),
+            )));
+        }
+
+        let mut blob = BufReader::new(
+            remote
+                .fetch_blob(digest)
+                .context("failed to download a layer")?,
+        );
+
+        // A buffer used for determining the blob type
+        let mut buf = [0u8; TAR_BLOCK_SIZE];
+        let (blob_type, offset) = determine_blob_type(&mut buf, &mut blob)
+            .context("failed to determine the blob type of a layer")?;
+
+        let blob_type = self.layer_blob_type(digest, blob_type);
+        if let BlobType::Json | BlobType::Unknown = blob_type {
+            tracing::debug!(
+                "Layer {} is not a Tar blob",
+                sha256_digest_to_hex(digest)
+            );
+            return Ok(None);
+        }
+
+        // Restore the blob (as we've read some bytes from it to determine the blob type)
+        let parsed =
+            self.read_layer_blob(digest, blob_type, buf[..offset].chain(blob))?;
+
+        Ok(Some((
+            parsed,
+            LayerBlob::remote(remote.clone(), *digest, blob_type),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::parser::Platform;
+    use crate::parser::test_util::{TarEntry, gzip, make_tar};
+
+    /// An in-memory [RemoteImage] that records which blobs were fetched.
+    #[derive(Default)]
+    struct InMemoryImage {
+        blobs: HashMap<Sha256Digest, Vec<u8>>,
+        fetched: Mutex<Vec<Sha256Digest>>,
+    }
+
+    impl InMemoryImage {
+        /// Stores the provided blob, returning its digest in the `sha256:<hex>` format.
+        fn add(&mut self, blob: Vec<u8>) -> String {
+            let digest: Sha256Digest = Sha256::digest(&blob).into();
+            self.blobs.insert(digest, blob);
+            format!("sha256:{}", sha256_digest_to_hex(&digest))
+        }
+
+        fn add_json(&mut self, json: serde_json::Value) -> String {
+            self.add(serde_json::to_vec(&json).unwrap())
+        }
+
+        fn was_fetched(&self, digest: &str) -> bool {
+            self.fetched
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|fetched| digest.ends_with(&sha256_digest_to_hex(fetched)))
+        }
+
+        fn get(&self, digest: &Sha256Digest) -> anyhow::Result<Vec<u8>> {
+            self.fetched.lock().unwrap().push(*digest);
+            self.blobs.get(digest).cloned().context("blob not found")
+        }
+    }
+
+    impl RemoteImage for InMemoryImage {
+        fn fetch_manifest(
+            &self,
+            digest: &Sha256Digest,
+        ) -> anyhow::Result<Vec<u8>> {
+            self.get(digest)
+        }
+
+        fn fetch_blob(
+            &self,
+            digest: &Sha256Digest,
+        ) -> anyhow::Result<Box<dyn Read + Send>> {
+            Ok(Box::new(std::io::Cursor::new(self.get(digest)?)))
+        }
+    }
+
+    fn make_layer(contents: &[u8]) -> Vec<u8> {
+        gzip(&make_tar(&[TarEntry::File(
+            "etc/hostname",
+            0o644,
+            contents,
+        )]))
+    }
+
+    /// Adds a single-layer image for the provided architecture, returning the digest of its manifest.
+    fn add_image(remote: &mut InMemoryImage, architecture: &str) -> String {
+        let layer = remote.add(make_layer(architecture.as_bytes()));
+        let config = remote.add_json(json!({
+            "architecture": architecture,
+            "os": "linux",
+            "history": [{ "created_by": "COPY hostname /etc/hostname" }],
+        }));
+        remote.add_json(json!({
+            "config": { "digest": config },
+            "layers": [{
+                "mediaType": "application/vnd.oci.image.layer.v1.tar+gzip",
+                "digest": layer,
+            }],
+        }))
+    }
+
+    #[test]
+    fn parse_multi_platform_remote_image() {
+        let mut remote = InMemoryImage::default();
+        let amd64 = add_image(&mut remote, "amd64");
+        let arm64 = add_image(&mut remote, "arm64");
+        let attestation = remote.add_json(json!({ "unrelated": true }));
+        let index = serde_json::to_vec(&json!({
+            "manifests": [
+                { "digest": amd64, "platform": { "os": "linux", "architecture": "amd64" } },
+                { "digest": arm64, "platform": { "os": "linux", "architecture": "arm64" } },
+                { "digest": attestation, "platform": { "os": "unknown", "architecture": "unknown" } },
+            ],
+        }))
+        .unwrap();
+
+        let remote = Arc::new(remote);
+        let image = Parser::new()
+            .with_image("app:v1")
+            .with_platform(Some(Platform::new("linux", "arm64", None)))
+            .parse_remote_image(&index, remote.clone())
+            .unwrap();
+        assert_eq!(image.architecture, "arm64");
+        assert_eq!(image.tag, "v1");
+
+        // Only the selected platform must be fetched
+        assert!(remote.was_fetched(&arm64));
+        assert!(!remote.was_fetched(&amd64));
+        assert!(!remote.was_fetched(&attestation));
+
+        let layer = image.layers.values().next().unwrap();
+        let node = layer
+            .changeset
+            .as_ref()
+            .unwrap()
+            .get(Path::new("etc/hostname"))
+            .unwrap();
+        let contents = layer
+            .blob
+            .as_ref()
+            .unwrap()
+            .read_file(
+                node.inner.content_offset().unwrap(),
+                node.inner.size(),
+                u64::MAX,
+            )
+            .unwrap();
+        assert_eq!(contents, b"arm64");
+    }
+
+    #[test]
+    fn parse_single_platform_remote_image() {
+        let mut remote = InMemoryImage::default();
+        let manifest = add_image(&mut remote, "amd64");
+        let manifest = remote
+            .blobs
+            .iter()
+            .find(|(digest, _)| {
+                manifest.ends_with(&sha256_digest_to_hex(digest))
+            })
+            .map(|(_, manifest)| manifest.clone())
+            .unwrap();
+
+        let image = Parser::new()
+            .parse_remote_image(&manifest, Arc::new(remote))
+            .unwrap();
+        assert_eq!(image.architecture, "amd64");
+        assert_eq!(image.non_empty_layers, 1);
+    }
+
+    #[test]
+    fn parse_remote_image_with_invalid_digest() {
+        let invalid_index = json!({
+            "manifests": [
+                { "digest": "", "platform": { "os": "linux", "architecture": "amd64" } },
+            ],
+        });
+        let mut remote = InMemoryImage::default();
+        let nested_index = remote.add_json(invalid_index.clone());
+        let remote = Arc::new(remote);
+
+        let result = Parser::new().parse_remote_image(
+            &serde_json::to_vec(&invalid_index).unwrap(),
+            remote.clone(),
+        );
+        assert!(result.is_err());
+
+        // The same index served by the registry instead of being the root one
+        let index = serde_json::to_vec(&json!({
+            "manifests": [{ "digest": nested_index }],
+        }))
+        .unwrap();
+        let result = Parser::new().parse_remote_image(&index, remote.clone());
+        assert!(result.is_err());
+        assert!(remote.was_fetched(&nested_index));
+    }
+}
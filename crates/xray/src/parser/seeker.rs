@@ -53,4 +53,9 @@ impl<S> SeekerWithOffset<S> {
     pub fn mark_offset(&mut self) {
         self.pos_offset = self.pos;
     }
+
+    /// Returns the current offset, which is the absolute position that was marked by [Self::mark_offset].
+    pub fn offset(&self) -> u64 {
+        self.pos_offset
+    }
 }
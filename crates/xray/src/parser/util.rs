@@ -4,7 +4,11 @@ use anyhow::Context as _;
 use tar::Header;
 
 use super::constants::{
-    GZIP_MAGIC_NUMBER, SHA256_DIGEST_LENGTH, TAR_BLOCK_SIZE,
+    BLOB_PATH_PREFIX, BZIP2_MAGIC_NUMBER, DOCKER_LAYER_MEDIA_TYPE_PREFIX,
+    GZIP_MAGIC_NUMBER, IMAGE_INDEX_PATH, IMAGE_MANIFEST_PATH,
+    LEGACY_CONFIG_PATH_SUFFIX, LEGACY_LAYER_PATH_SUFFIX,
+    OCI_LAYER_MEDIA_TYPE_PREFIX, SHA256_DIGEST_LENGTH, TAR_BLOCK_SIZE,
+    XZ_MAGIC_NUMBER, ZSTD_MAGIC_NUMBER,
 };
 use super::{
     BlobType, Sha256Digest, TAR_MAGIC_NUMBER, TAR_MAGIC_NUMBER_START_IDX,
@@ -38,6 +42,40 @@ pub(super) fn sha256_digest_from_hex(
     Ok(sha256_hash)
 }
 
+/// Converts a [Sha256Digest] to a hex string.
+pub(super) fn sha256_digest_to_hex(digest: &Sha256Digest) -> String {
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Extracts the layer's digest from its path within the image.
+///
+/// Supports both OCI blobs (`blobs/sha256/<digest>`) and layers from the legacy `docker save` archives (`<id>/layer.tar`).
+/// Paths relative to a layer's directory (`../<id>/layer.tar`) are supported as well, as they are used by symlinks in
+/// the legacy archives.
+///
+/// Returns `None` if the path doesn't point to a layer.
+pub(super) fn layer_digest_from_path(
+    path: &[u8],
+) -> anyhow::Result<Option<Sha256Digest>> {
+    let path = path.strip_prefix(b"../").unwrap_or(path);
+    let Some(hex_digest) = path
+        .strip_prefix(BLOB_PATH_PREFIX)
+        .or_else(|| path.strip_suffix(LEGACY_LAYER_PATH_SUFFIX))
+    else {
+        return Ok(None);
+    };
+
+    sha256_digest_from_hex(hex_digest).map(Some)
+}
+
+/// Checks if the path points to an image config from a legacy `docker save` archive (`<id>.json` in the root of the image).
+pub(super) fn is_legacy_config_path(path: &[u8]) -> bool {
+    path.ends_with(LEGACY_CONFIG_PATH_SUFFIX)
+        && !path.contains(&b'/')
+        && path != IMAGE_MANIFEST_PATH
+        && path != IMAGE_INDEX_PATH
+}
+
 /// Converts a Tar entry's [size](Header::entry_size) to the number of Tar blocks, rounding up.
 pub(super) fn get_entry_size_in_blocks(header: &Header) -> anyhow::Result<u64> {
     let entry_size = header
@@ -89,6 +127,18 @@ pub(super) fn determine_blob_type<R: Read>(
                     && buf.starts_with(&GZIP_MAGIC_NUMBER)
                 {
                     BlobType::GzippedTar
+                } else if filled >= ZSTD_MAGIC_NUMBER.len()
+                    && buf.starts_with(&ZSTD_MAGIC_NUMBER)
+                {
+                    BlobType::ZstdTar
+                } else if filled >= XZ_MAGIC_NUMBER.len()
+                    && buf.starts_with(&XZ_MAGIC_NUMBER)
+                {
+                    BlobType::XzTar
+                } else if filled >= BZIP2_MAGIC_NUMBER.len()
+                    && buf.starts_with(&BZIP2_MAGIC_NUMBER)
+                {
+                    BlobType::Bzip2Tar
                 } else if filled == TAR_BLOCK_SIZE {
                     // We read a single tar block and weren't able to match this layer to any other type, so it must be a JSON
                     BlobType::Json
@@ -105,6 +155,43 @@ pub(super) fn determine_blob_type<R: Read>(
     Ok((BlobType::Json, filled))
 }
 
+/// Determines the type of a layer blob using its media type from the Image Manifest.
+///
+/// Returns `None` if the media type doesn't describe a known layer type.
+pub(super) fn blob_type_from_media_type(media_type: &str) -> Option<BlobType> {
+    let layer_type = media_type
+        .strip_prefix(OCI_LAYER_MEDIA_TYPE_PREFIX)
+        .or_else(|| media_type.strip_prefix(DOCKER_LAYER_MEDIA_TYPE_PREFIX))?;
+
+    // OCI uses structured suffixes (`tar+gzip`), while Docker uses dots (`tar.gzip`)
+    let (_, compression) = layer_type.rsplit_once("tar")?;
+    let blob_type = match compression {
+        "" => BlobType::Tar,
+        "+gzip" | ".gzip" => BlobType::GzippedTar,
+        "+zstd" | ".zstd" => BlobType::ZstdTar,
+        "+xz" | ".xz" => BlobType::XzTar,
+        "+bzip2" | ".bzip2" => BlobType::Bzip2Tar,
+        _ => return None,
+    };
+
+    Some(blob_type)
+}
+
+/// Resolves the [BlobType] of a layer from its sniffed type and the type from its media type if it's known.
+///
+/// Magic numbers identify layers unambiguously, so they take precedence over media types, as some tools label layers
+/// incorrectly (e.g. uncompressed layers as `tar+gzip`). The media type is only used if sniffing is inconclusive, which
+/// is the case for Tar archives without the ustar magic number.
+pub(super) fn resolve_layer_blob_type(
+    sniffed_blob_type: BlobType,
+    media_type_blob_type: Option<BlobType>,
+) -> BlobType {
+    match (sniffed_blob_type, media_type_blob_type) {
+        (BlobType::Json | BlobType::Unknown, Some(blob_type)) => blob_type,
+        (sniffed_blob_type, _) => sniffed_blob_type,
+    }
+}
+
 /// Checks if the provided buffer has the [Tar magic number](TAR_MAGIC_NUMBER) set.
 fn has_tar_magic_number(buf: impl AsRef<[u8]>) -> bool {
     let buf = buf.as_ref();
@@ -157,6 +244,14 @@ mod tests {
         assert_eq!(result, [0u8; 32]);
     }
 
+    #[test]
+    fn sha256_to_hex_roundtrip() {
+        let hex =
+            "a665a45920422f9d417e4867efdc4fb8a04a1f3fff1fa07e998e86f7f7a27ae3";
+        let digest = sha256_digest_from_hex(hex).unwrap();
+        assert_eq!(sha256_digest_to_hex(&digest), hex);
+    }
+
     #[test]
     fn sha256_from_hex_wrong_length() {
         let hex = b"a665a459";
@@ -234,6 +329,26 @@ mod tests {
         assert!(matches!(blob_type, BlobType::GzippedTar));
     }
 
+    #[test]
+    fn blob_type_compressed() {
+        for (magic_number, expected) in [
+            (ZSTD_MAGIC_NUMBER.as_slice(), BlobType::ZstdTar),
+            (XZ_MAGIC_NUMBER.as_slice(), BlobType::XzTar),
+            (BZIP2_MAGIC_NUMBER.as_slice(), BlobType::Bzip2Tar),
+        ] {
+            let mut buf = [0u8; TAR_BLOCK_SIZE];
+            let mut data = magic_number.to_vec();
+            data.resize(TAR_BLOCK_SIZE, 0x01);
+            let mut cursor = Cursor::new(data);
+            let (blob_type, _) =
+                determine_blob_type(&mut buf, &mut cursor).unwrap();
+            assert_eq!(
+                std::mem::discriminant(&blob_type),
+                std::mem::discriminant(&expected)
+            );
+        }
+    }
+
     #[test]
     fn blob_type_tar() {
         let mut buf = [0u8; TAR_BLOCK_SIZE];
@@ -292,4 +407,147 @@ mod tests {
             determine_blob_type(&mut buf, &mut cursor).unwrap();
         assert!(matches!(blob_type, BlobType::Json));
     }
+
+    // --- blob_type_from_media_type ---
+
+    #[test]
+    fn media_type_oci_layers() {
+        assert!(matches!(
+            blob_type_from_media_type("application/vnd.oci.image.layer.v1.tar"),
+            Some(BlobType::Tar)
+        ));
+        assert!(matches!(
+            blob_type_from_media_type(
+                "application/vnd.oci.image.layer.v1.tar+gzip"
+            ),
+            Some(BlobType::GzippedTar)
+        ));
+        assert!(matches!(
+            blob_type_from_media_type(
+                "application/vnd.oci.image.layer.v1.tar+zstd"
+            ),
+            Some(BlobType::ZstdTar)
+        ));
+        assert!(matches!(
+            blob_type_from_media_type(
+                "application/vnd.oci.image.layer.nondistributable.v1.tar+gzip"
+            ),
+            Some(BlobType::GzippedTar)
+        ));
+    }
+
+    #[test]
+    fn media_type_docker_layers() {
+        assert!(matches!(
+            blob_type_from_media_type(
+                "application/vnd.docker.image.rootfs.diff.tar.gzip"
+            ),
+            Some(BlobType::GzippedTar)
+        ));
+        assert!(matches!(
+            blob_type_from_media_type(
+                "application/vnd.docker.image.rootfs.foreign.diff.tar.gzip"
+            ),
+            Some(BlobType::GzippedTar)
+        ));
+    }
+
+    #[test]
+    fn media_type_unknown() {
+        assert!(
+            blob_type_from_media_type(
+                "application/vnd.oci.image.config.v1+json"
+            )
+            .is_none()
+        );
+        assert!(
+            blob_type_from_media_type(
+                "application/vnd.oci.image.layer.v1.tar+lz4"
+            )
+            .is_none()
+        );
+    }
+
+    // --- resolve_layer_blob_type ---
+
+    #[test]
+    fn layer_blob_type_prefers_magic_numbers() {
+        // Some tools label uncompressed layers as compressed ones
+        assert!(matches!(
+            resolve_layer_blob_type(BlobType::Tar, Some(BlobType::GzippedTar)),
+            BlobType::Tar
+        ));
+        assert!(matches!(
+            resolve_layer_blob_type(BlobType::ZstdTar, Some(BlobType::Tar)),
+            BlobType::ZstdTar
+        ));
+        assert!(matches!(
+            resolve_layer_blob_type(
+                BlobType::Empty,
+                Some(BlobType::GzippedTar)
+            ),
+            BlobType::Empty
+        ));
+    }
+
+    #[test]
+    fn layer_blob_type_falls_back_to_media_type() {
+        // Tar archives without the ustar magic number are sniffed as JSON
+        assert!(matches!(
+            resolve_layer_blob_type(BlobType::Json, Some(BlobType::Tar)),
+            BlobType::Tar
+        ));
+        assert!(matches!(
+            resolve_layer_blob_type(BlobType::Unknown, Some(BlobType::XzTar)),
+            BlobType::XzTar
+        ));
+        assert!(matches!(
+            resolve_layer_blob_type(BlobType::Json, None),
+            BlobType::Json
+        ));
+    }
+
+    // --- layer_digest_from_path ---
+
+    const HEX_DIGEST: &str =
+        "a665a45920422f9d417e4867efdc4fb8a04a1f3fff1fa07e998e86f7f7a27ae3";
+
+    #[test]
+    fn layer_digest_from_blob_path() {
+        let path = format!("blobs/sha256/{HEX_DIGEST}");
+        let digest = layer_digest_from_path(path.as_bytes()).unwrap().unwrap();
+        assert_eq!(digest, sha256_digest_from_hex(HEX_DIGEST).unwrap());
+    }
+
+    #[test]
+    fn layer_digest_from_legacy_path() {
+        let path = format!("{HEX_DIGEST}/layer.tar");
+        let digest = layer_digest_from_path(path.as_bytes()).unwrap().unwrap();
+        assert_eq!(digest, sha256_digest_from_hex(HEX_DIGEST).unwrap());
+
+        // Symlinks to other layers are relative to the layer's directory
+        let link = format!("../{HEX_DIGEST}/layer.tar");
+        let digest = layer_digest_from_path(link.as_bytes()).unwrap().unwrap();
+        assert_eq!(digest, sha256_digest_from_hex(HEX_DIGEST).unwrap());
+    }
+
+    #[test]
+    fn layer_digest_from_unrelated_path() {
+        assert!(layer_digest_from_path(b"manifest.json").unwrap().is_none());
+        let path = format!("{HEX_DIGEST}/json");
+        assert!(layer_digest_from_path(path.as_bytes()).unwrap().is_none());
+        assert!(layer_digest_from_path(b"blobs/sha256/abc").is_err());
+    }
+
+    // --- is_legacy_config_path ---
+
+    #[test]
+    fn legacy_config_path_detection() {
+        let config = format!("{HEX_DIGEST}.json");
+        assert!(is_legacy_config_path(config.as_bytes()));
+        assert!(!is_legacy_config_path(b"manifest.json"));
+        assert!(!is_legacy_config_path(b"index.json"));
+        let layer_json = format!("{HEX_DIGEST}/json");
+        assert!(!is_legacy_config_path(layer_json.as_bytes()));
+    }
 }
@@ -0,0 +1,227 @@
+//! Contains the logic related to verifying digests of the image's layers.
+
+use std::fmt::Display;
+use std::io::Read;
+
+use sha2::{Digest, Sha256};
+
+use super::Sha256Digest;
+use super::util::sha256_digest_to_hex;
+
+/// A reader that calculates the SHA256 digest of all the data that is read through it.
+pub(super) struct DigestReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> Read for DigestReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<R: Read> DigestReader<R> {
+    pub fn new(inner: R) -> Self {
+        DigestReader {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Reads the rest of the data from the inner reader.
+    ///
+    /// Parsers tend to stop before the actual end of the data (e.g. at the Tar's end-of-archive marker), so this must be
+    /// called before [Self::finalize] to get the digest of the whole blob.
+    pub fn drain(&mut self) -> std::io::Result<()> {
+        std::io::copy(self, &mut std::io::sink()).map(|_| ())
+    }
+
+    /// Returns the digest of all the data that was read so far.
+    pub fn finalize(self) -> Sha256Digest {
+        self.hasher.finalize().into()
+    }
+}
+
+/// Digests of a single layer that were calculated while parsing it.
+#[derive(Debug, Clone)]
+pub(super) struct LayerDigests {
+    /// Digest of the blob as it's stored in the image.
+    pub blob: Sha256Digest,
+    /// Digest of the uncompressed Tar stream.
+    ///
+    /// Is `None` if the layer couldn't be decompressed.
+    pub diff_id: Option<Sha256Digest>,
+    /// The error that happened while parsing the layer.
+    pub error: Option<String>,
+}
+
+/// A problem that was found while verifying a [layer](super::Layer).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationError {
+    /// The layer's blob is referenced by the image, but is not present in it.
+    MissingBlob,
+    /// The layer's blob doesn't match the digest it's referenced by.
+    BlobDigestMismatch { actual: Sha256Digest },
+    /// The uncompressed layer doesn't match its diff ID from the image config.
+    DiffIdMismatch {
+        expected: Sha256Digest,
+        actual: Sha256Digest,
+    },
+    /// The image config doesn't have a diff ID for the layer.
+    MissingDiffId,
+    /// The layer couldn't be parsed, which usually means that its blob is truncated or corrupted.
+    Unreadable(String),
+}
+
+impl Display for VerificationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerificationError::MissingBlob => {
+                write!(f, "the layer's blob is missing in the image")
+            }
+            VerificationError::BlobDigestMismatch { actual } => write!(
+                f,
+                "blob digest mismatch, got sha256:{}",
+                sha256_digest_to_hex(actual)
+            ),
+            VerificationError::DiffIdMismatch { expected, actual } => write!(
+                f,
+                "diff ID mismatch, expected sha256:{}, got sha256:{}",
+                sha256_digest_to_hex(expected),
+                sha256_digest_to_hex(actual)
+            ),
+            VerificationError::MissingDiffId => {
+                write!(
+                    f,
+                    "the image config doesn't have a diff ID for the layer"
+                )
+            }
+            VerificationError::Unreadable(error) => {
+                write!(f, "failed to parse the layer: {}", error)
+            }
+        }
+    }
+}
+
+/// Compares the calculated digests of a layer with the expected ones.
+///
+/// The expected blob digest can be `None` for layers that are not content-addressed (e.g. `<id>/layer.tar` in the
+/// legacy `docker save` archives).
+///
+/// Returns all the found problems, an empty [Vec] means that the layer was verified successfully.
+pub(super) fn verify_layer(
+    digests: Option<&LayerDigests>,
+    expected_blob: Option<&Sha256Digest>,
+    expected_diff_id: Option<&Sha256Digest>,
+) -> Vec<VerificationError> {
+    let Some(digests) = digests else {
+        return vec![VerificationError::MissingBlob];
+    };
+
+    let mut errors = Vec::new();
+    if let Some(expected_blob) = expected_blob
+        && *expected_blob != digests.blob
+    {
+        errors.push(VerificationError::BlobDigestMismatch {
+            actual: digests.blob,
+        });
+    }
+
+    if let Some(error) = digests.error.as_ref() {
+        errors.push(VerificationError::Unreadable(error.clone()));
+    }
+
+    match (expected_diff_id, digests.diff_id) {
+        (Some(expected), Some(actual)) if *expected != actual => {
+            errors.push(VerificationError::DiffIdMismatch {
+                expected: *expected,
+                actual,
+            })
+        }
+        (None, _) => errors.push(VerificationError::MissingDiffId),
+        // Unreadable layers are already reported above
+        _ => {}
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY_SHA256: &str =
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    const ABC_SHA256: &str =
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    fn make_digests(blob: u8, diff_id: Option<u8>) -> LayerDigests {
+        LayerDigests {
+            blob: [blob; 32],
+            diff_id: diff_id.map(|diff_id| [diff_id; 32]),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn digest_reader_hashes_read_data() {
+        let mut reader = DigestReader::new(b"abc".as_slice());
+        let mut buf = [0u8; 2];
+        reader.read_exact(&mut buf).unwrap();
+        reader.drain().unwrap();
+        assert_eq!(sha256_digest_to_hex(&reader.finalize()), ABC_SHA256);
+
+        let reader = DigestReader::new(b"".as_slice());
+        assert_eq!(sha256_digest_to_hex(&reader.finalize()), EMPTY_SHA256);
+    }
+
+    #[test]
+    fn verify_layer_matching_digests() {
+        let digests = make_digests(1, Some(2));
+        assert!(
+            verify_layer(Some(&digests), Some(&[1; 32]), Some(&[2; 32]))
+                .is_empty()
+        );
+        // Layers that are not content-addressed only have their diff IDs checked
+        assert!(verify_layer(Some(&digests), None, Some(&[2; 32])).is_empty());
+    }
+
+    #[test]
+    fn verify_layer_mismatching_digests() {
+        let digests = make_digests(1, Some(2));
+        assert_eq!(
+            verify_layer(Some(&digests), Some(&[3; 32]), Some(&[4; 32])),
+            vec![
+                VerificationError::BlobDigestMismatch { actual: [1; 32] },
+                VerificationError::DiffIdMismatch {
+                    expected: [4; 32],
+                    actual: [2; 32]
+                }
+            ]
+        );
+        assert_eq!(
+            verify_layer(Some(&digests), Some(&[1; 32]), None),
+            vec![VerificationError::MissingDiffId]
+        );
+    }
+
+    #[test]
+    fn verify_layer_missing_or_unreadable() {
+        assert_eq!(
+            verify_layer(None, Some(&[1; 32]), Some(&[2; 32])),
+            vec![VerificationError::MissingBlob]
+        );
+
+        let mut digests = make_digests(1, None);
+        digests.error = Some("unexpected EOF".to_owned());
+        assert_eq!(
+            verify_layer(Some(&digests), Some(&[3; 32]), Some(&[2; 32])),
+            vec![
+                VerificationError::BlobDigestMismatch { actual: [1; 32] },
+                VerificationError::Unreadable("unexpected EOF".to_owned())
+            ]
+        );
+    }
+}
@@ -28,6 +28,20 @@ pub enum AppAction {
     InputDeleteCharacter,
     /// Scroll horizontally in the specified [Direction] within the currently selected [super::view::Pane].
     Scroll(Direction),
+    /// Show/hide the metadata columns in the layer inspector pane.
+    ToggleMetadataColumns,
+    /// Show/hide the details popup for the currently selected node in the layer inspector pane.
+    ToggleNodeDetails,
+    /// Show/hide the contents of the currently selected file in the layer inspector pane.
+    ToggleFileContents,
+    /// Show/hide the diff between the currently selected file and its previous version in the layer inspector pane.
+    ToggleFileDiff,
+    /// Show the popup that asks where the currently selected node in the layer inspector pane should be extracted to.
+    ExtractNode,
+    /// Extract the currently selected node in the layer inspector pane into the directory from the extraction popup.
+    ConfirmExtraction,
+    /// Close the extraction popup without extracting anything.
+    CancelExtraction,
     /// Does a context-dependant subaction.
     ///
     /// The actual action depends on the currently active pane and its state.
@@ -16,7 +16,7 @@ use crate::keybindings::KeyAction;
 use crate::parser::Image;
 mod dispatcher;
 mod store;
-mod util;
+pub(crate) mod util;
 mod view;
 
 pub type AppDispatcher = Dispatcher<AppState, App>;
@@ -65,6 +65,33 @@ impl AppDispatcher {
                     }
 
                     // This block handles insert mode, as it requires handling free text input.
+                    // The extraction popup only accepts the destination directory, so any key except for the ones
+                    // below is a part of it.
+                    if self.get_store().show_extract_popup {
+                        match event.code {
+                            KeyCode::Enter => {
+                                self.dispatch(AppAction::ConfirmExtraction)?
+                            }
+                            KeyCode::Esc => {
+                                self.dispatch(AppAction::CancelExtraction)?
+                            }
+                            KeyCode::Backspace | KeyCode::Delete => {
+                                self.dispatch(AppAction::InputDeleteCharacter)?
+                            }
+                            KeyCode::Char(mut input) => {
+                                if event
+                                    .modifiers
+                                    .intersects(KeyModifiers::SHIFT)
+                                {
+                                    input = input.to_ascii_uppercase()
+                                }
+                                self.dispatch(AppAction::InputCharacter(input))?
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+
                     if self.get_store().is_in_insert_mode {
                         // Close the popup.
                         if event.code == KeyCode::Enter
@@ -112,7 +139,10 @@ impl AppDispatcher {
 
                     // This match handles selecting a pane by its index.
                     if let KeyEvent {
-                        code: KeyCode::Char(code @ ('1' | '2' | '3' | '4')),
+                        code:
+                            KeyCode::Char(
+                                code @ ('1' | '2' | '3' | '4' | '5' | '6'),
+                            ),
                         ..
                     } = event
                     {
@@ -134,6 +164,20 @@ impl AppDispatcher {
                             {
                                 self.dispatch(AppAction::ToggleHelpPane)?;
                             }
+                            // Close the node details popup if it's active.
+                            KeyAction::CloseActiveWindow
+                                if self.get_store().show_node_details_popup =>
+                            {
+                                self.dispatch(AppAction::ToggleNodeDetails)?;
+                            }
+                            // Close the file contents popup if it's active.
+                            KeyAction::CloseActiveWindow
+                                if self
+                                    .get_store()
+                                    .show_file_contents_popup =>
+                            {
+                                self.dispatch(AppAction::ToggleFileContents)?;
+                            }
                             KeyAction::CloseActiveWindow => {
                                 break 'outer Ok(());
                             }
@@ -143,6 +187,23 @@ impl AppDispatcher {
                             KeyAction::ToggleFilterPopup => {
                                 self.dispatch(AppAction::ToggleInputMode)?;
                             }
+                            KeyAction::ToggleMetadataColumns => {
+                                self.dispatch(
+                                    AppAction::ToggleMetadataColumns,
+                                )?;
+                            }
+                            KeyAction::ToggleNodeDetails => {
+                                self.dispatch(AppAction::ToggleNodeDetails)?;
+                            }
+                            KeyAction::ToggleFileContents => {
+                                self.dispatch(AppAction::ToggleFileContents)?;
+                            }
+                            KeyAction::ToggleFileDiff => {
+                                self.dispatch(AppAction::ToggleFileDiff)?;
+                            }
+                            KeyAction::ExtractNode => {
+                                self.dispatch(AppAction::ExtractNode)?;
+                            }
                             KeyAction::PreviousItem => {
                                 self.dispatch(AppAction::TogglePane(
                                     Direction::Backward,
@@ -23,7 +23,7 @@ pub struct AppState {
     /// All the [Panes](Pane) with their corresponding [rendering areas](Rect) sorted by their render order.
     ///
     /// Check docs of [ActivePane] to understand how panes are ordered.
-    pub panes: [(Option<Pane>, Rect); 4],
+    pub panes: [(Option<Pane>, Rect); 6],
     /// A [place](Rect) to render the command bar.
     pub command_bar_area: Rect,
     /// The currently selected pane.
@@ -38,11 +38,33 @@ pub struct AppState {
     pub show_help_popup: bool,
     /// Whether the UI is currently in the "insert" mode (i.e. allows unrestricted text input).
     pub is_in_insert_mode: bool,
+    /// Whether the details popup of the selected node is currently shown in the layer inspector pane.
+    pub show_node_details_popup: bool,
+    /// Whether the contents of the selected file are currently shown in the layer inspector pane.
+    pub show_file_contents_popup: bool,
+    /// Whether the popup that asks where to extract the selected node is currently shown in the layer inspector pane.
+    ///
+    /// The UI is in the "insert" mode while it's shown.
+    pub show_extract_popup: bool,
+    /// Whether the viewed image is the result of comparing two images.
+    ///
+    /// Check [Image::compare] for more details.
+    pub is_comparison: bool,
+    /// A message about the result of the last action that is shown in the command bar until the next action.
+    pub status_message: Option<StatusMessage>,
+}
+
+/// A message that is shown in the command bar.
+#[derive(Debug, Clone)]
+pub enum StatusMessage {
+    Info(String),
+    Error(String),
 }
 
 impl AppState {
     /// Creates a new instance of the [AppState] using data from the provided [Image].
     pub fn new(mut image: Image) -> anyhow::Result<Self> {
+        let is_comparison = image.comparison.is_some();
         let panes = init_panes(&mut image).context("failed to init panes")?;
         let clipboard = match Clipboard::new() {
             Ok(clipboard) => Some(clipboard),
@@ -60,6 +82,11 @@ impl AppState {
             layers: image.layers,
             show_help_popup: false,
             is_in_insert_mode: false,
+            show_node_details_popup: false,
+            show_file_contents_popup: false,
+            show_extract_popup: false,
+            is_comparison,
+            status_message: None,
         })
     }
 
@@ -100,6 +127,28 @@ impl AppState {
         }
     }
 
+    /// Returns a reference to the aggregated [LayerChangeSet] of the layer with the provided index and all the layers
+    /// before it.
+    ///
+    /// Only the layers up to the currently selected one can be used.
+    pub fn get_aggregated_layers_changeset_at(
+        &self,
+        layer_idx: usize,
+    ) -> anyhow::Result<&LayerChangeSet> {
+        let layer_selector_pane_idx: usize = ActivePane::LayerSelector.into();
+        let (layer_selector_pane, _) = &self.panes[layer_selector_pane_idx];
+        if let Some(Pane::LayerSelector(pane)) = layer_selector_pane {
+            pane.aggregated_layers_changeset_at(layer_idx)
+                .with_context(|| {
+                    format!("layer {layer_idx} comes after the selected layer")
+                })
+        } else {
+            anyhow::bail!(
+                "layer selector pane is no longer at the expected position in the UI"
+            );
+        }
+    }
+
     /// Returns a reference to the currently selected [Pane].
     fn get_active_pane(&self) -> anyhow::Result<&Pane> {
         self.panes
@@ -126,6 +175,16 @@ impl AppState {
             })
     }
 
+    /// Returns a mutable reference to the layer inspector [Pane].
+    ///
+    /// Unlike [Self::get_active_pane_mut], this works regardless of which pane is currently selected.
+    fn get_layer_inspector_pane_mut(&mut self) -> anyhow::Result<&mut Pane> {
+        let layer_inspector_pane_idx: usize = ActivePane::LayerInspector.into();
+        self.panes[layer_inspector_pane_idx].0.as_mut().context(
+            "bug: layer inspector pane is no longer at its expected place",
+        )
+    }
+
     /// A hook that applies the filters to the current changeset and should be called whenever the aggregated layer changeset is updated.
     fn on_changeset_updated(&mut self) -> anyhow::Result<()> {
         let layer_inspector_pane_idx: usize = ActivePane::LayerInspector.into();
@@ -176,6 +235,11 @@ impl Store for AppState {
     type Action = AppAction;
 
     fn handle(&mut self, action: Self::Action) -> anyhow::Result<()> {
+        if !matches!(action, AppAction::Empty(..)) {
+            // The message is only relevant until the user does something else
+            self.status_message = None;
+        }
+
         match action {
             AppAction::Empty((width, height)) => {
                 tracing::trace!("Received an empty event");
@@ -280,6 +344,100 @@ impl Store for AppState {
                 }
                 self.is_in_insert_mode = is_in_insert_mode;
             }
+            AppAction::ToggleMetadataColumns if !self.show_help_popup => {
+                self.get_active_pane_mut()?.toggle_metadata_columns();
+            }
+            AppAction::ToggleNodeDetails
+                if !self.show_help_popup
+                    && (self.show_node_details_popup
+                        || self.active_pane == ActivePane::LayerInspector) =>
+            {
+                self.show_node_details_popup =
+                    self.get_layer_inspector_pane_mut()?.toggle_node_details();
+            }
+            action @ (AppAction::ToggleFileContents
+            | AppAction::ToggleFileDiff)
+                if !self.show_help_popup
+                    && (self.show_file_contents_popup
+                        || self.active_pane == ActivePane::LayerInspector) =>
+            {
+                let layer_inspector_pane_idx: usize =
+                    ActivePane::LayerInspector.into();
+                // HACK: take the pane here in order to be able to provide a reference to the state when loading the file.
+                let mut layer_inspector_pane =
+                    self.panes[layer_inspector_pane_idx].0.take().context(
+                        "bug: forgot to return the layer inspector pane?",
+                    )?;
+
+                let show_diff = matches!(action, AppAction::ToggleFileDiff);
+                let result =
+                    layer_inspector_pane.toggle_file_contents(self, show_diff);
+
+                // Return the pane back
+                self.panes[layer_inspector_pane_idx]
+                    .0
+                    .replace(layer_inspector_pane);
+
+                self.show_file_contents_popup = result
+                    .context("failed to toggle the file contents popup")?;
+            }
+            AppAction::ExtractNode
+                if !self.show_help_popup
+                    && self.active_pane == ActivePane::LayerInspector =>
+            {
+                let dest = std::env::current_dir()
+                    .context("failed to get the current directory")?;
+                let layer_inspector_pane_idx: usize =
+                    ActivePane::LayerInspector.into();
+                // HACK: take the pane here in order to be able to provide a reference to the state when opening the popup.
+                let mut layer_inspector_pane =
+                    self.panes[layer_inspector_pane_idx].0.take().context(
+                        "bug: forgot to return the layer inspector pane?",
+                    )?;
+
+                let result = layer_inspector_pane
+                    .open_extract_popup(self, dest.display().to_string());
+
+                // Return the pane back
+                self.panes[layer_inspector_pane_idx]
+                    .0
+                    .replace(layer_inspector_pane);
+
+                self.show_extract_popup =
+                    result.context("failed to open the extraction popup")?;
+                self.is_in_insert_mode = self.show_extract_popup;
+            }
+            AppAction::ConfirmExtraction if self.show_extract_popup => {
+                let dest =
+                    self.get_layer_inspector_pane_mut()?.close_extract_popup();
+                self.show_extract_popup = false;
+                self.is_in_insert_mode = false;
+
+                let Some(dest) = dest else {
+                    return Ok(());
+                };
+                // Extraction errors (e.g. an already existing file) are shown to the user instead of closing the app
+                self.status_message = match self
+                    .get_active_pane()?
+                    .extract_current_node(self, &dest)
+                {
+                    Ok(Some((path, summary))) => {
+                        Some(StatusMessage::Info(format!(
+                            "Extracted {path} into {} ({summary})",
+                            dest.display()
+                        )))
+                    }
+                    Ok(None) => None,
+                    Err(e) => Some(StatusMessage::Error(format!(
+                        "Failed to extract the node: {e:#}"
+                    ))),
+                };
+            }
+            AppAction::CancelExtraction if self.show_extract_popup => {
+                self.get_layer_inspector_pane_mut()?.close_extract_popup();
+                self.show_extract_popup = false;
+                self.is_in_insert_mode = false;
+            }
             AppAction::InputCharacter(input) => {
                 self.get_active_pane_mut()?.on_input_character(input);
             }
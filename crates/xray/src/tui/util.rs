@@ -6,7 +6,7 @@ use ratatui::layout::{Constraint, Layout, Rect};
 use crate::render_order_enum;
 
 type CommandBarArea = Rect;
-type PaneAreas = [Rect; 4];
+type PaneAreas = [Rect; 6];
 
 /// An immutable value that has a predefined string representation.
 ///
@@ -111,9 +111,9 @@ pub(crate) fn copy_to_clipboard(
     }
 }
 
-/// Splits the passed [Rect] into two equal columns, also splitting the first column into three vertical sections.
+/// Splits the passed [Rect] into two columns, also splitting the first column into four vertical sections and the second one into two.
 ///
-/// Returns an array that contains upper left, middle left, lower left, and right [Rect], as well as a single [Rect] below them to render a command bar.
+/// Returns an array that contains four left [Rects](Rect) and two right [Rects](Rect) (from top to bottom), as well as a single [Rect] below them to render a command bar.
 pub(crate) fn split_layout(initial_area: Rect) -> (PaneAreas, CommandBarArea) {
     let [main, command_bar] =
         Layout::vertical([Constraint::Percentage(100), Constraint::Min(1)])
@@ -123,12 +123,27 @@ pub(crate) fn split_layout(initial_area: Rect) -> (PaneAreas, CommandBarArea) {
         Constraint::Percentage(70),
     ])
     .areas(main);
-    let [upper_left, middle_left, lower_left] = Layout::vertical([
-        Constraint::Min(8),
-        Constraint::Min(10),
-        Constraint::Percentage(100),
-    ])
-    .areas(left);
-
-    ([upper_left, middle_left, lower_left, right], command_bar)
+    let [upper_left, upper_middle_left, lower_middle_left, lower_left] =
+        Layout::vertical([
+            Constraint::Min(8),
+            Constraint::Min(8),
+            Constraint::Min(10),
+            Constraint::Percentage(100),
+        ])
+        .areas(left);
+    let [upper_right, lower_right] =
+        Layout::vertical([Constraint::Percentage(100), Constraint::Min(10)])
+            .areas(right);
+
+    (
+        [
+            upper_left,
+            upper_middle_left,
+            lower_middle_left,
+            lower_left,
+            upper_right,
+            lower_right,
+        ],
+        command_bar,
+    )
 }
@@ -75,9 +75,11 @@ impl View<AppState> for App {
 ///
 /// By default, panes are placed as follows:
 ///     1. Upper left pane - image information pane.
-///     2. Middle left pane - layer information pane.
-///     3. Bottom left pane - layer selection pane.
-///     4. Right pane - layer diff pane.
+///     2. Upper middle left pane - image configuration pane.
+///     3. Lower middle left pane - layer information pane.
+///     4. Bottom left pane - layer selection pane.
+///     5. Upper right pane - layer diff pane.
+///     6. Lower right pane - image efficiency pane.
 ///
 /// This function also renders the command bar below the main panes and
 /// the help popup if it's currently visible.
@@ -104,7 +106,7 @@ fn render(frame: &mut Frame, state: &AppState) -> anyhow::Result<()> {
     if state.show_help_popup {
         let popup_area = popup_area(
             frame.area(),
-            Some(Constraint::Length(23)),
+            Some(Constraint::Length(25)),
             Some(Constraint::Length(75)),
         );
         clear_area(frame, popup_area);
@@ -6,18 +6,22 @@ use crate::render_order_enum;
 render_order_enum!(
     ActivePane,
     ImageInfo,
+    Configuration,
     LayerInfo,
     LayerSelector,
-    LayerInspector
+    LayerInspector,
+    Efficiency
 );
 
 impl From<&Pane> for ActivePane {
     fn from(value: &Pane) -> Self {
         match value {
             Pane::ImageInfo(..) => ActivePane::ImageInfo,
+            Pane::Configuration(..) => ActivePane::Configuration,
             Pane::LayerInfo(..) => ActivePane::LayerInfo,
             Pane::LayerSelector(..) => ActivePane::LayerSelector,
             Pane::LayerInspector(..) => ActivePane::LayerInspector,
+            Pane::Efficiency(..) => ActivePane::Efficiency,
         }
     }
 }
@@ -1,16 +1,30 @@
 use crossterm_keybind::KeyBindTrait;
-use ratatui::style::Stylize;
+use ratatui::style::{Color, Stylize};
 use ratatui::widgets::{Paragraph, Widget};
 
 use crate::keybindings::KeyAction;
-use crate::tui::store::AppState;
+use crate::tui::store::{AppState, StatusMessage};
 
 /// A command bar that shows the most important hotkeys for the current [supper::Pane].
+///
+/// Shows the [StatusMessage] instead if there is one.
 pub struct CommandBar {}
 
 impl CommandBar {
     /// Renders the command bar.
     pub fn render(state: &AppState) -> anyhow::Result<impl Widget> {
+        match &state.status_message {
+            Some(StatusMessage::Info(message)) => {
+                return Ok(Paragraph::new(message.clone()).centered());
+            }
+            Some(StatusMessage::Error(message)) => {
+                return Ok(Paragraph::new(message.clone())
+                    .centered()
+                    .fg(Color::Red));
+            }
+            None => {}
+        }
+
         let action = if state.show_help_popup {
             "close"
         } else {
@@ -77,7 +77,7 @@ fn get_common_hotkeys() -> Vec<(Cow<'static, str>, &'static str)> {
             KeyAction::Exit.key_bindings_display().into(),
             "exit the app",
         ),
-        ("1, 2, 3, 4".into(), "select the corresponding pane"),
+        ("1, 2, 3, 4, 5, 6".into(), "select the corresponding pane"),
     ]
 }
 
@@ -87,7 +87,9 @@ fn get_hotkeys_for_active_pane(
     active_pane: ActivePane,
 ) {
     match active_pane {
-        ActivePane::ImageInfo | ActivePane::LayerInfo => {
+        ActivePane::ImageInfo
+        | ActivePane::Configuration
+        | ActivePane::LayerInfo => {
             hotkeys.push((
                 KeyAction::Copy.key_bindings_display().into(),
                 "copy the selected value to the clipboard",
@@ -110,6 +112,34 @@ fn get_hotkeys_for_active_pane(
                 KeyAction::Subaction.key_bindings_display().into(),
                 "show only changed files",
             ));
+            hotkeys.push((
+                KeyAction::ToggleMetadataColumns
+                    .key_bindings_display()
+                    .into(),
+                "show mode, owner and mtime columns",
+            ));
+            hotkeys.push((
+                KeyAction::ToggleNodeDetails.key_bindings_display().into(),
+                "show details of the selected node",
+            ));
+            hotkeys.push((
+                KeyAction::ToggleFileContents.key_bindings_display().into(),
+                "show contents of the selected file",
+            ));
+            hotkeys.push((
+                KeyAction::ToggleFileDiff.key_bindings_display().into(),
+                "show changes to the selected modified file",
+            ));
+            hotkeys.push((
+                KeyAction::ExtractNode.key_bindings_display().into(),
+                "extract the selected node into a directory",
+            ));
+        }
+        ActivePane::Efficiency => {
+            hotkeys.push((
+                KeyAction::Copy.key_bindings_display().into(),
+                "copy path to the clipboard",
+            ));
         }
         ActivePane::LayerSelector => {
             hotkeys.push((
@@ -1,25 +1,33 @@
+mod configuration;
+mod efficiency;
+mod extract_popup;
+mod file_contents_popup;
 mod filter_popup;
 mod image_info;
 mod layer_info;
 mod layer_inspector;
 mod layer_selector;
+mod node_details_popup;
 mod style;
 mod util;
 
 use std::borrow::Cow;
+use std::path::{Path, PathBuf};
 
 use anyhow::Context;
+pub use configuration::ConfigurationPane;
+pub use efficiency::EfficiencyPane;
 use image_info::ImageInfoField;
 pub use image_info::ImageInfoPane;
-use layer_info::LayerInfoField;
 pub use layer_info::LayerInfoPane;
+use layer_info::{HISTORY_MISMATCH_WARNING, LayerInfoField};
 pub use layer_inspector::LayerInspectorPane;
 pub use layer_selector::LayerSelectorPane;
 use ratatui::layout::Rect;
 use ratatui::style::{Modifier, Style, Stylize};
 use ratatui::text::{Line, Text};
 use ratatui::widgets::{Block, BorderType, Paragraph, Widget, Wrap};
-use style::{ACTIVE_FIELD_STYLE, text_style};
+use style::{ACTIVE_FIELD_STYLE, WARNING_STYLE, text_style};
 pub(super) use style::{
     FIELD_KEY_STYLE, FIELD_VALUE_STYLE, LayerInspectorNodeStyles,
 };
@@ -27,6 +35,7 @@ use util::fields_into_lines;
 
 use super::widgets::PaneWithPopup;
 use super::{ActivePane, SideEffect};
+use crate::extract::ExtractionSummary;
 use crate::parser::{Image, LayerChangeSet};
 use crate::tui::action::Direction;
 use crate::tui::store::AppState;
@@ -39,12 +48,16 @@ use crate::tui::util::encode_hex;
 pub enum Pane {
     /// Contains all image-related information from [crate::parser::Image].
     ImageInfo(ImageInfoPane),
+    /// Contains the runtime configuration and other metadata of the [crate::parser::Image].
+    Configuration(ConfigurationPane),
     /// Displays information about the [LayerSelectorPane::selected_layer].
     LayerInfo(LayerInfoPane),
     /// Allows switching between [Layers](crate::parser::Layer) of the [crate::parser::Image].
     LayerSelector(LayerSelectorPane),
     /// Displays the aggregated changeset from the currently selected [Layers](crate::parser::Layer).
     LayerInspector(LayerInspectorPane),
+    /// Displays the space wasted by the [crate::parser::Image] and where exactly it's wasted.
+    Efficiency(EfficiencyPane),
 }
 
 impl Pane {
@@ -90,6 +103,20 @@ impl Pane {
 
                 Paragraph::new(Text::from(lines)).block(block)
             }
+            Pane::Configuration(pane_state) => {
+                let lines = pane_state.lines(
+                    field_key_style,
+                    field_value_style,
+                    if pane_is_active {
+                        active_field_style
+                    } else {
+                        Style::default()
+                    },
+                    remaining_rows,
+                );
+
+                Paragraph::new(Text::from(lines)).block(block)
+            }
             Pane::LayerSelector(pane_state) => {
                 let lines = pane_state.lines(
                     state.layers.iter(),
@@ -123,6 +150,19 @@ impl Pane {
                     },
                 );
 
+                // Show the warning in the border, so that it's visible regardless of the pane's size
+                let block = if selected_layer.history_mismatch {
+                    block.title_bottom(
+                        Line::styled(
+                            HISTORY_MISMATCH_WARNING,
+                            WARNING_STYLE.patch(pane_text_style),
+                        )
+                        .centered(),
+                    )
+                } else {
+                    block
+                };
+
                 // FIXME: add a vertical scroll
                 // - this requires doing wrap manually, as there is no way to know how many lines will the wrapped text take
                 Paragraph::new(Text::from(lines))
@@ -133,10 +173,17 @@ impl Pane {
                 let (layer_changeset, _) =
                     state.get_aggregated_layers_changeset()?;
                 let (_, _, current_layer_idx) = state.get_selected_layer()?;
+                // Show how the sizes have changed compared to the base image
+                let base_changeset = state
+                    .layers
+                    .get_index(0)
+                    .and_then(|(_, layer)| layer.changeset.as_ref())
+                    .filter(|_| state.is_comparison && current_layer_idx != 0);
 
                 let lines = pane_state
                     .changeset_to_lines(
                         layer_changeset,
+                        base_changeset,
                         |node_is_selected,
                          node_updated_in,
                          node_is_deleted,
@@ -161,7 +208,15 @@ impl Pane {
                     )
                     .context("layer inspector: failed to render a changeset")?;
 
-                if let Some(filter_popup) = pane_state.filter_popup() {
+                if let Some(extract_popup) = pane_state.extract_popup() {
+                    let (popup, v_constraint, h_constraint) =
+                        extract_popup.render_with_layout_constraints();
+                    widget.set_popup((
+                        popup,
+                        Some(v_constraint),
+                        Some(h_constraint),
+                    ));
+                } else if let Some(filter_popup) = pane_state.filter_popup() {
                     let (popup, v_constraint, h_constraint) =
                         filter_popup.render_with_layout_constraints();
                     widget.set_popup((
@@ -169,11 +224,47 @@ impl Pane {
                         Some(v_constraint),
                         Some(h_constraint),
                     ));
+                } else if let Some(file_contents_popup) =
+                    pane_state.file_contents_popup()
+                {
+                    let (popup, v_constraint, h_constraint) =
+                        file_contents_popup
+                            .render_with_layout_constraints(remaining_rows);
+                    widget.set_popup((
+                        popup,
+                        Some(v_constraint),
+                        Some(h_constraint),
+                    ));
+                } else if let Some(node_details_popup) = pane_state
+                    .node_details_popup(state)
+                    .context("layer inspector: failed to render node details")?
+                {
+                    let (popup, v_constraint, h_constraint) =
+                        node_details_popup.render_with_layout_constraints();
+                    widget.set_popup((
+                        popup,
+                        Some(v_constraint),
+                        Some(h_constraint),
+                    ));
                 }
 
                 // FIXME: add a horizontal scroll
                 Paragraph::new(Text::from(lines)).block(block)
             }
+            Pane::Efficiency(pane_state) => {
+                let lines = pane_state.lines(
+                    field_key_style,
+                    field_value_style,
+                    if pane_is_active {
+                        active_field_style
+                    } else {
+                        Style::default()
+                    },
+                    remaining_rows,
+                );
+
+                Paragraph::new(Text::from(lines)).block(block)
+            }
         };
 
         widget.set_pane(pane_widget);
@@ -191,6 +282,10 @@ impl Pane {
                 pane_state.toggle_active_field(direction);
                 Ok(None)
             }
+            Pane::Configuration(pane_state) => {
+                pane_state.move_within_pane(direction);
+                Ok(None)
+            }
             Pane::LayerSelector(pane_state) => {
                 pane_state.move_within_pane(direction, state)
             }
@@ -201,6 +296,10 @@ impl Pane {
             Pane::LayerInspector(pane_state) => {
                 pane_state.move_within_pane(direction, state).map(|_| None)
             }
+            Pane::Efficiency(pane_state) => {
+                pane_state.move_within_pane(direction);
+                Ok(None)
+            }
         }
     }
 
@@ -224,6 +323,9 @@ impl Pane {
                 ImageInfoField::Architecture => architecture.into(),
                 ImageInfoField::Os => os.into(),
             }),
+            Pane::Configuration(pane) => {
+                pane.selected_value().map(Cow::Borrowed)
+            }
             Pane::LayerInfo(LayerInfoPane { active_field }) => {
                 let Ok((selected_layer_digest, selected_layer, _)) =
                     state.get_selected_layer()
@@ -239,6 +341,9 @@ impl Pane {
                     LayerInfoField::Digest => {
                         encode_hex(selected_layer_digest).into()
                     }
+                    LayerInfoField::DiffId => {
+                        encode_hex(selected_layer.diff_id.as_ref()?).into()
+                    }
                     LayerInfoField::Command => {
                         selected_layer.created_by.as_str().into()
                     }
@@ -259,6 +364,7 @@ impl Pane {
                     }
                 }
             }
+            Pane::Efficiency(pane) => pane.selected_path().map(Cow::Owned),
             _ => None,
         }
     }
@@ -306,7 +412,7 @@ impl Pane {
     pub fn on_input_character(&mut self, input: char) {
         // Only the inspector pane supports this action for now.
         if let Pane::LayerInspector(pane_state) = self {
-            pane_state.append_to_filter(input);
+            pane_state.append_to_input(input);
         };
     }
 
@@ -320,7 +426,7 @@ impl Pane {
     pub fn on_backspace(&mut self) {
         // Only the inspector pane supports this action for now.
         if let Pane::LayerInspector(pane_state) = self {
-            pane_state.pop_from_filter();
+            pane_state.pop_from_input();
         };
     }
 
@@ -361,6 +467,87 @@ impl Pane {
         None
     }
 
+    /// Toggles the metadata columns within the [Pane].
+    pub fn toggle_metadata_columns(&mut self) {
+        // Only the inspector pane supports this action for now.
+        if let Pane::LayerInspector(pane_state) = self {
+            pane_state.toggle_metadata_columns();
+        };
+    }
+
+    /// Toggles the details popup of the currently selected entry within the [Pane].
+    ///
+    /// Returns `true` if the popup is shown after toggling.
+    pub fn toggle_node_details(&mut self) -> bool {
+        // Only the inspector pane supports this action for now.
+        if let Pane::LayerInspector(pane_state) = self {
+            return pane_state.toggle_node_details();
+        };
+
+        false
+    }
+
+    /// Toggles the contents popup of the currently selected entry within the [Pane].
+    ///
+    /// Shows the changes made to the entry instead of its contents if `show_diff` is `true`. Returns `true` if the
+    /// popup is shown after toggling.
+    pub fn toggle_file_contents(
+        &mut self,
+        state: &AppState,
+        show_diff: bool,
+    ) -> anyhow::Result<bool> {
+        // Only the inspector pane supports this action for now.
+        if let Pane::LayerInspector(pane_state) = self {
+            return pane_state.toggle_file_contents(state, show_diff);
+        };
+
+        Ok(false)
+    }
+
+    /// Shows the popup that asks where the currently selected entry within the [Pane] should be extracted to.
+    ///
+    /// The destination directory is set to `dest` by default. Returns `true` if the popup is shown.
+    pub fn open_extract_popup(
+        &mut self,
+        state: &AppState,
+        dest: String,
+    ) -> anyhow::Result<bool> {
+        // Only the inspector pane supports this action for now.
+        if let Pane::LayerInspector(pane_state) = self {
+            return pane_state.open_extract_popup(state, dest);
+        };
+
+        Ok(false)
+    }
+
+    /// Closes the extraction popup within the [Pane] and returns the destination directory that was supplied by the
+    /// user or [Option::None] if the popup wasn't shown.
+    pub fn close_extract_popup(&mut self) -> Option<PathBuf> {
+        // Only the inspector pane supports this action for now.
+        if let Pane::LayerInspector(pane_state) = self {
+            return pane_state.close_extract_popup();
+        };
+
+        None
+    }
+
+    /// Extracts the currently selected entry within the [Pane] into the `dest` directory.
+    ///
+    /// Returns the path to the extracted entry and the [ExtractionSummary] or [Option::None] if the [Pane] doesn't
+    /// support extraction.
+    pub fn extract_current_node(
+        &self,
+        state: &AppState,
+        dest: &Path,
+    ) -> anyhow::Result<Option<(Cow<'static, str>, ExtractionSummary)>> {
+        // Only the inspector pane supports this action for now.
+        if let Pane::LayerInspector(pane_state) = self {
+            return pane_state.extract_current_node(state, dest).map(Some);
+        };
+
+        Ok(None)
+    }
+
     /// Returns a styled [Block] for the pane.
     fn get_styled_block(&self, is_active: bool) -> Block<'_> {
         let (border_type, border_style) = if is_active {
@@ -382,9 +569,11 @@ impl Pane {
     fn get_styled_title(&self, is_active: bool) -> impl Into<Line<'static>> {
         let title = match self {
             Pane::ImageInfo(..) => "Image Information",
+            Pane::Configuration(..) => "Configuration",
             Pane::LayerSelector(..) => "Layers",
             Pane::LayerInfo(..) => "Layer Information",
             Pane::LayerInspector(..) => "Layer Changes",
+            Pane::Efficiency(..) => "Image Efficiency",
         };
 
         let title = if is_active {
@@ -400,7 +589,7 @@ impl Pane {
 /// Initializes all panes from the provided [Image].
 pub fn init_panes(
     image: &mut Image,
-) -> anyhow::Result<[(Option<Pane>, Rect); 4]> {
+) -> anyhow::Result<[(Option<Pane>, Rect); 6]> {
     let image_info_pane = Pane::ImageInfo(ImageInfoPane::new(
         std::mem::take(&mut image.image_name),
         std::mem::take(&mut image.tag),
@@ -409,6 +598,8 @@ pub fn init_panes(
         std::mem::take(&mut image.os),
     ));
 
+    let configuration_pane = Pane::Configuration(ConfigurationPane::new(image));
+
     let (_, layer) = image
         .layers
         .get_index(0)
@@ -421,13 +612,22 @@ pub fn init_panes(
     let layer_inspector_pane =
         Pane::LayerInspector(LayerInspectorPane::default());
 
+    // Layers of a comparison don't waste any space, so the efficiency of the compared image is shown instead
+    let efficiency = match image.comparison.take() {
+        Some(comparison) => comparison.target_efficiency,
+        None => image.efficiency(),
+    };
+    let efficiency_pane = Pane::Efficiency(EfficiencyPane::new(efficiency));
+
     // Note that we assign zeroed rects here. This means that we won't be able to render anything before dispatching at least one
     // [AppAction::Empty] event with the correct terminal size.
     let mut panes = [
         (Some(image_info_pane), Rect::ZERO),
+        (Some(configuration_pane), Rect::ZERO),
         (Some(layer_info_pane), Rect::ZERO),
         (Some(layer_selector_pane), Rect::ZERO),
         (Some(layer_inspector_pane), Rect::ZERO),
+        (Some(efficiency_pane), Rect::ZERO),
     ];
 
     // Ensure that panes are always sorted by the render order, determined
@@ -0,0 +1,166 @@
+use ratatui::style::Style;
+use ratatui::text::{Line, Span};
+
+use super::style::FIELD_VALUE_DELIMITER;
+use crate::parser::{Image, LayerPresence};
+use crate::tui::action::Direction;
+use crate::tui::util::encode_hex;
+
+/// Displayed instead of the entries if the image doesn't have any configuration.
+const MISSING_CONFIGURATION_PLACEHOLDER: &str = "<missing>";
+
+/// The number of characters of a layer's digest that is shown when comparing images.
+const SHORT_DIGEST_LENGTH: usize = 12;
+
+/// A single entry within the pane with its key and value.
+type ConfigurationEntry = (&'static str, String);
+
+/// [super::Pane::Configuration] pane's state.
+#[derive(Debug)]
+pub struct ConfigurationPane {
+    /// Index of the currently selected entry.
+    active_entry_idx: usize,
+    /// All known configuration entries of the image in the order they are rendered in.
+    ///
+    /// Entries that can have multiple values (e.g. `Env` and `Labels`) are split into one entry per value.
+    entries: Vec<ConfigurationEntry>,
+}
+
+impl ConfigurationPane {
+    /// Creates a new pane by taking the configuration-related fields from the provided [Image].
+    pub fn new(image: &mut Image) -> Self {
+        let config = std::mem::take(&mut image.runtime_config);
+        let mut entries = Vec::new();
+
+        // Show how the compared images relate to each other before their configuration
+        if let Some(comparison) = image.comparison.as_ref() {
+            entries.push(("Compared With", comparison.base_image.clone()));
+            entries.extend(comparison.layers.iter().map(|layer| {
+                let key = match layer.presence {
+                    LayerPresence::Shared => "Shared Layer",
+                    LayerPresence::OnlyInBase => "Removed Layer",
+                    LayerPresence::OnlyInTarget => "Added Layer",
+                };
+                let mut digest = encode_hex(layer.digest);
+                digest.truncate(SHORT_DIGEST_LENGTH);
+                let command =
+                    layer.created_by.lines().next().unwrap_or_default();
+                (key, format!("sha256:{digest} {}", command.trim()))
+            }));
+        }
+
+        let mut push_if_present = |key: &'static str, value: Option<String>| {
+            if let Some(value) = value {
+                entries.push((key, value));
+            }
+        };
+
+        push_if_present("Created", image.created.take());
+        push_if_present("Author", image.author.take());
+        push_if_present("Variant", image.variant.take());
+        push_if_present("OS Version", image.os_version.take());
+        push_if_present("User", config.user);
+        push_if_present("Working Dir", config.working_dir);
+        push_if_present("Entrypoint", config.entrypoint.map(format_command));
+        push_if_present("Cmd", config.cmd.map(format_command));
+        push_if_present("Stop Signal", config.stop_signal);
+        push_if_present(
+            "Exposed Ports",
+            config.exposed_ports.map(|ports| ports.join(", ")),
+        );
+        push_if_present(
+            "Volumes",
+            config.volumes.map(|volumes| volumes.join(", ")),
+        );
+
+        entries.extend(
+            config
+                .env
+                .into_iter()
+                .flatten()
+                .map(|variable| ("Env", variable)),
+        );
+        entries.extend(
+            config
+                .labels
+                .into_iter()
+                .flatten()
+                .map(|(key, value)| ("Label", format!("{key}={value}"))),
+        );
+
+        ConfigurationPane {
+            active_entry_idx: 0,
+            entries,
+        }
+    }
+
+    /// The main entrypoint for rendering this pane.
+    ///
+    /// It processes the current state and returns back the lines that should be rendered in the pane.
+    pub fn lines(
+        &self,
+        field_key_style: Style,
+        field_value_style: Style,
+        active_entry_style: Style,
+        visible_rows: u16,
+    ) -> Vec<Line<'_>> {
+        if self.entries.is_empty() {
+            return vec![Line::from(Span::styled(
+                MISSING_CONFIGURATION_PLACEHOLDER,
+                field_value_style,
+            ))];
+        }
+
+        self.entries
+            .iter()
+            .enumerate()
+            // Always keep the selected entry visible
+            .skip(
+                (self.active_entry_idx + 1)
+                    .saturating_sub(Into::<usize>::into(visible_rows)),
+            )
+            .map(|(idx, (key, value))| {
+                let line = Line::from(vec![
+                    Span::styled(*key, field_key_style),
+                    Span::styled(FIELD_VALUE_DELIMITER, field_key_style),
+                    Span::styled(value.as_str(), field_value_style),
+                ]);
+
+                if idx == self.active_entry_idx {
+                    line.style(active_entry_style)
+                } else {
+                    line
+                }
+            })
+            .collect()
+    }
+
+    /// Selects the next entry in the specified direction.
+    pub fn move_within_pane(&mut self, direction: Direction) {
+        if self.entries.is_empty() {
+            return;
+        }
+
+        self.active_entry_idx = match direction {
+            Direction::Forward => {
+                (self.active_entry_idx + 1) % self.entries.len()
+            }
+            Direction::Backward => {
+                (self.active_entry_idx + self.entries.len() - 1)
+                    % self.entries.len()
+            }
+        };
+    }
+
+    /// Returns the value of the currently selected entry.
+    pub fn selected_value(&self) -> Option<&str> {
+        self.entries
+            .get(self.active_entry_idx)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Formats a command (e.g. `Entrypoint` or `Cmd`) the same way as `docker inspect` does, so that arguments with spaces stay unambiguous.
+fn format_command(command: Vec<String>) -> String {
+    serde_json::to_string(&command).unwrap_or_else(|_| command.join(" "))
+}
@@ -0,0 +1,167 @@
+use ratatui::style::Style;
+use ratatui::text::{Line, Span};
+
+use super::style::FIELD_VALUE_DELIMITER;
+use crate::parser::{ImageEfficiency, Inefficiency};
+use crate::tui::action::Direction;
+use crate::tui::util::Unit;
+
+/// Displayed instead of the entries if the image doesn't waste any space.
+const NO_WASTED_SPACE_PLACEHOLDER: &str = "No wasted space found";
+/// Number of rows taken by the image-wide summary at the top of the pane.
+const SUMMARY_ROWS: u16 = 2;
+/// Separates layer indexes of an entry.
+const LAYER_SEPARATOR: &str = ",";
+
+/// [super::Pane::Efficiency] pane's state.
+#[derive(Debug)]
+pub struct EfficiencyPane {
+    /// Index of the currently selected entry in [ImageEfficiency::inefficiencies].
+    active_entry_idx: usize,
+    efficiency: ImageEfficiency,
+    /// Width of the column with the layers involved, so that the paths are aligned.
+    layers_column_width: usize,
+}
+
+impl EfficiencyPane {
+    pub fn new(efficiency: ImageEfficiency) -> Self {
+        let layers_column_width = efficiency
+            .inefficiencies
+            .iter()
+            .map(|inefficiency| format_layers(inefficiency).len())
+            .max()
+            .unwrap_or(0);
+
+        EfficiencyPane {
+            active_entry_idx: 0,
+            efficiency,
+            layers_column_width,
+        }
+    }
+
+    /// The main entrypoint for rendering this pane.
+    ///
+    /// It processes the current state and returns back the lines that should be rendered in the pane.
+    pub fn lines(
+        &self,
+        field_key_style: Style,
+        field_value_style: Style,
+        active_entry_style: Style,
+        visible_rows: u16,
+    ) -> Vec<Line<'_>> {
+        let (wasted_size, wasted_unit) =
+            Unit::bytes_to_human_readable_units(self.efficiency.wasted_bytes);
+        let summary = [
+            (
+                "Efficiency Score",
+                format!("{:.1} %", self.efficiency.score * 100.0),
+            ),
+            (
+                "Wasted Space",
+                format!(
+                    "{:.1} {} in {} paths",
+                    wasted_size,
+                    wasted_unit.human_readable(),
+                    self.efficiency.inefficiencies.len()
+                ),
+            ),
+        ];
+        let mut lines = summary
+            .into_iter()
+            .map(|(key, value)| {
+                Line::from(vec![
+                    Span::styled(key, field_key_style),
+                    Span::styled(FIELD_VALUE_DELIMITER, field_key_style),
+                    Span::styled(value, field_value_style),
+                ])
+            })
+            .collect::<Vec<_>>();
+
+        if self.efficiency.inefficiencies.is_empty() {
+            lines.push(Line::from(Span::styled(
+                NO_WASTED_SPACE_PLACEHOLDER,
+                field_value_style,
+            )));
+            return lines;
+        }
+
+        let visible_rows = visible_rows.saturating_sub(SUMMARY_ROWS);
+        lines.extend(
+            self.efficiency
+                .inefficiencies
+                .iter()
+                .enumerate()
+                // Always keep the selected entry visible
+                .skip(
+                    (self.active_entry_idx + 1)
+                        .saturating_sub(Into::<usize>::into(visible_rows)),
+                )
+                .take(visible_rows.into())
+                .map(|(idx, inefficiency)| {
+                    let (size, unit) = Unit::bytes_to_human_readable_units(
+                        inefficiency.wasted_bytes,
+                    );
+                    let line = Line::from(vec![
+                        Span::styled(
+                            format!(
+                                "{:>5.1} {:<2} {:<11} {:<width$}  ",
+                                size,
+                                unit.human_readable(),
+                                inefficiency.kind.name(),
+                                format_layers(inefficiency),
+                                width = self.layers_column_width
+                            ),
+                            field_key_style,
+                        ),
+                        Span::styled(
+                            inefficiency.path.to_string_lossy(),
+                            field_value_style,
+                        ),
+                    ]);
+
+                    if idx == self.active_entry_idx {
+                        line.style(active_entry_style)
+                    } else {
+                        line
+                    }
+                }),
+        );
+
+        lines
+    }
+
+    /// Selects the next entry in the specified direction.
+    pub fn move_within_pane(&mut self, direction: Direction) {
+        let entries = self.efficiency.inefficiencies.len();
+        if entries == 0 {
+            return;
+        }
+
+        self.active_entry_idx = match direction {
+            Direction::Forward => (self.active_entry_idx + 1) % entries,
+            Direction::Backward => {
+                (self.active_entry_idx + entries - 1) % entries
+            }
+        };
+    }
+
+    /// Returns the path of the currently selected entry.
+    pub fn selected_path(&self) -> Option<String> {
+        self.efficiency
+            .inefficiencies
+            .get(self.active_entry_idx)
+            .map(|inefficiency| {
+                inefficiency.path.to_string_lossy().into_owned()
+            })
+    }
+}
+
+/// Formats the layers involved in an [Inefficiency] using the same 1-based numbering as the "Layers" pane.
+fn format_layers(inefficiency: &Inefficiency) -> String {
+    inefficiency
+        .layers
+        .iter()
+        .map(|layer_idx| (layer_idx + 1).to_string())
+        .collect::<Vec<_>>()
+        .join(LAYER_SEPARATOR)
+}
@@ -0,0 +1,114 @@
+use std::borrow::Cow;
+use std::path::PathBuf;
+
+use ratatui::layout::Constraint;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, BorderType, Padding, Paragraph, Wrap};
+
+const POPUP_PADDING: Padding = Padding {
+    left: 2,
+    right: 2,
+    top: 0,
+    bottom: 0,
+};
+
+/// A popup that asks the user where the currently selected node in the layer inspector pane should be extracted to.
+///
+/// Nothing is written to the disk until the user confirms the destination directory.
+#[derive(Debug)]
+pub struct ExtractPopup {
+    /// Path to the node that is extracted
+    path: Cow<'static, str>,
+    /// Destination directory supplied by the user
+    dest: String,
+}
+
+impl ExtractPopup {
+    /// Creates a new [ExtractPopup] for the node at the provided path with the destination directory set to `dest`.
+    pub fn new(path: Cow<'static, str>, dest: String) -> Self {
+        ExtractPopup { path, dest }
+    }
+
+    /// Returns the destination directory, which is the current directory if the user cleared the input.
+    pub fn dest(&self) -> PathBuf {
+        if self.dest.is_empty() {
+            PathBuf::from(".")
+        } else {
+            PathBuf::from(&self.dest)
+        }
+    }
+
+    /// Appends a single character to the destination directory.
+    pub fn append_to_dest(&mut self, input: char) {
+        self.dest.push(input);
+    }
+
+    /// Removes a single character from the destination directory.
+    pub fn pop_from_dest(&mut self) {
+        self.dest.pop();
+    }
+
+    /// Returns a widget that can be rendered inside the layer inspector pane and its vertical and horizontal size constraints.
+    pub fn render_with_layout_constraints(
+        &self,
+    ) -> (Paragraph<'_>, Constraint, Constraint) {
+        let block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .padding(POPUP_PADDING)
+            .title(
+                Line::from(format!("  Extract {} into  ", self.path))
+                    .centered(),
+            )
+            .title_bottom(Line::from(Self::keybindings()).centered());
+
+        (
+            Paragraph::new(Text::from(self.dest.as_str()))
+                .wrap(Wrap { trim: false })
+                .block(block),
+            Constraint::Length(3),
+            Constraint::Percentage(70),
+        )
+    }
+
+    /// Returns a [Vec] of keybindings to be rendered at the bottom of the popup.
+    fn keybindings() -> Vec<Span<'static>> {
+        let key_style = Style::new().bold();
+        let text_style = Style::new().add_modifier(Modifier::DIM);
+        vec![
+            // Padding
+            Span::from("  "),
+            Span::styled("enter", key_style),
+            Span::styled(" - ", text_style),
+            Span::styled("extract", text_style),
+            Span::styled(", ", text_style),
+            Span::styled("esc", key_style),
+            Span::styled(" - ", text_style),
+            Span::styled("cancel", text_style),
+            // Padding
+            Span::from("  "),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_destination() {
+        let mut popup = ExtractPopup::new("/etc/nginx".into(), "/tmp".into());
+        assert_eq!(popup.dest(), PathBuf::from("/tmp"));
+
+        for input in "/out".chars() {
+            popup.append_to_dest(input);
+        }
+        assert_eq!(popup.dest(), PathBuf::from("/tmp/out"));
+
+        // An empty destination means the current directory
+        for _ in 0.."/tmp/out".len() + 1 {
+            popup.pop_from_dest();
+        }
+        assert_eq!(popup.dest(), PathBuf::from("."));
+    }
+}
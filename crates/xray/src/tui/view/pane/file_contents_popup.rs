@@ -0,0 +1,504 @@
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crossterm_keybind::KeyBindTrait as _;
+use ratatui::layout::Constraint;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, BorderType, Padding, Paragraph, Wrap};
+use similar::{ChangeTag, TextDiff};
+
+use super::style::LayerInspectorNodeStyles;
+use crate::keybindings::KeyAction;
+use crate::parser::{LayerChangeSet, Node, NodeKind};
+use crate::tui::action::Direction;
+use crate::tui::store::AppState;
+use crate::tui::util::Unit;
+
+const POPUP_PADDING: Padding = Padding {
+    left: 2,
+    right: 2,
+    top: 0,
+    bottom: 0,
+};
+
+/// Files that are larger than this are only partially shown, as the whole file needs to be kept in memory.
+const MAX_FILE_CONTENTS_SIZE: u64 = 256 * 1024;
+/// Number of bytes that are shown in a single line of the hex dump.
+const HEX_DUMP_BYTES_PER_LINE: usize = 16;
+/// Tabs are replaced with spaces, as the terminal doesn't know where the popup starts.
+const TAB_REPLACEMENT: &str = "    ";
+/// Used for notes that are shown instead of or before the file's contents.
+const NOTE_STYLE: Style =
+    Style::new().add_modifier(Modifier::DIM.union(Modifier::ITALIC));
+/// Number of unchanged lines that are shown around each change in a diff.
+const DIFF_CONTEXT_LINES: usize = 3;
+/// Used for the headers of the diff's hunks.
+const HUNK_HEADER_STYLE: Style = Style::new().fg(Color::Cyan);
+
+/// A popup that displays the contents of the file that is currently selected in the layer inspector pane.
+///
+/// The contents are read from the layer in which the file was last updated as of the selected layer. Text files are
+/// shown as is, while binary files are shown as a hex dump.
+///
+/// Can also display a diff between the file's contents and its previous version (check [Self::load_diff]).
+#[derive(Debug)]
+pub struct FileContentsPopup {
+    /// Popup's title (i.e. the path to the file and the layer it's read from)
+    title: String,
+    /// All lines of the file (or a note explaining why they are missing)
+    lines: Vec<Line<'static>>,
+    /// Index of the first line that is shown in the popup
+    scroll: usize,
+}
+
+impl FileContentsPopup {
+    /// Reads the contents of the provided node from its layer.
+    ///
+    /// Errors that happen while reading the file are shown in the popup instead of being returned.
+    pub fn load(path: Cow<'_, str>, node: &Node, state: &AppState) -> Self {
+        let title = format!("  {} (layer {})  ", path, node.updated_in);
+        let lines = match state
+            .get_aggregated_layers_changeset()
+            .map_err(|e| e.to_string())
+            .and_then(|(tree, _)| read_contents(node, tree, state))
+        {
+            Ok((contents, size)) => {
+                let mut lines = vec![];
+                if size == 0 {
+                    lines.push(Line::styled("The file is empty", NOTE_STYLE));
+                }
+                lines.extend(truncation_note(&contents, size));
+
+                let is_truncated = (contents.len() as u64) < size;
+                match as_text(&contents, is_truncated) {
+                    Some(text) => lines.extend(
+                        text.lines().map(|line| Line::from(sanitize(line))),
+                    ),
+                    None => lines.extend(hex_dump_lines(&contents)),
+                }
+                lines
+            }
+            Err(note) => vec![Line::styled(note, NOTE_STYLE)],
+        };
+
+        FileContentsPopup {
+            title,
+            lines,
+            scroll: 0,
+        }
+    }
+
+    /// Compares the contents of the provided modified node with its contents in the previous layer that updated it and
+    /// shows the differences as a unified diff.
+    ///
+    /// Notes are shown instead of the diff if the node wasn't modified or either version is not a text file.
+    pub fn load_diff(
+        path: Cow<'_, str>,
+        node: &Node,
+        state: &AppState,
+    ) -> Self {
+        let (title, lines) =
+            match diff_with_previous_version(&path, node, state) {
+                Ok((previous_layer_idx, lines)) => (
+                    format!(
+                        "  {} (layer {} → layer {})  ",
+                        path, previous_layer_idx, node.updated_in
+                    ),
+                    lines,
+                ),
+                Err(note) => (
+                    format!("  {} (layer {})  ", path, node.updated_in),
+                    vec![Line::styled(note, NOTE_STYLE)],
+                ),
+            };
+
+        FileContentsPopup {
+            title,
+            lines,
+            scroll: 0,
+        }
+    }
+
+    /// Scrolls the contents by a single line in the provided [Direction].
+    pub fn scroll(&mut self, direction: Direction) {
+        self.scroll = match direction {
+            Direction::Forward => {
+                (self.scroll + 1).min(self.lines.len().saturating_sub(1))
+            }
+            Direction::Backward => self.scroll.saturating_sub(1),
+        };
+    }
+
+    /// Returns a widget that can be rendered inside the layer inspector pane and its vertical and horizontal size constraints.
+    ///
+    /// Only the lines that can fit into the provided number of rows are rendered.
+    pub fn render_with_layout_constraints(
+        &self,
+        rows: u16,
+    ) -> (Paragraph<'_>, Constraint, Constraint) {
+        let block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .padding(POPUP_PADDING)
+            .title(Line::from(self.title.as_str()).centered())
+            .title_bottom(Line::from(Self::keybindings()).centered());
+
+        let lines = self
+            .lines
+            .iter()
+            .skip(self.scroll)
+            .take(rows.into())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        (
+            Paragraph::new(Text::from(lines))
+                .wrap(Wrap { trim: false })
+                .block(block),
+            Constraint::Percentage(80),
+            Constraint::Percentage(80),
+        )
+    }
+
+    /// Returns a [Vec] of keybindings to be rendered at the bottom of the popup.
+    fn keybindings() -> Vec<Span<'static>> {
+        let key_style = Style::new().bold();
+        let text_style = Style::new().add_modifier(Modifier::DIM);
+        vec![
+            // Padding
+            Span::from("  "),
+            Span::styled(KeyAction::Down.key_bindings_display(), key_style),
+            Span::styled(" / ", text_style),
+            Span::styled(KeyAction::Up.key_bindings_display(), key_style),
+            Span::styled(" - ", text_style),
+            Span::styled("scroll", text_style),
+            Span::from("  "),
+            Span::styled(
+                KeyAction::CloseActiveWindow.key_bindings_display(),
+                key_style,
+            ),
+            Span::styled(" - ", text_style),
+            Span::styled("close", text_style),
+            // Padding
+            Span::from("  "),
+        ]
+    }
+}
+
+/// Reads up to [MAX_FILE_CONTENTS_SIZE] bytes of the node's contents and returns them together with the node's size.
+///
+/// Hard links are resolved using the provided tree. Returns a note that explains why there is nothing to show if the
+/// contents can't be read.
+fn read_contents(
+    node: &Node,
+    tree: &LayerChangeSet,
+    state: &AppState,
+) -> Result<(Vec<u8>, u64), String> {
+    let node = match node.inner.kind() {
+        // Hard links point to a file that has the actual contents
+        NodeKind::Hardlink if !node.inner.is_deleted() => {
+            let target = node
+                .inner
+                .get_link()
+                .ok_or("The hard link doesn't have a target")?;
+            tree.get(target).ok_or_else(|| {
+                format!(
+                    "The hard link's target '{}' doesn't exist in this layer",
+                    target.display()
+                )
+            })?
+        }
+        _ => node,
+    };
+
+    if node.inner.is_deleted() {
+        return Err("The file is deleted in this layer".into());
+    }
+    let Some(content_offset) = node.inner.content_offset() else {
+        let kind = node.inner.kind();
+        return Err(match node.inner.get_link() {
+            Some(link) if kind == NodeKind::Symlink => {
+                format!("This is a symbolic link to '{}'", link.display())
+            }
+            _ => format!("A {} doesn't have any contents", kind.name()),
+        });
+    };
+
+    let blob = state
+        .layers
+        .get_index(node.updated_in.into())
+        .and_then(|(_, layer)| layer.blob.as_ref())
+        .ok_or("The contents of this file are not available for this image")?;
+    let size = node.inner.size();
+    let contents = blob
+        .read_file(content_offset, size, MAX_FILE_CONTENTS_SIZE)
+        .map_err(|e| format!("Failed to read the file: {e:#}"))?;
+
+    Ok((contents, size))
+}
+
+/// Builds a unified diff between the node's contents and its contents in the previous layer that updated it.
+///
+/// Returns the index of that layer together with the diff's lines.
+fn diff_with_previous_version(
+    path: &str,
+    node: &Node,
+    state: &AppState,
+) -> Result<(u8, Vec<Line<'static>>), String> {
+    if !node.inner.is_modified() {
+        return Err(
+            "Only modified files can be compared with their previous version"
+                .into(),
+        );
+    }
+
+    let (tree, _) = state
+        .get_aggregated_layers_changeset()
+        .map_err(|e| e.to_string())?;
+    let (contents, size) = read_contents(node, tree, state)?;
+
+    let previous_tree = node
+        .updated_in
+        .checked_sub(1)
+        .ok_or_else(|| "The file doesn't have a previous version".to_string())
+        .and_then(|previous_layer_idx| {
+            state
+                .get_aggregated_layers_changeset_at(previous_layer_idx.into())
+                .map_err(|e| e.to_string())
+        })?;
+    let (previous_layer_idx, previous_contents, previous_size) =
+        match previous_version(path, previous_tree) {
+            Some(previous_node) => {
+                let (previous_contents, previous_size) =
+                    read_contents(previous_node, previous_tree, state)?;
+                (previous_node.updated_in, previous_contents, previous_size)
+            }
+            // The file was added or re-created after being deleted
+            None => (node.updated_in - 1, Vec::new(), 0),
+        };
+
+    let (Some(text), Some(previous_text)) = (
+        as_text(&contents, (contents.len() as u64) < size),
+        as_text(
+            &previous_contents,
+            (previous_contents.len() as u64) < previous_size,
+        ),
+    ) else {
+        return Err("Binary files can't be compared".into());
+    };
+
+    let mut lines = truncation_note(&previous_contents, previous_size)
+        .into_iter()
+        .chain(truncation_note(&contents, size))
+        .collect::<Vec<_>>();
+    let diff = TextDiff::from_lines(previous_text, text);
+    let mut has_changes = false;
+    for hunk in diff
+        .unified_diff()
+        .context_radius(DIFF_CONTEXT_LINES)
+        .iter_hunks()
+    {
+        has_changes = true;
+        lines.push(Line::styled(hunk.header().to_string(), HUNK_HEADER_STYLE));
+        for change in hunk.iter_changes() {
+            let (sign, style) = match change.tag() {
+                ChangeTag::Equal => (' ', Style::new()),
+                ChangeTag::Delete => (
+                    '-',
+                    LayerInspectorNodeStyles::get_deleted_node_style(true),
+                ),
+                ChangeTag::Insert => {
+                    ('+', LayerInspectorNodeStyles::get_added_node_style(true))
+                }
+            };
+            let line = change.value().trim_end_matches(['\n', '\r']);
+            lines
+                .push(Line::styled(format!("{sign}{}", sanitize(line)), style));
+            if change.missing_newline() {
+                lines.push(Line::styled(
+                    "\\ No newline at end of file",
+                    NOTE_STYLE,
+                ));
+            }
+        }
+    }
+
+    if !has_changes {
+        lines.push(Line::styled(
+            "The contents are the same in both layers",
+            NOTE_STYLE,
+        ));
+    }
+
+    Ok((previous_layer_idx, lines))
+}
+
+/// Returns the version of the file at the provided path as of the previous layer.
+///
+/// `previous_tree` is the aggregated tree of all layers before the one that updated the file, so hard links of the
+/// returned node can be resolved within it as well. Returns [None] if the file is missing or deleted there.
+fn previous_version<'t>(
+    path: &str,
+    previous_tree: &'t LayerChangeSet,
+) -> Option<&'t Node> {
+    previous_tree
+        .get(Path::new(path))
+        .filter(|node| !node.inner.is_deleted())
+}
+
+/// Returns a note that is shown if only some of the file's contents were read.
+fn truncation_note(contents: &[u8], size: u64) -> Option<Line<'static>> {
+    if contents.len() as u64 >= size {
+        return None;
+    }
+
+    let (shown, shown_unit) =
+        Unit::bytes_to_human_readable_units(contents.len() as u64);
+    let (size, unit) = Unit::bytes_to_human_readable_units(size);
+    Some(Line::styled(
+        format!(
+            "Only the first {:.1} {} out of {:.1} {} are shown",
+            shown,
+            shown_unit.human_readable(),
+            size,
+            unit.human_readable()
+        ),
+        NOTE_STYLE,
+    ))
+}
+
+/// Returns the provided contents as a string if they look like text.
+///
+/// `is_truncated` allows the contents to end in the middle of a multibyte character.
+fn as_text(contents: &[u8], is_truncated: bool) -> Option<&str> {
+    if contents.contains(&0) {
+        return None;
+    }
+
+    match std::str::from_utf8(contents) {
+        Ok(text) => Some(text),
+        Err(e) if is_truncated && e.error_len().is_none() => {
+            std::str::from_utf8(&contents[..e.valid_up_to()]).ok()
+        }
+        Err(_) => None,
+    }
+}
+
+/// Replaces characters that can't be rendered inside the popup.
+fn sanitize(line: &str) -> String {
+    line.replace('\t', TAB_REPLACEMENT)
+        .replace(char::is_control, "\u{FFFD}")
+}
+
+/// Returns a hex dump of the provided contents with the offset of each line and its printable characters.
+fn hex_dump_lines(contents: &[u8]) -> impl Iterator<Item = Line<'static>> + '_ {
+    contents
+        .chunks(HEX_DUMP_BYTES_PER_LINE)
+        .enumerate()
+        .map(|(idx, chunk)| {
+            let mut hex = String::with_capacity(HEX_DUMP_BYTES_PER_LINE * 3);
+            for (byte_idx, byte) in chunk.iter().enumerate() {
+                if byte_idx == HEX_DUMP_BYTES_PER_LINE / 2 {
+                    // Split each line into two halves to make it easier to read
+                    hex.push(' ');
+                }
+                // Writing to a String can't fail
+                let _ = write!(hex, "{byte:02x} ");
+            }
+
+            let printable = chunk
+                .iter()
+                .map(|&byte| {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        byte as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>();
+
+            Line::from(vec![
+                Span::styled(
+                    format!("{:08x}  ", idx * HEX_DUMP_BYTES_PER_LINE),
+                    NOTE_STYLE,
+                ),
+                Span::raw(format!(
+                    "{hex:<width$} ",
+                    width = HEX_DUMP_BYTES_PER_LINE * 3 + 1
+                )),
+                Span::styled(format!("|{printable}|"), NOTE_STYLE),
+            ])
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::test_util::{Entry, make_layer};
+
+    /// Aggregates the provided layers the same way the layer selector does.
+    fn aggregate(layers: &[&[Entry]]) -> LayerChangeSet {
+        layers
+            .iter()
+            .enumerate()
+            .map(|(idx, entries)| make_layer(idx as u8, entries))
+            .reduce(LayerChangeSet::merge)
+            .unwrap()
+    }
+
+    #[test]
+    fn previous_version_from_earlier_layer() {
+        // The file is modified in layer 2, while layer 1 doesn't touch it
+        let previous_tree = aggregate(&[
+            &[Entry::File("etc/config", 10)],
+            &[Entry::File("etc/other", 5)],
+        ]);
+
+        let previous_node = previous_version("etc/config", &previous_tree)
+            .expect("the file exists in the first layer");
+        assert_eq!(previous_node.updated_in, 0);
+        assert_eq!(previous_node.inner.size(), 10);
+    }
+
+    #[test]
+    fn previous_version_from_latest_update() {
+        let previous_tree = aggregate(&[
+            &[Entry::File("etc/config", 10)],
+            &[Entry::File("etc/config", 20)],
+        ]);
+
+        let previous_node =
+            previous_version("/etc/config", &previous_tree).unwrap();
+        assert_eq!(previous_node.updated_in, 1);
+        assert_eq!(previous_node.inner.size(), 20);
+    }
+
+    #[test]
+    fn previous_version_of_recreated_file() {
+        // The file is re-created in layer 2 after being deleted in layer 1
+        let previous_tree = aggregate(&[
+            &[Entry::File("etc/config", 10)],
+            &[Entry::Whiteout("etc/config")],
+        ]);
+
+        assert!(previous_version("etc/config", &previous_tree).is_none());
+    }
+
+    #[test]
+    fn previous_version_hidden_by_opaque_dir() {
+        let previous_tree = aggregate(&[
+            &[Entry::File("etc/config", 10)],
+            &[Entry::OpaqueDir("etc"), Entry::File("etc/other", 5)],
+        ]);
+
+        assert!(previous_version("etc/config", &previous_tree).is_none());
+    }
+
+    #[test]
+    fn previous_version_of_new_file() {
+        let previous_tree = aggregate(&[&[Entry::File("etc/other", 5)]]);
+
+        assert!(previous_version("etc/config", &previous_tree).is_none());
+    }
+}
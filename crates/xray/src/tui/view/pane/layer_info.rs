@@ -7,14 +7,19 @@ use crate::tui::util::encode_hex;
 use crate::{render_order_enum, sort_fields_by_render_order};
 
 const MISSING_COMMENT_PLACEHOLDER: &str = "<missing>";
+const MISSING_DIFF_ID_PLACEHOLDER: &str = "<missing>";
+/// Displayed at the bottom of the pane if the image's history doesn't match its layers, as the layer's command is unknown.
+pub const HISTORY_MISMATCH_WARNING: &str =
+    " ⚠ history doesn't match the layers ";
 
-render_order_enum!(LayerInfoField, Digest, Command, Comment);
+render_order_enum!(LayerInfoField, Digest, DiffId, Command, Comment);
 sort_fields_by_render_order!(LayerInfoField);
 
 impl FieldKey for LayerInfoField {
     fn name(&self) -> &'static str {
         match self {
             LayerInfoField::Digest => "Digest",
+            LayerInfoField::DiffId => "Diff ID",
             LayerInfoField::Command => "Command",
             LayerInfoField::Comment => "Comment",
         }
@@ -31,15 +36,22 @@ impl LayerInfoPane {
     pub fn get_fields<'a>(
         digest: &'a Sha256Digest,
         layer: &'a Layer,
-    ) -> [Field<'a, LayerInfoField>; 3] {
+    ) -> [Field<'a, LayerInfoField>; 4] {
         let comment: Cow<'a, str> =
             if let Some(comment) = layer.comment.as_ref() {
                 comment.into()
             } else {
                 MISSING_COMMENT_PLACEHOLDER.into()
             };
+        let diff_id: Cow<'a, str> =
+            if let Some(diff_id) = layer.diff_id.as_ref() {
+                encode_hex(diff_id).into()
+            } else {
+                MISSING_DIFF_ID_PLACEHOLDER.into()
+            };
         let mut fields = [
             (LayerInfoField::Digest, encode_hex(digest).into()),
+            (LayerInfoField::DiffId, diff_id),
             (LayerInfoField::Command, (&layer.created_by).into()),
             (LayerInfoField::Comment, comment),
         ];
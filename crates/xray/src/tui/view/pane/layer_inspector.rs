@@ -1,13 +1,18 @@
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::fmt::{self, Write as _};
+use std::path::{Path, PathBuf};
 
 use anyhow::Context;
 use ratatui::style::Style;
 use ratatui::text::{Line, Span};
 
+use super::extract_popup::ExtractPopup;
+use super::file_contents_popup::FileContentsPopup;
 use super::filter_popup::FilterPopup;
-use crate::parser::LayerChangeSet;
+use super::node_details_popup::NodeDetailsPopup;
+use crate::extract::{ExtractionSummary, extract_node};
+use crate::parser::{LayerChangeSet, Node, NodeKind};
 use crate::tui::action::Direction;
 use crate::tui::store::AppState;
 use crate::tui::util::Unit;
@@ -19,6 +24,16 @@ const ACTIVE_LEVEL_PREFIX: &str = "├─";
 const INACTIVE_LEVEL_PREFIX: &str = "└─";
 const COLLAPSED_NODE_STATUS_INDICATOR: &str = "⊕";
 const EXPANDED_NODE_STATUS_INDICATOR: &str = "─";
+const SYMLINK_INDICATOR: &str = "->";
+const HARDLINK_INDICATOR: &str = "=>";
+const CHAR_DEVICE_MARKER: &str = "[char device]";
+const BLOCK_DEVICE_MARKER: &str = "[block device]";
+const FIFO_MARKER: &str = "[fifo]";
+const UNKNOWN_KIND_MARKER: &str = "[unknown type]";
+/// Width of the column that shows how the sizes of nodes have changed when comparing images.
+const SIZE_DELTA_COLUMN_WIDTH: usize = 13;
+/// Displayed in metadata columns of nodes that don't have any recorded metadata.
+const MISSING_METADATA_PLACEHOLDER: &str = "?";
 
 /// [super::Pane::LayerInspector]'s pane state.
 #[derive(Debug, Default)]
@@ -35,15 +50,27 @@ pub struct LayerInspectorPane {
     is_showing_filter_popup: bool,
     /// Current aggregated changeset with all user-selected filters applied
     filtered_changeset: Option<(LayerChangeSet, usize)>,
+    /// Whether we are showing the mode, owner and modification time of each node
+    show_metadata_columns: bool,
+    /// Whether we are showing the details popup for the currently selected node
+    is_showing_node_details: bool,
+    /// Contents of the file that was selected when the file contents popup was opened (if it's shown)
+    file_contents_popup: Option<FileContentsPopup>,
+    /// Destination of the node that is about to be extracted (if the extraction popup is shown)
+    extract_popup: Option<ExtractPopup>,
 }
 
 impl LayerInspectorPane {
     /// The main entrypoint for rendering this pane.
     ///
     /// It processes the current state and returns back the lines that should be rendered in the pane.
+    ///
+    /// If `base_changeset` is provided, a column with the difference between the sizes of the changed nodes and their
+    /// sizes in it is rendered as well.
     pub fn changeset_to_lines<'a>(
         &self,
         changeset: &'a LayerChangeSet,
+        base_changeset: Option<&LayerChangeSet>,
         get_node_style: impl Fn(bool, u8, bool, bool) -> Style,
         visible_rows: u16,
     ) -> anyhow::Result<Vec<Line<'a>>> {
@@ -62,8 +89,15 @@ impl LayerInspectorPane {
             };
 
         let mut iter = changeset.iter_with_levels().enumerate();
-        if nodes_to_skip != 0 {
+        // Nodes from the base changeset that correspond to the parents of the current node, starting from the root
+        let mut base_parents: Vec<Option<&Node>> = Vec::new();
+        if let Some(base_changeset) = base_changeset {
+            base_parents.push(Some(base_changeset));
+        } else if nodes_to_skip != 0 {
             // HACK: mimic the `Skip` combinator
+            //
+            // Skipped nodes still need to be visited when looking up the base nodes, as they may be the parents
+            // of the rendered ones.
             iter.nth(
                 nodes_to_skip - 1, /* this requires a 0-based index */
             );
@@ -71,6 +105,23 @@ impl LayerInspectorPane {
         'outer: while let Some((idx, (path, node, depth, level_is_active))) =
             iter.next()
         {
+            let base_node = if base_changeset.is_some() {
+                base_parents.truncate(depth + 1);
+                let base_node = base_parents
+                    .last()
+                    .copied()
+                    .flatten()
+                    .and_then(|parent| parent.inner.children())
+                    .and_then(|children| children.get(path));
+                base_parents.push(base_node);
+                if idx < nodes_to_skip {
+                    continue;
+                }
+                base_node
+            } else {
+                None
+            };
+
             // Check if any parent of this node is collapsed
             for (node_idx, n_of_children) in self
                 .collapsed_nodes
@@ -113,42 +164,65 @@ impl LayerInspectorPane {
             write!(&mut node_tree_branch, "{node_name_prefix}{status_prefix}")
                 .with_context(|| format!("failed to format a node {idx}"))?;
 
-            let mut spans = vec![
-                Span::styled(
-                    format!(
-                        "   {:>5.1} {:<2}   ",
-                        node_size,
-                        unit.human_readable()
-                    ),
-                    get_node_style(
-                        node_is_active,
-                        node.updated_in,
-                        node.inner.is_deleted(),
-                        node.inner.is_modified(),
-                    ),
-                ),
-                Span::styled(
-                    node_tree_branch,
-                    get_node_style(node_is_active, u8::MAX, false, false),
+            let node_style = get_node_style(
+                node_is_active,
+                node.updated_in,
+                node.inner.is_deleted(),
+                node.inner.is_modified(),
+            );
+
+            let mut spans = vec![Span::styled(
+                format!(
+                    "   {:>5.1} {:<2}   ",
+                    node_size,
+                    unit.human_readable()
                 ),
-            ];
+                node_style,
+            )];
+
+            if base_changeset.is_some() {
+                // Nodes that weren't changed are the same in both images
+                let size_delta = (node.updated_in != 0).then(|| {
+                    node.inner.size() as i128
+                        - base_node.map_or(0, |node| node.inner.size()) as i128
+                });
+                spans.push(Span::styled(
+                    format_size_delta(size_delta),
+                    node_style,
+                ));
+            }
 
-            let mut path = format!(" {}", path.display());
-            if let Some(link) = node.inner.get_link() {
-                write!(&mut path, " -> {}", link.display()).with_context(
-                    || format!("failed to format a link {idx}"),
-                )?;
+            if self.show_metadata_columns {
+                spans.push(Span::styled(
+                    format_metadata_columns(node),
+                    node_style,
+                ));
             }
 
             spans.push(Span::styled(
-                path,
-                get_node_style(
-                    node_is_active,
-                    node.updated_in,
-                    node.inner.is_deleted(),
-                    node.inner.is_modified(),
-                ),
+                node_tree_branch,
+                get_node_style(node_is_active, u8::MAX, false, false),
             ));
+
+            let mut path = format!(" {}", path.display());
+            let node_kind = node.inner.kind();
+            if let Some(link) = node.inner.get_link() {
+                let link_indicator = if node_kind == NodeKind::Hardlink {
+                    HARDLINK_INDICATOR
+                } else {
+                    SYMLINK_INDICATOR
+                };
+                write!(&mut path, " {link_indicator} {}", link.display())
+                    .with_context(|| {
+                        format!("failed to format a link {idx}")
+                    })?;
+            } else if let Some(marker) = special_file_marker(node_kind) {
+                write!(&mut path, " {marker}").with_context(|| {
+                    format!("failed to format a special file {idx}")
+                })?;
+            }
+
+            spans.push(Span::styled(path, node_style));
             lines.push(Line::from(spans));
 
             // No need to process more entries than we can display
@@ -198,6 +272,12 @@ impl LayerInspectorPane {
             return Ok(());
         }
 
+        if let Some(file_contents_popup) = self.file_contents_popup.as_mut() {
+            // Scroll the file's contents instead of moving between nodes
+            file_contents_popup.scroll(direction);
+            return Ok(());
+        }
+
         let (tree, total_nodes) = if let Some((tree, total_nodes)) =
             self.filtered_changeset.as_ref()
         {
@@ -344,14 +424,22 @@ impl LayerInspectorPane {
         self.is_showing_filter_popup
     }
 
-    /// Appends to the currently active filter in the filter popup.
-    pub fn append_to_filter(&mut self, input: char) {
-        self.filter_popup.append_to_filter(input);
+    /// Appends to the destination in the extraction popup if it's shown or to the currently active filter in the filter
+    /// popup otherwise.
+    pub fn append_to_input(&mut self, input: char) {
+        match self.extract_popup.as_mut() {
+            Some(extract_popup) => extract_popup.append_to_dest(input),
+            None => self.filter_popup.append_to_filter(input),
+        }
     }
 
-    /// Pops from the currently active filter in the filter popup.
-    pub fn pop_from_filter(&mut self) {
-        self.filter_popup.pop_from_filter();
+    /// Pops from the destination in the extraction popup if it's shown or from the currently active filter in the
+    /// filter popup otherwise.
+    pub fn pop_from_input(&mut self) {
+        match self.extract_popup.as_mut() {
+            Some(extract_popup) => extract_popup.pop_from_dest(),
+            None => self.filter_popup.pop_from_filter(),
+        }
     }
 
     /// Toggles the [FilterPopup::show_only_changed_files] filter.
@@ -364,6 +452,115 @@ impl LayerInspectorPane {
         self.is_showing_filter_popup.then_some(&self.filter_popup)
     }
 
+    /// Toggles the metadata columns.
+    pub fn toggle_metadata_columns(&mut self) {
+        self.show_metadata_columns = !self.show_metadata_columns;
+    }
+
+    /// Toggles the node details popup.
+    pub fn toggle_node_details(&mut self) -> bool {
+        self.is_showing_node_details = !self.is_showing_node_details;
+        self.is_showing_node_details
+    }
+
+    /// Returns a node details popup for the currently selected node if it should be shown on the screen.
+    pub fn node_details_popup<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> anyhow::Result<Option<NodeDetailsPopup<'a>>> {
+        if !self.is_showing_node_details || self.is_showing_filter_popup {
+            return Ok(None);
+        }
+
+        let Some(node) = self.get_current_node(state)? else {
+            return Ok(None);
+        };
+        let path = self.get_current_node_full_path(state)?;
+
+        Ok(Some(NodeDetailsPopup::new(path, node)))
+    }
+
+    /// Toggles the file contents popup, reading the contents of the currently selected node when showing it.
+    ///
+    /// If `show_diff` is `true`, the popup shows a diff between the node and its previous version instead.
+    pub fn toggle_file_contents(
+        &mut self,
+        state: &AppState,
+        show_diff: bool,
+    ) -> anyhow::Result<bool> {
+        if self.file_contents_popup.take().is_some() {
+            return Ok(false);
+        }
+
+        let Some(node) = self.get_current_node(state)? else {
+            return Ok(false);
+        };
+        let path = self.get_current_node_full_path(state)?;
+        self.file_contents_popup = Some(if show_diff {
+            FileContentsPopup::load_diff(path, node, state)
+        } else {
+            FileContentsPopup::load(path, node, state)
+        });
+
+        Ok(true)
+    }
+
+    /// Returns a file contents popup if it should be shown on the screen.
+    pub fn file_contents_popup(&self) -> Option<&FileContentsPopup> {
+        self.file_contents_popup
+            .as_ref()
+            .filter(|_| !self.is_showing_filter_popup)
+    }
+
+    /// Shows the extraction popup for the currently selected node with the destination directory set to `dest`.
+    ///
+    /// Returns `true` if the popup is shown.
+    pub fn open_extract_popup(
+        &mut self,
+        state: &AppState,
+        dest: String,
+    ) -> anyhow::Result<bool> {
+        if self.get_current_node(state)?.is_none() {
+            return Ok(false);
+        }
+
+        let path = self.get_current_node_full_path(state)?;
+        self.extract_popup = Some(ExtractPopup::new(path, dest));
+        Ok(true)
+    }
+
+    /// Closes the extraction popup and returns the destination directory that was supplied by the user.
+    pub fn close_extract_popup(&mut self) -> Option<PathBuf> {
+        self.extract_popup.take().map(|popup| popup.dest())
+    }
+
+    /// Returns an extraction popup if it should be shown on the screen.
+    pub fn extract_popup(&self) -> Option<&ExtractPopup> {
+        self.extract_popup.as_ref()
+    }
+
+    /// Extracts the currently selected node into the `dest` directory.
+    ///
+    /// The node is looked up in the unfiltered aggregated changeset, so that all of its children are extracted. The
+    /// setuid, setgid, and sticky bits are always dropped.
+    pub fn extract_current_node(
+        &self,
+        state: &AppState,
+        dest: &Path,
+    ) -> anyhow::Result<(Cow<'static, str>, ExtractionSummary)> {
+        let path = self.get_current_node_full_path(state)?;
+        let (tree, _) = state.get_aggregated_layers_changeset()?;
+        let summary = extract_node(
+            tree,
+            &state.layers,
+            Path::new(path.as_ref()),
+            dest,
+            false,
+        )?;
+
+        Ok((path, summary))
+    }
+
     /// Returns a string representation of absolute path to the currently selected node.
     pub fn get_current_node_full_path(
         &self,
@@ -400,6 +597,26 @@ impl LayerInspectorPane {
         Ok(path.into())
     }
 
+    /// Returns the currently selected node or [Option::None] if the current changeset is empty.
+    fn get_current_node<'a>(
+        &'a self,
+        state: &'a AppState,
+    ) -> anyhow::Result<Option<&'a Node>> {
+        let (tree, _) = if let Some((tree, total_nodes)) =
+            self.filtered_changeset.as_ref()
+        {
+            // Use the filtered changeset if it's present
+            (tree, *total_nodes)
+        } else {
+            state.get_aggregated_layers_changeset()?
+        };
+
+        Ok(tree
+            .iter()
+            .nth(self.current_node_idx)
+            .map(|(_, node, _, _)| node))
+    }
+
     /// Returns true if node at the provided index is currently collapsed.
     fn is_node_collapsed(&self, idx: usize) -> bool {
         self.collapsed_nodes.contains_key(&idx)
@@ -444,3 +661,59 @@ impl LayerInspectorPane {
         adjusted_skip_count
     }
 }
+
+/// Formats the mode, owner and modification time of a node into fixed-width columns.
+fn format_metadata_columns(node: &Node) -> String {
+    if let Some(metadata) = node.inner.metadata() {
+        format!(
+            "{:<10} {:<17.17} {:<16.16}   ",
+            metadata.permissions(node.inner.kind()),
+            metadata.owner(),
+            // Seconds are rarely useful here, so we omit them to save some space
+            metadata.modified_at(),
+        )
+    } else {
+        format!(
+            "{:<10} {:<17} {:<16}   ",
+            MISSING_METADATA_PLACEHOLDER,
+            MISSING_METADATA_PLACEHOLDER,
+            MISSING_METADATA_PLACEHOLDER,
+        )
+    }
+}
+
+/// Formats the difference between two sizes of a node into a fixed-width column.
+///
+/// The column is left empty if the size is unknown.
+fn format_size_delta(size_delta: Option<i128>) -> String {
+    let Some(size_delta) = size_delta else {
+        return " ".repeat(SIZE_DELTA_COLUMN_WIDTH);
+    };
+
+    let sign = match size_delta.signum() {
+        1 => "+",
+        -1 => "-",
+        _ => "",
+    };
+    let (size, unit) =
+        Unit::bytes_to_human_readable_units(size_delta.unsigned_abs() as u64);
+    format!(
+        "{:>7} {:<2}   ",
+        format!("{sign}{size:.1}"),
+        unit.human_readable()
+    )
+}
+
+/// Returns a marker that is appended to the names of special files (i.e. devices, FIFOs, etc.).
+fn special_file_marker(kind: NodeKind) -> Option<&'static str> {
+    match kind {
+        NodeKind::CharDevice => Some(CHAR_DEVICE_MARKER),
+        NodeKind::BlockDevice => Some(BLOCK_DEVICE_MARKER),
+        NodeKind::Fifo => Some(FIFO_MARKER),
+        NodeKind::Other => Some(UNKNOWN_KIND_MARKER),
+        NodeKind::File
+        | NodeKind::Directory
+        | NodeKind::Symlink
+        | NodeKind::Hardlink => None,
+    }
+}
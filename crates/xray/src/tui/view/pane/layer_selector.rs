@@ -2,7 +2,7 @@ use anyhow::Context as _;
 use ratatui::style::Style;
 use ratatui::text::{Line, Span};
 
-use super::style::layer_status_indicator_style;
+use super::style::{FAILED_VERIFICATION_STYLE, layer_status_indicator_style};
 use crate::parser::{Layer, LayerChangeSet, Sha256Digest};
 use crate::tui::action::Direction;
 use crate::tui::store::AppState;
@@ -10,6 +10,9 @@ use crate::tui::util::Unit;
 use crate::tui::view::SideEffect;
 
 const LAYER_STATUS_INDICATOR_LEN: usize = 2;
+const LAYER_STATUS_INDICATOR: &str = "  ";
+/// Replaces [LAYER_STATUS_INDICATOR] for layers that failed verification.
+const FAILED_VERIFICATION_INDICATOR: &str = "✗ ";
 /// Length of the fixed part (i.e. without the command that created the layer)
 const LAYER_INFO_FIXED_LEN: usize = 16;
 
@@ -24,10 +27,13 @@ pub struct LayerSelectorPane {
     ///
     /// The index **must** be a valid index that points to an entry in [AppState::layers].
     selected_layer_idx: usize,
-    /// An aggregated changeset of all layers up to the current one (including it as well).
+    /// Aggregated changesets of all layers up to the current one (including it as well), one for each layer.
     ///
-    /// The second value is the total number of entries (both files and directories) in this changeset.
-    aggregated_layers_changeset: (LayerChangeSet, usize),
+    /// The second value is the total number of entries (both files and directories) in each changeset.
+    ///
+    /// Aggregated changesets of the previous layers are kept around to make moving back to them free. They share most
+    /// of their nodes with the changesets of the next layers, so keeping them is cheap.
+    aggregated_layers_changesets: Vec<(LayerChangeSet, usize)>,
     /// Current horizontal scroll offset
     scroll_offset: usize,
 }
@@ -37,7 +43,7 @@ impl LayerSelectorPane {
         let changeset_size = changeset.iter().count();
         LayerSelectorPane {
             selected_layer_idx: idx,
-            aggregated_layers_changeset: (changeset, changeset_size),
+            aggregated_layers_changesets: vec![(changeset, changeset_size)],
             scroll_offset: 0,
         }
     }
@@ -49,10 +55,23 @@ impl LayerSelectorPane {
 
     /// Returns a reference to the aggregated changeset and the number of entries inside it.
     pub fn aggregated_layers_changeset(&self) -> (&LayerChangeSet, usize) {
-        (
-            &self.aggregated_layers_changeset.0,
-            self.aggregated_layers_changeset.1,
-        )
+        let (changeset, changeset_size) = self
+            .aggregated_layers_changesets
+            .last()
+            .expect("there is always a changeset for the selected layer");
+        (changeset, *changeset_size)
+    }
+
+    /// Returns a reference to the aggregated changeset of the layer with the provided index and all the layers before it.
+    ///
+    /// Returns [None] if the layer comes after the currently selected one.
+    pub fn aggregated_layers_changeset_at(
+        &self,
+        layer_idx: usize,
+    ) -> Option<&LayerChangeSet> {
+        self.aggregated_layers_changesets
+            .get(layer_idx)
+            .map(|(changeset, _)| changeset)
     }
 
     /// The main entrypoint for rendering this pane.
@@ -123,14 +142,35 @@ impl LayerSelectorPane {
                         NOT_SCROLLABLE_INDICATOR
                     };
 
+                let failed_verification = layer
+                    .verification_errors
+                    .as_ref()
+                    .is_some_and(|errors| !errors.is_empty());
+                let (status_indicator, field_value_style) =
+                    if failed_verification {
+                        (
+                            FAILED_VERIFICATION_INDICATOR,
+                            field_value_style.patch(FAILED_VERIFICATION_STYLE),
+                        )
+                    } else {
+                        (LAYER_STATUS_INDICATOR, field_value_style)
+                    };
+
                 Line::from(vec![
                     // A colored block that acts as an indicator of the currently selected layer.
                     // It's also used to display the layers that are currently used to show aggregated changes.
                     Span::styled(
-                        "  ",
+                        status_indicator,
                         layer_status_indicator_style(
                             idx,
                             &self.selected_layer_idx,
+                        )
+                        .patch(
+                            if failed_verification {
+                                FAILED_VERIFICATION_STYLE
+                            } else {
+                                Style::default()
+                            },
                         ),
                     ),
                     // Render per-layer information
@@ -175,31 +215,46 @@ impl LayerSelectorPane {
             }
         };
 
-        let all_current_layers =
-            state.layers.get_range(..next_layer_idx + 1).context(
-                "bug: the next layer idx points to an invalid index",
-            )?;
-
-        // Get the first changeset and use it as a base layer for merging the rest of the layers
-        let mut aggregated_layers = all_current_layers
-            .get_index(0)
-            .and_then(|(_, layer)| layer.changeset.clone())
-            .context("bug: not a single layer is selected")?;
+        match direction {
+            Direction::Forward => {
+                let (aggregated_layers, aggregated_layers_changeset_size) =
+                    self.aggregated_layers_changeset();
+                let (_, next_layer) =
+                    state.layers.get_index(next_layer_idx).context(
+                        "bug: the next layer idx points to an invalid index",
+                    )?;
 
-        // Merge the rest of the layers with the first one
-        for (_, layer) in
-            all_current_layers.get_range(1..).into_iter().flatten()
-        {
-            if let Some(changeset) = layer.changeset.as_ref() {
-                aggregated_layers = aggregated_layers.merge(changeset.clone())
+                // Only merge the next layer into the current aggregated changeset
+                let next_aggregated_layers = if let Some(changeset) =
+                    next_layer.changeset.as_ref()
+                {
+                    let (aggregated_layers, n_of_nodes_delta) =
+                        aggregated_layers
+                            .clone()
+                            .merge_and_count(changeset.clone());
+                    let aggregated_layers_changeset_size =
+                        aggregated_layers_changeset_size
+                            .checked_add_signed(n_of_nodes_delta)
+                            .context(
+                                "bug: the aggregated changeset has a negative size",
+                            )?;
+                    (aggregated_layers, aggregated_layers_changeset_size)
+                } else {
+                    (
+                        aggregated_layers.clone(),
+                        aggregated_layers_changeset_size,
+                    )
+                };
+                self.aggregated_layers_changesets
+                    .push(next_aggregated_layers);
+            }
+            Direction::Backward => {
+                // The aggregated changeset of the previous layer is already there
+                self.aggregated_layers_changesets.pop();
             }
         }
 
-        // Calculate the total number of entries in the new changeset
-        let aggregated_layers_changeset_size = aggregated_layers.iter().count();
         self.selected_layer_idx = next_layer_idx;
-        self.aggregated_layers_changeset =
-            (aggregated_layers, aggregated_layers_changeset_size);
         // Reset the scroll offset as well
         self.scroll_offset = 0;
 
@@ -0,0 +1,184 @@
+use std::borrow::Cow;
+
+use crossterm_keybind::KeyBindTrait as _;
+use ratatui::layout::Constraint;
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, BorderType, Padding, Paragraph, Wrap};
+
+use super::style::{FIELD_KEY_STYLE, FIELD_VALUE_STYLE};
+use super::util::{Field, FieldKey, fields_into_lines};
+use crate::keybindings::KeyAction;
+use crate::parser::Node;
+use crate::tui::util::Unit;
+
+const POPUP_PADDING: Padding = Padding {
+    left: 2,
+    right: 2,
+    top: 0,
+    bottom: 0,
+};
+
+/// Displayed instead of the values that weren't recorded in the image (e.g. for directories that don't have their own entry).
+const MISSING_VALUE_PLACEHOLDER: &str = "<not recorded>";
+
+/// Fields that are shown in the [NodeDetailsPopup].
+#[derive(Debug, Clone, Copy)]
+enum NodeDetailsField {
+    Path,
+    Status,
+    Size,
+    Kind,
+    Mode,
+    Owner,
+    Modified,
+    LinkTarget,
+}
+
+impl FieldKey for NodeDetailsField {
+    fn name(&self) -> &'static str {
+        match self {
+            NodeDetailsField::Path => "Path",
+            NodeDetailsField::Status => "Status",
+            NodeDetailsField::Size => "Size",
+            NodeDetailsField::Kind => "Kind",
+            NodeDetailsField::Mode => "Mode",
+            NodeDetailsField::Owner => "Owner",
+            NodeDetailsField::Modified => "Modified",
+            NodeDetailsField::LinkTarget => "Link Target",
+        }
+    }
+}
+
+/// A popup that displays all known details about the node that is currently selected in the layer inspector pane.
+pub struct NodeDetailsPopup<'a> {
+    /// Full path to the node
+    path: Cow<'a, str>,
+    /// The node itself
+    node: &'a Node,
+}
+
+impl<'a> NodeDetailsPopup<'a> {
+    pub fn new(path: Cow<'a, str>, node: &'a Node) -> Self {
+        NodeDetailsPopup { path, node }
+    }
+
+    /// Returns a widget that can be rendered inside the layer inspector pane and its vertical and horizontal size constraints.
+    pub fn render_with_layout_constraints(
+        self,
+    ) -> (Paragraph<'a>, Constraint, Constraint) {
+        let block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .padding(POPUP_PADDING)
+            .title(Line::from("  Node Details  ").centered())
+            .title_bottom(Line::from(Self::keybindings()).centered());
+
+        let fields = self.fields();
+        // Two rows are taken by the block borders
+        let height = fields.len() as u16 + 2;
+        let lines = fields_into_lines(
+            fields,
+            FIELD_KEY_STYLE,
+            FIELD_VALUE_STYLE,
+            |_| Style::default(),
+        );
+
+        (
+            Paragraph::new(Text::from(lines))
+                .wrap(Wrap { trim: false })
+                .block(block),
+            Constraint::Length(height),
+            Constraint::Percentage(70),
+        )
+    }
+
+    /// Returns all fields that should be displayed for the node.
+    fn fields(&self) -> Vec<Field<'a, NodeDetailsField>> {
+        let inner = &self.node.inner;
+
+        let status = if inner.is_deleted() {
+            "deleted"
+        } else if inner.is_modified() {
+            "modified"
+        } else {
+            "added"
+        };
+        // Deleted nodes don't take any space, so show how much space was taken by the node before it was deleted
+        let (size, unit) = Unit::bytes_to_human_readable_units(
+            inner.deleted_size().unwrap_or_else(|| inner.size()),
+        );
+
+        let mut fields = vec![
+            (NodeDetailsField::Path, self.path.clone()),
+            (NodeDetailsField::Status, status.into()),
+            (
+                NodeDetailsField::Size,
+                format!("{:.1} {}", size, unit.human_readable()).into(),
+            ),
+            (NodeDetailsField::Kind, inner.kind().name().into()),
+        ];
+
+        if let Some(metadata) = inner.metadata() {
+            let mut mode = format!(
+                "{} ({:04o})",
+                metadata.permissions(inner.kind()),
+                metadata.mode
+            );
+            if metadata.is_setuid_or_setgid() {
+                mode.push_str(" [setuid/setgid]");
+            }
+
+            fields.extend([
+                (NodeDetailsField::Mode, mode.into()),
+                (
+                    NodeDetailsField::Owner,
+                    format!(
+                        "{} (uid={}, gid={})",
+                        metadata.owner(),
+                        metadata.uid,
+                        metadata.gid
+                    )
+                    .into(),
+                ),
+                (
+                    NodeDetailsField::Modified,
+                    format!("{} UTC", metadata.modified_at()).into(),
+                ),
+            ]);
+        } else {
+            fields.extend(
+                [
+                    NodeDetailsField::Mode,
+                    NodeDetailsField::Owner,
+                    NodeDetailsField::Modified,
+                ]
+                .map(|field| (field, MISSING_VALUE_PLACEHOLDER.into())),
+            );
+        }
+
+        if let Some(link) = inner.get_link() {
+            fields.push((
+                NodeDetailsField::LinkTarget,
+                link.display().to_string().into(),
+            ));
+        }
+
+        fields
+    }
+
+    /// Returns a [Vec] of keybindings to be rendered at the bottom of the popup.
+    fn keybindings() -> Vec<Span<'static>> {
+        vec![
+            // Padding
+            Span::from("  "),
+            Span::styled(
+                KeyAction::ToggleNodeDetails.key_bindings_display(),
+                Style::new().bold(),
+            ),
+            Span::styled(" - ", Style::new().add_modifier(Modifier::DIM)),
+            Span::styled("close", Style::new().add_modifier(Modifier::DIM)),
+            // Padding
+            Span::from("  "),
+        ]
+    }
+}
@@ -11,6 +11,11 @@ pub const ACTIVE_FIELD_STYLE: Style =
     Style::new().add_modifier(Modifier::UNDERLINED);
 /// A delimiter between the field's name and value.
 pub const FIELD_VALUE_DELIMITER: &str = ": ";
+/// A style for warnings that are displayed inside panes.
+pub const WARNING_STYLE: Style = Style::new().fg(Color::Yellow);
+/// A style that is applied on top of other styles to highlight layers that failed verification.
+pub const FAILED_VERIFICATION_STYLE: Style =
+    Style::new().fg(Color::Red).add_modifier(Modifier::BOLD);
 
 pub struct LayerInspectorNodeStyles;
 